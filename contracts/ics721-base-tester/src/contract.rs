@@ -32,7 +32,7 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    env: Env,
+    _env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -51,7 +51,7 @@ pub fn execute(
             recipient,
             channel_id,
             memo,
-        } => execute_send_nft(env, cw721, ics721, token_id, recipient, channel_id, memo),
+        } => execute_send_nft(cw721, ics721, token_id, recipient, channel_id, memo),
         ExecuteMsg::SendPacket {
             channel_id,
             timeout,
@@ -206,7 +206,6 @@ mod receive_callbacks {
 }
 
 fn execute_send_nft(
-    env: Env,
     cw721: String,
     ics721: String,
     token_id: String,
@@ -223,7 +222,9 @@ fn execute_send_nft(
             msg: to_json_binary(&IbcOutgoingMsg {
                 receiver: recipient,
                 channel_id,
-                timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(1000)),
+                // ics721 fills in timestamp relative to block time
+                timeout: None,
+                relative_timeout_seconds: Some(1000),
                 memo,
             })?,
         })?,
@@ -5,6 +5,7 @@ use cosmwasm_std::{
     IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketAckMsg, IbcPacketReceiveMsg,
     IbcPacketTimeoutMsg, IbcReceiveResponse,
 };
+use ics721_types::ack::{ack_fail, ack_success, try_get_ack_error};
 
 use crate::{
     error::Never,
@@ -53,11 +54,8 @@ pub fn ibc_packet_receive(
     _msg: IbcPacketReceiveMsg,
 ) -> Result<IbcReceiveResponse, Never> {
     match ACK_MODE.load(deps.storage).unwrap() {
-        AckMode::Error => Ok(IbcReceiveResponse::default()
-            .set_ack(ics721::ibc_helpers::ack_fail("error".to_string()))),
-        AckMode::Success => {
-            Ok(IbcReceiveResponse::default().set_ack(ics721::ibc_helpers::ack_success()))
-        }
+        AckMode::Error => Ok(IbcReceiveResponse::default().set_ack(ack_fail("error".to_string()))),
+        AckMode::Success => Ok(IbcReceiveResponse::default().set_ack(ack_success())),
     }
 }
 
@@ -67,7 +65,7 @@ pub fn ibc_packet_ack(
     _env: Env,
     ack: IbcPacketAckMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
-    let err = ics721::ibc_helpers::try_get_ack_error(&ack.acknowledgement);
+    let err = try_get_ack_error(&ack.acknowledgement);
     LAST_ACK.save(
         deps.storage,
         &err.map(|_| AckMode::Error).unwrap_or(AckMode::Success),
@@ -1,39 +1,31 @@
-use cosmwasm_std::{from_json, to_json_binary, Addr, Binary, Deps, DepsMut, Env, StdResult};
-use ics721::{execute::Ics721Execute, state::CollectionData, utils::get_collection_data};
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Empty, Env, StdResult, WasmMsg,
+};
+use ics721::{
+    execute::Ics721Execute,
+    state::CollectionData,
+    utils::{convert_wallet_address, query_collection_data},
+};
 use ics721_types::token_types::Class;
 
+use sg721::{RoyaltyInfoResponse, UpdateCollectionInfoMsg};
 use sg721_base::msg::{CollectionInfoResponse, QueryMsg};
 
 use crate::state::{SgCollectionData, SgIcs721Contract, STARGAZE_ICON_PLACEHOLDER};
 
+/// Max description length (in bytes) accepted by sg721-base.
+const MAX_DESCRIPTION_LENGTH: usize = 512;
+
 impl Ics721Execute for SgIcs721Contract {
     type ClassData = SgCollectionData;
 
     /// sg-ics721 sends custom SgCollectionData, basically it extends ics721-base::state::CollectionData with additional collection_info.
     fn get_class_data(&self, deps: &DepsMut, sender: &Addr) -> StdResult<Option<Self::ClassData>> {
-        let CollectionData {
-            owner,
-            contract_info,
-            name,
-            symbol,
-            num_tokens,
-        } = get_collection_data(deps, sender)?;
-        let collection_info: CollectionInfoResponse = deps
-            .querier
-            .query_wasm_smart(sender, &QueryMsg::CollectionInfo {})?;
-
-        Ok(Some(SgCollectionData {
-            owner,
-            contract_info,
-            name,
-            symbol,
-            num_tokens,
-            collection_info: Some(collection_info),
-        }))
+        get_sg_collection_data(deps.as_ref(), sender).map(Option::Some)
     }
 
     fn init_msg(&self, deps: Deps, env: &Env, class: &Class) -> StdResult<Binary> {
-        // ics721 creator is used, in case no source owner in class data is provided (e.g. due to nft-transfer module).
+        // ics721 creator receives royalties, in case source payment address can't be converted.
         let ics721_contract_info = deps
             .querier
             .query_wasm_contract_info(env.contract.address.to_string())?;
@@ -45,8 +37,8 @@ impl Ics721Execute for SgIcs721Contract {
             collection_info: sg721::CollectionInfo {
                 // source owner could be: 1. regular wallet, 2. contract, or 3. multisig
                 // bech32 calculation for 2. and 3. leads to unknown address
-                // therefore, we use ics721 creator as owner
-                creator: ics721_contract_info.creator,
+                // therefore, we use ics721 as owner, allowing it to update collection info
+                creator: env.contract.address.to_string(),
                 description: "".to_string(),
                 // use Stargaze icon as placeholder
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
@@ -57,16 +49,152 @@ impl Ics721Execute for SgIcs721Contract {
             },
         };
 
-        // use collection data for setting name and symbol
-        let collection_data = class
+        // use sg collection data for setting name, symbol, and collection info,
+        // otherwise use collection data for setting name and symbol
+        let sg_collection_data = class
             .data
             .clone()
-            .and_then(|binary| from_json::<CollectionData>(binary).ok());
-        if let Some(collection_data) = collection_data {
-            instantiate_msg.name = collection_data.name;
-            instantiate_msg.symbol = collection_data.symbol;
+            .and_then(|binary| from_json::<SgCollectionData>(binary).ok());
+        if let Some(sg_collection_data) = sg_collection_data {
+            instantiate_msg.name = sg_collection_data.name;
+            instantiate_msg.symbol = sg_collection_data.symbol;
+            if let Some(collection_info) = sg_collection_data.collection_info {
+                let target = &mut instantiate_msg.collection_info;
+                target.description = truncate_description(collection_info.description);
+                if let Some(image) = sanitize_url(collection_info.image) {
+                    target.image = image;
+                }
+                target.external_link = collection_info.external_link.and_then(sanitize_url);
+                target.explicit_content = collection_info.explicit_content;
+                // start trading time is specific to source chain, so it is not carried over
+                target.royalty_info = collection_info
+                    .royalty_info
+                    .filter(|royalty_info| royalty_info.share <= Decimal::one())
+                    .map(|royalty_info| RoyaltyInfoResponse {
+                        payment_address: convert_wallet_address(
+                            deps,
+                            env,
+                            &royalty_info.payment_address,
+                        )
+                        // royalties of contracts and multisigs go to ics721 creator
+                        .unwrap_or_else(|| ics721_contract_info.creator.clone()),
+                        share: royalty_info.share,
+                    });
+            }
+        } else {
+            let collection_data = class
+                .data
+                .clone()
+                .and_then(|binary| from_json::<CollectionData>(binary).ok());
+            if let Some(collection_data) = collection_data {
+                instantiate_msg.name = collection_data.name;
+                instantiate_msg.symbol = collection_data.symbol;
+            }
         }
 
         to_json_binary(&instantiate_msg)
     }
+
+    /// Updates collection info of the voucher collection with the one
+    /// of the changed class. Royalty info, name and symbol are kept.
+    /// sg721-base only allows the collection creator to update it, so
+    /// updates are skipped in case ics721 is no longer creator.
+    fn update_class_msg(
+        &self,
+        deps: Deps,
+        env: &Env,
+        nft_contract: &Addr,
+        _old_class: &Class,
+        new_class: &Class,
+    ) -> StdResult<Option<WasmMsg>> {
+        let collection_info = match new_class
+            .data
+            .clone()
+            .and_then(|binary| from_json::<SgCollectionData>(binary).ok())
+            .and_then(|sg_collection_data| sg_collection_data.collection_info)
+        {
+            Some(collection_info) => collection_info,
+            None => return Ok(None),
+        };
+        let current: CollectionInfoResponse = deps
+            .querier
+            .query_wasm_smart(nft_contract, &QueryMsg::CollectionInfo {})?;
+        if current.creator != env.contract.address.as_str() {
+            return Ok(None);
+        }
+
+        let description = truncate_description(collection_info.description);
+        let image = sanitize_url(collection_info.image).unwrap_or_else(|| current.image.clone());
+        let external_link = collection_info.external_link.and_then(sanitize_url);
+        let explicit_content = collection_info.explicit_content;
+        if description == current.description
+            && image == current.image
+            && external_link == current.external_link
+            && explicit_content == current.explicit_content
+        {
+            return Ok(None);
+        }
+        let msg = sg721::ExecuteMsg::<Option<Empty>, Empty>::UpdateCollectionInfo {
+            collection_info: UpdateCollectionInfoMsg {
+                description: Some(description),
+                image: Some(image),
+                external_link: Some(external_link),
+                explicit_content,
+                // royalty changes are rate limited by sg721-base
+                royalty_info: None,
+                creator: None,
+            },
+        };
+        Ok(Some(WasmMsg::Execute {
+            contract_addr: nft_contract.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        }))
+    }
+}
+
+/// Truncates description to the max length accepted by sg721-base.
+fn truncate_description(mut description: String) -> String {
+    if description.len() > MAX_DESCRIPTION_LENGTH {
+        let mut end = MAX_DESCRIPTION_LENGTH;
+        while !description.is_char_boundary(end) {
+            end -= 1;
+        }
+        description.truncate(end);
+    }
+    description
+}
+
+/// Returns URL only in case it has a scheme (e.g. `ipfs://` or
+/// `https://`) and no whitespace, since sg721-base rejects invalid URLs.
+fn sanitize_url(url: String) -> Option<String> {
+    let (scheme, path) = url.split_once("://")?;
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+    let valid_path = !path.is_empty() && !url.chars().any(|c| c.is_whitespace() || c.is_control());
+    (valid_scheme && valid_path).then_some(url)
+}
+
+pub(crate) fn get_sg_collection_data(deps: Deps, sender: &Addr) -> StdResult<SgCollectionData> {
+    let CollectionData {
+        owner,
+        contract_info,
+        name,
+        symbol,
+        num_tokens,
+    } = query_collection_data(deps, sender)?;
+    let collection_info: CollectionInfoResponse = deps
+        .querier
+        .query_wasm_smart(sender, &QueryMsg::CollectionInfo {})?;
+
+    Ok(SgCollectionData {
+        owner,
+        contract_info,
+        name,
+        symbol,
+        num_tokens,
+        collection_info: Some(collection_info),
+    })
 }
@@ -1,5 +1,10 @@
+use cosmwasm_std::{to_json_binary, Addr, Binary, Deps, StdResult};
 use ics721::query::Ics721Query;
 
-use crate::state::SgIcs721Contract;
+use crate::{execute::get_sg_collection_data, state::SgIcs721Contract};
 
-impl Ics721Query for SgIcs721Contract {}
+impl Ics721Query for SgIcs721Contract {
+    fn get_preview_class_data(&self, deps: Deps, nft_contract: &Addr) -> StdResult<Option<Binary>> {
+        to_json_binary(&get_sg_collection_data(deps, nft_contract)?).map(Some)
+    }
+}
@@ -1,17 +1,15 @@
-use anyhow::Result;
-use bech32::{decode, encode, FromBase32, ToBase32, Variant};
+use bech32::{encode, ToBase32, Variant};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    from_json, instantiate2_address, to_json_binary, Addr, Api, Binary, CanonicalAddr, Deps,
-    DepsMut, Empty, Env, GovMsg, IbcTimeout, IbcTimeoutBlock, MemoryStorage, MessageInfo,
-    RecoverPubkeyError, Reply, Response, StdError, StdResult, Storage, VerificationError, WasmMsg,
+    from_json, to_json_binary, Addr, Api, Binary, Deps, DepsMut, Empty, Env, GovMsg, IbcTimeout,
+    IbcTimeoutBlock, MemoryStorage, MessageInfo, Reply, Response, StdResult, Storage, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw721_base::msg::QueryMsg as Cw721QueryMsg;
 use cw_cii::{Admin, ContractInstantiateInfo};
 use cw_multi_test::{
-    AddressGenerator, App, AppBuilder, BankKeeper, Contract, ContractWrapper, DistributionKeeper,
-    Executor, FailingModule, IbcAcceptingModule, Router, StakeKeeper, StargateFailing, WasmKeeper,
+    App, AppBuilder, BankKeeper, Contract, ContractWrapper, DistributionKeeper, Executor,
+    FailingModule, IbcAcceptingModule, Router, StakeKeeper, StargateFailing, WasmKeeper,
 };
 use cw_pause_once::PauseError;
 use ics721::{
@@ -22,13 +20,13 @@ use ics721::{
     state::CollectionData,
     token_types::VoucherCreation,
 };
+use ics721_testing::mock::{MockAddressGenerator, MockApiBech32};
 use ics721_types::{
     ibc_types::{IbcOutgoingMsg, IbcOutgoingProxyMsg},
     token_types::{Class, ClassId, Token, TokenId},
 };
 use sg721::InstantiateMsg as Sg721InstantiateMsg;
 use sg721_base::msg::{CollectionInfoResponse, QueryMsg as Sg721QueryMsg};
-use sha2::{digest::Update, Digest, Sha256};
 
 use crate::{
     state::{SgCollectionData, STARGAZE_ICON_PLACEHOLDER},
@@ -113,150 +111,6 @@ fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, Contrac
 
 fn no_init(_router: &mut MockRouter, _api: &dyn Api, _storage: &mut dyn Storage) {}
 
-#[derive(Default)]
-pub struct MockAddressGenerator;
-
-impl AddressGenerator for MockAddressGenerator {
-    fn contract_address(
-        &self,
-        api: &dyn Api,
-        _storage: &mut dyn Storage,
-        code_id: u64,
-        instance_id: u64,
-    ) -> Result<Addr> {
-        let canonical_addr = Self::instantiate_address(code_id, instance_id);
-        Ok(Addr::unchecked(api.addr_humanize(&canonical_addr)?))
-    }
-
-    fn predictable_contract_address(
-        &self,
-        api: &dyn Api,
-        _storage: &mut dyn Storage,
-        _code_id: u64,
-        _instance_id: u64,
-        checksum: &[u8],
-        creator: &CanonicalAddr,
-        salt: &[u8],
-    ) -> Result<Addr> {
-        let canonical_addr = instantiate2_address(checksum, creator, salt)?;
-        Ok(Addr::unchecked(api.addr_humanize(&canonical_addr)?))
-    }
-}
-
-impl MockAddressGenerator {
-    // non-predictable contract address generator, see `BuildContractAddressClassic`
-    // implementation in wasmd: https://github.com/CosmWasm/wasmd/blob/main/x/wasm/keeper/addresses.go#L35-L42
-    fn instantiate_address(code_id: u64, instance_id: u64) -> CanonicalAddr {
-        let mut key = Vec::<u8>::new();
-        key.extend_from_slice(b"wasm\0");
-        key.extend_from_slice(&code_id.to_be_bytes());
-        key.extend_from_slice(&instance_id.to_be_bytes());
-        let module = Sha256::digest("module".as_bytes());
-        Sha256::new()
-            .chain(module)
-            .chain(key)
-            .finalize()
-            .to_vec()
-            .into()
-    }
-}
-pub struct MockApiBech32 {
-    prefix: &'static str,
-}
-
-impl MockApiBech32 {
-    pub fn new(prefix: &'static str) -> Self {
-        Self { prefix }
-    }
-}
-
-impl Api for MockApiBech32 {
-    fn addr_validate(&self, input: &str) -> StdResult<Addr> {
-        let canonical = self.addr_canonicalize(input)?;
-        let normalized = self.addr_humanize(&canonical)?;
-        if input != normalized {
-            Err(StdError::generic_err(
-                "Invalid input: address not normalized",
-            ))
-        } else {
-            Ok(Addr::unchecked(input))
-        }
-    }
-
-    fn addr_canonicalize(&self, input: &str) -> StdResult<CanonicalAddr> {
-        if let Ok((prefix, decoded, Variant::Bech32)) = decode(input) {
-            if prefix == self.prefix {
-                if let Ok(bytes) = Vec::<u8>::from_base32(&decoded) {
-                    return Ok(bytes.into());
-                }
-            }
-        }
-        Err(StdError::generic_err(format!("Invalid input: {input}")))
-    }
-
-    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
-        if let Ok(encoded) = encode(
-            self.prefix,
-            canonical.as_slice().to_base32(),
-            Variant::Bech32,
-        ) {
-            Ok(Addr::unchecked(encoded))
-        } else {
-            Err(StdError::generic_err("Invalid canonical address"))
-        }
-    }
-
-    fn secp256k1_verify(
-        &self,
-        _message_hash: &[u8],
-        _signature: &[u8],
-        _public_key: &[u8],
-    ) -> Result<bool, VerificationError> {
-        unimplemented!()
-    }
-
-    fn secp256k1_recover_pubkey(
-        &self,
-        _message_hash: &[u8],
-        _signature: &[u8],
-        _recovery_param: u8,
-    ) -> Result<Vec<u8>, RecoverPubkeyError> {
-        unimplemented!()
-    }
-
-    fn ed25519_verify(
-        &self,
-        _message: &[u8],
-        _signature: &[u8],
-        _public_key: &[u8],
-    ) -> Result<bool, VerificationError> {
-        unimplemented!()
-    }
-
-    fn ed25519_batch_verify(
-        &self,
-        _messages: &[&[u8]],
-        _signatures: &[&[u8]],
-        _public_keys: &[&[u8]],
-    ) -> Result<bool, VerificationError> {
-        unimplemented!()
-    }
-
-    fn debug(&self, _message: &str) {
-        unimplemented!()
-    }
-}
-
-impl MockApiBech32 {
-    pub fn addr_make(&self, input: &str) -> Addr {
-        let digest = Sha256::digest(input).to_vec();
-        match encode(self.prefix, digest.to_base32(), Variant::Bech32) {
-            Ok(address) => Addr::unchecked(address),
-            Err(reason) => panic!("Generating address failed with reason: {reason}"),
-        }
-    }
-}
-
 #[cw_serde]
 pub struct CustomClassData {
     // even there is collection name, but it doesn't apply to CollectionData type
@@ -647,6 +501,7 @@ fn test_do_instantiate_and_mint_weird_data() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -689,6 +544,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -736,8 +592,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is also creator of collection, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ics721 is creator of collection, since source owner may not exist on this chain
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -865,6 +721,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -912,8 +769,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator based on owner from collection in soure chain
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ics721 is creator, not owner from collection in source chain
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1039,6 +896,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1086,8 +944,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is creator of nft contract, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ics721 is creator of nft contract, since source owner may not exist on this chain
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1217,6 +1075,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1264,8 +1123,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is creator of nft contract, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ics721 is creator of nft contract, since source owner may not exist on this chain
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1395,6 +1254,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1442,8 +1302,8 @@ fn test_do_instantiate_and_mint() {
         assert_eq!(
             collection_info,
             CollectionInfoResponse {
-                // creator of ics721 contract is creator of nft contract, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ics721 is creator of nft contract, since source owner may not exist on this chain
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1567,6 +1427,7 @@ fn test_do_instantiate_and_mint_2_different_collections() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1597,6 +1458,7 @@ fn test_do_instantiate_and_mint_2_different_collections() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1674,8 +1536,8 @@ fn test_do_instantiate_and_mint_2_different_collections() {
         assert_eq!(
             collection_info_1,
             CollectionInfoResponse {
-                // creator of ics721 contract is also creator of collection, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ics721 is creator of collection, since source owner may not exist on this chain
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1687,8 +1549,8 @@ fn test_do_instantiate_and_mint_2_different_collections() {
         assert_eq!(
             collection_info_2,
             CollectionInfoResponse {
-                // creator of ics721 contract is also creator of collection, since no owner in ClassData provided
-                creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+                // ics721 is creator of collection, since source owner may not exist on this chain
+                creator: test.ics721.to_string(),
                 description: "".to_string(),
                 image: STARGAZE_ICON_PLACEHOLDER.to_string(),
                 external_link: None,
@@ -1878,6 +1740,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -1910,6 +1773,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -1923,7 +1787,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
     let nft_contract: Addr = test
         .app
         .wrap()
-        .query_wasm_smart(test.ics721, &QueryMsg::NftContract { class_id })
+        .query_wasm_smart(&test.ics721, &QueryMsg::NftContract { class_id })
         .unwrap();
 
     // check collection info is properly set
@@ -1935,7 +1799,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
     assert_eq!(
         collection_info,
         CollectionInfoResponse {
-            creator: test.app.api().addr_make(ICS721_CREATOR).to_string(),
+            creator: test.ics721.to_string(),
             description: "".to_string(),
             image: STARGAZE_ICON_PLACEHOLDER.to_string(),
             external_link: None,
@@ -2004,6 +1868,7 @@ fn test_do_instantiate_and_mint_permissions() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -2022,10 +1887,11 @@ fn test_no_proxy_unknown_msg() {
         msg: to_json_binary(&IbcOutgoingMsg {
             receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
             channel_id: "channel-0".to_string(),
-            timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+            timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                 revision: 0,
                 height: 10,
-            }),
+            })),
+            relative_timeout_seconds: None,
             memo: None,
         })
         .unwrap(),
@@ -2060,10 +1926,11 @@ fn test_no_proxy_unauthorized() {
         msg: to_json_binary(&IbcOutgoingMsg {
             receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
             channel_id: "channel-0".to_string(),
-            timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+            timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                 revision: 0,
                 height: 10,
-            }),
+            })),
+            relative_timeout_seconds: None,
             memo: None,
         })
         .unwrap(),
@@ -2166,10 +2033,11 @@ fn test_proxy_authorized() {
                     msg: to_json_binary(&IbcOutgoingMsg {
                         receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
                         channel_id: "channel-0".to_string(),
-                        timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                        timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                             revision: 0,
                             height: 10,
-                        }),
+                        })),
+                        relative_timeout_seconds: None,
                         memo: None,
                     })
                     .unwrap(),
@@ -2200,10 +2068,11 @@ fn test_receive_nft() {
                 msg: to_json_binary(&IbcOutgoingMsg {
                     receiver: "mr-t".to_string(),
                     channel_id: "channel-0".to_string(),
-                    timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                    timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                         revision: 0,
                         height: 10,
-                    }),
+                    })),
+                    relative_timeout_seconds: None,
                     memo: None,
                 })
                 .unwrap(),
@@ -2259,6 +2128,220 @@ fn test_receive_nft() {
     );
 }
 
+#[test]
+fn test_do_instantiate_with_sg_collection_data() {
+    let mut test = Test::new(false, false, None, None, sg721_base_contract());
+    // wallet on source chain is converted to wallet on target chain
+    let wallet_data = [7u8; 20].to_base32();
+    let source_wallet = encode("juno", wallet_data.clone(), Variant::Bech32).unwrap();
+    let target_wallet = encode(BECH32_PREFIX_HRP, wallet_data, Variant::Bech32).unwrap();
+    // contract can't be converted, ics721 creator is used instead
+    let source_contract = test.app.api().addr_make("royalty-contract").to_string();
+    let source_creator = test.app.api().addr_make("source-creator").to_string();
+    let collection_info = |payment_address: String| CollectionInfoResponse {
+        creator: source_creator.clone(),
+        description: "x".repeat(600),
+        image: "ipfs://bafkreie5vwrm5zts4wiq6ebtopmztgl5qzyl4uszyllgwpaizyc5w2uycm/image.png"
+            .to_string(),
+        external_link: Some("not a link".to_string()),
+        explicit_content: Some(true),
+        start_trading_time: Some(cosmwasm_std::Timestamp::from_seconds(1)),
+        royalty_info: Some(sg721::RoyaltyInfoResponse {
+            payment_address,
+            share: cosmwasm_std::Decimal::percent(5),
+        }),
+    };
+
+    for (index, (payment_address, expected_payment_address)) in [
+        (source_wallet, target_wallet),
+        (
+            source_contract,
+            test.app.api().addr_make(ICS721_CREATOR).to_string(),
+        ),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let class_id = format!(
+            "wasm.{}/{}/{}",
+            test.ics721,
+            CHANNEL_TARGET_CHAIN,
+            test.app
+                .api()
+                .addr_make(&format!("{COLLECTION_CONTRACT_SOURCE_CHAIN}-{index}"))
+        );
+        let source_collection_info = collection_info(payment_address);
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                    receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                    create: VoucherCreation {
+                        class: Class {
+                            id: ClassId::new(class_id.clone()),
+                            uri: None,
+                            data: Some(
+                                to_json_binary(&SgCollectionData {
+                                    owner: None,
+                                    contract_info: None,
+                                    name: "ark".to_string(),
+                                    symbol: "protocol".to_string(),
+                                    num_tokens: Some(1),
+                                    collection_info: Some(source_collection_info.clone()),
+                                })
+                                .unwrap(),
+                            ),
+                        },
+                        tokens: vec![Token {
+                            id: TokenId::new("1"),
+                            uri: Some("https://moonphase.is/image.svg".to_string()),
+                            data: None,
+                        }],
+                    },
+                    transfer: None,
+                }),
+                &[],
+            )
+            .unwrap();
+        let nft_contract: Addr = test
+            .app
+            .wrap()
+            .query_wasm_smart(
+                test.ics721.clone(),
+                &QueryMsg::NftContract {
+                    class_id: class_id.to_string(),
+                },
+            )
+            .unwrap();
+        let collection_info: CollectionInfoResponse = test
+            .app
+            .wrap()
+            .query_wasm_smart(nft_contract, &Sg721QueryMsg::CollectionInfo {})
+            .unwrap();
+        assert_eq!(
+            collection_info,
+            CollectionInfoResponse {
+                // ics721 is creator, source creator may not exist on this chain
+                creator: test.ics721.to_string(),
+                // truncated to max length
+                description: "x".repeat(512),
+                image: source_collection_info.image,
+                // invalid link is dropped
+                external_link: None,
+                explicit_content: Some(true),
+                start_trading_time: None,
+                royalty_info: Some(sg721::RoyaltyInfoResponse {
+                    payment_address: expected_payment_address,
+                    share: cosmwasm_std::Decimal::percent(5),
+                }),
+            }
+        );
+    }
+}
+
+#[test]
+fn test_update_class_collection_info() {
+    let mut test = Test::new(false, false, None, None, sg721_base_contract());
+    let class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    let create_vouchers = |test: &mut Test, description: &str, token_id: &str| {
+        let collection_info = CollectionInfoResponse {
+            creator: test.app.api().addr_make("source-creator").to_string(),
+            description: description.to_string(),
+            image: STARGAZE_ICON_PLACEHOLDER.to_string(),
+            external_link: None,
+            explicit_content: None,
+            start_trading_time: None,
+            royalty_info: None,
+        };
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                    receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                    create: VoucherCreation {
+                        class: Class {
+                            id: class_id.clone(),
+                            uri: None,
+                            data: Some(
+                                to_json_binary(&SgCollectionData {
+                                    owner: None,
+                                    contract_info: None,
+                                    name: "ark".to_string(),
+                                    symbol: "protocol".to_string(),
+                                    num_tokens: Some(1),
+                                    collection_info: Some(collection_info),
+                                })
+                                .unwrap(),
+                            ),
+                        },
+                        tokens: vec![Token {
+                            id: TokenId::new(token_id),
+                            uri: None,
+                            data: None,
+                        }],
+                    },
+                    transfer: None,
+                }),
+                &[],
+            )
+            .unwrap();
+    };
+    let query_description = |test: &Test| {
+        let nft_contract: Addr = test
+            .app
+            .wrap()
+            .query_wasm_smart(
+                test.ics721.clone(),
+                &QueryMsg::NftContract {
+                    class_id: class_id.to_string(),
+                },
+            )
+            .unwrap();
+        let collection_info: CollectionInfoResponse = test
+            .app
+            .wrap()
+            .query_wasm_smart(nft_contract, &Sg721QueryMsg::CollectionInfo {})
+            .unwrap();
+        collection_info.description
+    };
+
+    create_vouchers(&mut test, "old", "1");
+    assert_eq!(query_description(&test), "old");
+
+    // ics721 is collection creator, so collection info is updated
+    create_vouchers(&mut test, "new", "2");
+    assert_eq!(query_description(&test), "new");
+
+    // once ics721 is no longer collection creator, collection info is kept
+    let (_, nft_contract) = test.query_nft_contracts()[0].clone();
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            nft_contract,
+            &sg721::ExecuteMsg::<Option<Empty>, Empty>::UpdateCollectionInfo {
+                collection_info: sg721::UpdateCollectionInfoMsg {
+                    description: None,
+                    image: None,
+                    external_link: None,
+                    explicit_content: None,
+                    royalty_info: None,
+                    creator: Some(test.app.api().addr_make(ICS721_CREATOR).to_string()),
+                },
+            },
+            &[],
+        )
+        .unwrap();
+    create_vouchers(&mut test, "newer", "3");
+    assert_eq!(query_description(&test), "new");
+}
+
 /// In case proxy for ICS721 is defined, ICS721 only accepts receival from proxy - not from nft contract!
 #[test]
 fn test_no_receive_with_proxy() {
@@ -2275,10 +2358,11 @@ fn test_no_receive_with_proxy() {
                 msg: to_json_binary(&IbcOutgoingMsg {
                     receiver: NFT_OWNER_TARGET_CHAIN.to_string(), // nft owner for other chain, on this chain ics721 is owner
                     channel_id: "channel-0".to_string(),
-                    timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                    timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                         revision: 0,
                         height: 10,
-                    }),
+                    })),
+                    relative_timeout_seconds: None,
                     memo: None,
                 })
                 .unwrap(),
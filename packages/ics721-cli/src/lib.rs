@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use bech32::{FromBase32, ToBase32, Variant};
+use cosmwasm_std::{from_json, to_json_binary, Binary, CanonicalAddr, HexBinary, IbcEndpoint};
+use ics721::{
+    helpers::get_instantiate2_canonical_address,
+    ibc_helpers::{get_endpoint_prefix, try_pop_source_prefix},
+};
+use ics721_types::{
+    ack::{AckResult, Ics721Ack},
+    ibc_types::{Ics721ControlPacketData, NonFungibleTokenPacketData},
+    token_types::ClassId,
+    types::{Ics721Callbacks, Ics721Memo},
+};
+use sha2::{Digest, Sha256};
+
+/// Reads CLI input either as raw JSON (starting with `{`) or as base64,
+/// the way packet and ack data is shown by explorers and relayers.
+pub fn parse_input(input: &str) -> Result<Binary> {
+    let input = input.trim();
+    if input.starts_with('{') {
+        Ok(Binary::from(input.as_bytes()))
+    } else {
+        Binary::from_base64(input).context("input is neither JSON nor base64")
+    }
+}
+
+/// A decoded ICS721 packet.
+#[derive(Debug, PartialEq)]
+pub enum DecodedPacket {
+    Transfer {
+        data: NonFungibleTokenPacketData,
+        /// Callbacks in the memo, if it holds a valid `Ics721Memo`.
+        callbacks: Option<Ics721Callbacks>,
+    },
+    Control(Ics721ControlPacketData),
+}
+
+/// Decodes and validates packet data, the same way the receiving
+/// contract does.
+pub fn decode_packet(data: &Binary) -> Result<DecodedPacket> {
+    if let Ok(control) = from_json::<Ics721ControlPacketData>(data) {
+        control.validate()?;
+        return Ok(DecodedPacket::Control(control));
+    }
+    let data: NonFungibleTokenPacketData = from_json(data).context("invalid ICS721 packet data")?;
+    data.validate()?;
+    let callbacks = data
+        .memo
+        .as_ref()
+        .and_then(|memo| Binary::from_base64(memo).ok())
+        .and_then(|memo| from_json::<Ics721Memo>(&memo).ok())
+        .and_then(|memo| memo.callbacks);
+    Ok(DecodedPacket::Transfer { data, callbacks })
+}
+
+/// Decodes ack data. Data not in ICS721 format is an error ack set by
+/// the SDK.
+pub fn decode_ack(data: &Binary) -> AckResult {
+    Ics721Ack::decode(data)
+}
+
+/// Local class ID on the receiving chain.
+#[derive(Debug, PartialEq)]
+pub struct LocalClassId {
+    pub class_id: ClassId,
+    /// True in case class ID is prefixed by the source endpoint, which
+    /// means NFTs are returning to their home chain.
+    pub is_returning: bool,
+}
+
+/// Computes the class ID a packet's class ID maps to on the receiving
+/// chain, the same way the receiving contract does.
+pub fn local_class_id(source: &IbcEndpoint, dest: &IbcEndpoint, class_id: &str) -> LocalClassId {
+    match try_pop_source_prefix(source, class_id) {
+        Some(local_class_id) => LocalClassId {
+            class_id: ClassId::new(local_class_id),
+            is_returning: true,
+        },
+        None => LocalClassId {
+            class_id: ClassId::new(format!("{}{}", get_endpoint_prefix(dest), class_id)),
+            is_returning: false,
+        },
+    }
+}
+
+/// Predicts the voucher collection address, instantiated by ICS721
+/// (`creator`) using instantiate2. Salt is the sha256 hash of the class
+/// ID. In case ICS721 has a `contract_addr_length`, the address is
+/// truncated to that length. The address uses the bech32 prefix of
+/// `creator`.
+pub fn voucher_address(
+    checksum: &str,
+    creator: &str,
+    class_id: &str,
+    contract_addr_length: Option<u32>,
+) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(class_id.as_bytes());
+    let salt = hasher.finalize().to_vec();
+
+    instantiate2_address(checksum, creator, &salt, contract_addr_length)
+}
+
+/// Computes the instantiate2 address of a contract instantiated by
+/// `creator` with the given salt, using the bech32 prefix of `creator`.
+pub fn instantiate2_address(
+    checksum: &str,
+    creator: &str,
+    salt: &[u8],
+    contract_addr_length: Option<u32>,
+) -> Result<String> {
+    let checksum = HexBinary::from_hex(checksum).context("checksum is not hex")?;
+    let (prefix, data, _) = bech32::decode(creator).context("creator is not bech32")?;
+    let creator = CanonicalAddr::from(Vec::<u8>::from_base32(&data)?);
+
+    let canonical =
+        get_instantiate2_canonical_address(&checksum, &creator, salt, contract_addr_length)?;
+    Ok(bech32::encode(
+        &prefix,
+        canonical.as_slice().to_base32(),
+        Variant::Bech32,
+    )?)
+}
+
+/// Builds a base64 encoded memo holding the given callbacks.
+pub fn build_memo(callbacks: Ics721Callbacks) -> Result<String> {
+    let memo = Ics721Memo {
+        callbacks: Some(callbacks),
+    };
+    Ok(to_json_binary(&memo)?.to_base64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(port_id: &str, channel_id: &str) -> IbcEndpoint {
+        IbcEndpoint {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_decode_packet_with_memo() {
+        let memo = build_memo(Ics721Callbacks {
+            ack_callback_data: Some(Binary::from(b"ack".to_vec())),
+            ack_callback_addr: None,
+            receive_callback_data: None,
+            receive_callback_addr: Some("receiver".to_string()),
+        })
+        .unwrap();
+        let data = NonFungibleTokenPacketData {
+            class_id: ClassId::new("class"),
+            class_uri: None,
+            class_data: None,
+            token_ids: vec![ics721_types::token_types::TokenId::new("1")],
+            token_uris: None,
+            token_data: None,
+            sender: "sender".to_string(),
+            receiver: "receiver".to_string(),
+            memo: Some(memo),
+        };
+        let input = to_json_binary(&data).unwrap().to_base64();
+
+        let DecodedPacket::Transfer { callbacks, .. } =
+            decode_packet(&parse_input(&input).unwrap()).unwrap()
+        else {
+            panic!("expected transfer packet");
+        };
+        let callbacks = callbacks.unwrap();
+        assert_eq!(
+            callbacks.ack_callback_data,
+            Some(Binary::from(b"ack".to_vec()))
+        );
+        assert_eq!(
+            callbacks.receive_callback_addr,
+            Some("receiver".to_string())
+        );
+
+        // invalid packet: no tokens
+        let input = r#"{"classId":"class","tokenIds":[],"sender":"a","receiver":"b"}"#;
+        assert!(decode_packet(&parse_input(input).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_local_class_id() {
+        let source = endpoint("wasm.source", "channel-0");
+        let dest = endpoint("wasm.dest", "channel-1");
+        assert_eq!(
+            local_class_id(&source, &dest, "class"),
+            LocalClassId {
+                class_id: ClassId::new("wasm.dest/channel-1/class"),
+                is_returning: false,
+            }
+        );
+        assert_eq!(
+            local_class_id(&source, &dest, "wasm.source/channel-0/class"),
+            LocalClassId {
+                class_id: ClassId::new("class"),
+                is_returning: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_instantiate2_address_known_answer() {
+        // test vector of cosmwasm-std `instantiate2_address`, salt is 0x61
+        let checksum = "13a1fc994cc6d1c81b746ee0c0ff6f90043875e0bf1d9be6b7d779fc978dc2a5";
+        let creator = HexBinary::from_hex("9999999999aaaaaaaaaabbbbbbbbbbcccccccccc").unwrap();
+        let creator =
+            bech32::encode("purple", creator.as_slice().to_base32(), Variant::Bech32).unwrap();
+        let expected =
+            HexBinary::from_hex("5e865d3e45ad3e961f77fd77d46543417ced44d924dc3e079b5415ff6775f847")
+                .unwrap();
+        assert_eq!(
+            instantiate2_address(checksum, &creator, b"a", None).unwrap(),
+            bech32::encode("purple", expected.as_slice().to_base32(), Variant::Bech32).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_voucher_address() {
+        let checksum = "13a1fc994cc6d1c81b746ee0c0ff6f90043875e0bf1d9be6b7d779fc978dc2a5";
+        let creator = bech32::encode("cosmos", [9u8; 32].to_base32(), Variant::Bech32).unwrap();
+        let address = voucher_address(checksum, &creator, "class", None).unwrap();
+        assert!(address.starts_with("cosmos1"));
+        // same class ID, same address
+        assert_eq!(
+            address,
+            voucher_address(checksum, &creator, "class", None).unwrap()
+        );
+        assert_ne!(
+            address,
+            voucher_address(checksum, &creator, "other", None).unwrap()
+        );
+
+        let truncated = voucher_address(checksum, &creator, "class", Some(20)).unwrap();
+        let (_, data, _) = bech32::decode(&truncated).unwrap();
+        assert_eq!(Vec::<u8>::from_base32(&data).unwrap().len(), 20);
+
+        assert!(voucher_address(checksum, &creator, "class", Some(33)).is_err());
+        assert!(voucher_address("not hex", &creator, "class", None).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use cosmwasm_std::{Binary, IbcEndpoint};
+use ics721_cli::{
+    build_memo, decode_ack, decode_packet, local_class_id, parse_input, voucher_address,
+    DecodedPacket,
+};
+use ics721_types::{ack::AckResult, types::Ics721Callbacks};
+use serde_json::to_string_pretty;
+
+/// Offline tooling for debugging ICS721 transfers. Packet and ack data
+/// may be passed as base64 or JSON.
+#[derive(Parser)]
+#[command(name = "ics721-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decodes and validates ICS721 packet data.
+    DecodePacket { data: String },
+    /// Decodes an ICS721 ack.
+    DecodeAck { data: String },
+    /// Computes the local class ID of a packet's class ID on the
+    /// receiving chain.
+    ClassId {
+        class_id: String,
+        #[arg(long)]
+        source_port: String,
+        #[arg(long)]
+        source_channel: String,
+        #[arg(long)]
+        dest_port: String,
+        #[arg(long)]
+        dest_channel: String,
+    },
+    /// Predicts the instantiate2 address of a voucher collection.
+    VoucherAddress {
+        class_id: String,
+        /// Hex encoded checksum of the cw721 code.
+        #[arg(long)]
+        checksum: String,
+        /// ICS721 contract address.
+        #[arg(long)]
+        creator: String,
+        /// `contract_addr_length` of ICS721, if set.
+        #[arg(long)]
+        contract_addr_length: Option<u32>,
+    },
+    /// Builds a base64 memo with callbacks. Callback data is base64.
+    Memo {
+        #[arg(long)]
+        ack_callback_data: Option<String>,
+        #[arg(long)]
+        ack_callback_addr: Option<String>,
+        #[arg(long)]
+        receive_callback_data: Option<String>,
+        #[arg(long)]
+        receive_callback_addr: Option<String>,
+    },
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::DecodePacket { data } => match decode_packet(&parse_input(&data)?)? {
+            DecodedPacket::Transfer { data, callbacks } => {
+                println!("{}", to_string_pretty(&data)?);
+                if let Some(callbacks) = callbacks {
+                    println!("memo callbacks: {}", to_string_pretty(&callbacks)?);
+                }
+            }
+            DecodedPacket::Control(control) => {
+                println!("control packet: {}", to_string_pretty(&control)?)
+            }
+        },
+        Command::DecodeAck { data } => match decode_ack(&parse_input(&data)?) {
+            AckResult::Success(payload) => println!("success, payload: {}", payload.to_base64()),
+            AckResult::Error(error) => println!("error: {error}"),
+            AckResult::SdkError(data) => println!("SDK error: {}", data.to_base64()),
+        },
+        Command::ClassId {
+            class_id,
+            source_port,
+            source_channel,
+            dest_port,
+            dest_channel,
+        } => {
+            let local = local_class_id(
+                &IbcEndpoint {
+                    port_id: source_port,
+                    channel_id: source_channel,
+                },
+                &IbcEndpoint {
+                    port_id: dest_port,
+                    channel_id: dest_channel,
+                },
+                &class_id,
+            );
+            println!("local class ID: {}", local.class_id);
+            println!("returning to home chain: {}", local.is_returning);
+        }
+        Command::VoucherAddress {
+            class_id,
+            checksum,
+            creator,
+            contract_addr_length,
+        } => println!(
+            "{}",
+            voucher_address(&checksum, &creator, &class_id, contract_addr_length)?
+        ),
+        Command::Memo {
+            ack_callback_data,
+            ack_callback_addr,
+            receive_callback_data,
+            receive_callback_addr,
+        } => {
+            let callbacks = Ics721Callbacks {
+                ack_callback_data: ack_callback_data.map(parse_base64).transpose()?,
+                ack_callback_addr,
+                receive_callback_data: receive_callback_data.map(parse_base64).transpose()?,
+                receive_callback_addr,
+            };
+            println!("{}", build_memo(callbacks)?);
+        }
+    }
+    Ok(())
+}
+
+fn parse_base64(data: String) -> Result<Binary> {
+    Ok(Binary::from_base64(&data)?)
+}
@@ -0,0 +1,308 @@
+use anyhow::Result as AnyResult;
+use cosmwasm_std::{to_json_binary, Addr, Empty, IbcEndpoint, MemoryStorage, StdResult};
+use cw721::{Cw721QueryMsg, OwnerOfResponse};
+use cw721_base::msg::{ExecuteMsg as Cw721ExecuteMsg, InstantiateMsg as Cw721InstantiateMsg};
+use cw_cii::{Admin, ContractInstantiateInfo};
+use cw_multi_test::{
+    next_block, App, AppBuilder, AppResponse, BankKeeper, Contract, ContractWrapper,
+    DistributionKeeper, Executor, FailingModule, StakeKeeper, WasmKeeper,
+};
+use ics721::msg::{InstantiateMsg, QueryMsg};
+use ics721_types::ibc_types::IbcOutgoingMsg;
+
+use crate::{
+    ibc::{ics721_contract, PacketCapturingModule},
+    mock::{MockAddressGenerator, MockApiBech32},
+};
+
+/// Timeout of outgoing transfers sent by `Chain::send_nft`.
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 3600;
+
+pub type Ics721App = App<
+    BankKeeper,
+    MockApiBech32,
+    MemoryStorage,
+    FailingModule<Empty, Empty, Empty>,
+    WasmKeeper<Empty, Empty>,
+    StakeKeeper,
+    DistributionKeeper,
+    PacketCapturingModule,
+>;
+
+#[derive(Clone, Debug)]
+pub struct ChainConfig {
+    pub bech32_prefix: &'static str,
+    /// Channel ID of ICS721 on this chain.
+    pub channel_id: String,
+    /// Instantiates ICS721 with a rate limiting outgoing proxy.
+    pub outgoing_proxy: bool,
+    /// Instantiates ICS721 with an incoming proxy, whitelisting
+    /// `channel_id`.
+    pub incoming_proxy: bool,
+}
+
+impl ChainConfig {
+    pub fn new(bech32_prefix: &'static str, channel_id: &str) -> Self {
+        Self {
+            bech32_prefix,
+            channel_id: channel_id.to_string(),
+            outgoing_proxy: false,
+            incoming_proxy: false,
+        }
+    }
+
+    pub fn with_outgoing_proxy(mut self) -> Self {
+        self.outgoing_proxy = true;
+        self
+    }
+
+    pub fn with_incoming_proxy(mut self) -> Self {
+        self.incoming_proxy = true;
+        self
+    }
+}
+
+/// A chain with ICS721 and cw721 code, connected to the other chain on
+/// `channel_id`.
+pub struct Chain {
+    pub app: Ics721App,
+    pub ics721: Addr,
+    pub ics721_code_id: u64,
+    pub cw721_code_id: u64,
+    pub channel_id: String,
+    pub outgoing_proxy: Option<Addr>,
+    pub incoming_proxy: Option<Addr>,
+}
+
+impl Chain {
+    pub fn new(config: ChainConfig) -> Self {
+        let mut app = AppBuilder::new()
+            .with_wasm::<WasmKeeper<Empty, Empty>>(
+                WasmKeeper::new().with_address_generator(MockAddressGenerator),
+            )
+            .with_ibc(PacketCapturingModule)
+            .with_api(MockApiBech32::new(config.bech32_prefix))
+            .build(|_, _, _| {});
+        let cw721_code_id = app.store_code(cw721_base_contract());
+        let ics721_code_id = app.store_code(ics721_contract());
+
+        let outgoing_proxy = match config.outgoing_proxy {
+            true => Some(ContractInstantiateInfo {
+                code_id: app.store_code(outgoing_proxy_contract()),
+                msg: to_json_binary(&cw_ics721_outgoing_proxy_rate_limit::msg::InstantiateMsg {
+                    rate_limit: cw_ics721_outgoing_proxy_rate_limit::Rate::PerBlock(10),
+                    origin: None,
+                })
+                .unwrap(),
+                admin: Some(Admin::Instantiator {}),
+                label: "outgoing proxy rate limit".to_string(),
+            }),
+            false => None,
+        };
+        let incoming_proxy = match config.incoming_proxy {
+            true => Some(ContractInstantiateInfo {
+                code_id: app.store_code(incoming_proxy_contract()),
+                msg: to_json_binary(&cw_ics721_incoming_proxy_base::msg::InstantiateMsg {
+                    origin: None,
+                    channels: Some(vec![config.channel_id.clone()]),
+                })
+                .unwrap(),
+                admin: Some(Admin::Instantiator {}),
+                label: "incoming proxy".to_string(),
+            }),
+            false => None,
+        };
+
+        let admin = app.api().addr_make("ics721-admin").to_string();
+        let ics721 = app
+            .instantiate_contract(
+                ics721_code_id,
+                app.api().addr_make("ics721-creator"),
+                &InstantiateMsg {
+                    cw721_base_code_id: cw721_code_id,
+                    incoming_proxy,
+                    outgoing_proxy,
+                    pauser: Some(admin.clone()),
+                    cw721_admin: Some(admin.clone()),
+                    contract_addr_length: None,
+                },
+                &[],
+                "ics721-base",
+                Some(admin),
+            )
+            .unwrap();
+        let outgoing_proxy = app
+            .wrap()
+            .query_wasm_smart(&ics721, &QueryMsg::OutgoingProxy {})
+            .unwrap();
+        let incoming_proxy = app
+            .wrap()
+            .query_wasm_smart(&ics721, &QueryMsg::IncomingProxy {})
+            .unwrap();
+
+        Self {
+            app,
+            ics721,
+            ics721_code_id,
+            cw721_code_id,
+            channel_id: config.channel_id,
+            outgoing_proxy,
+            incoming_proxy,
+        }
+    }
+
+    /// Returns an address using this chain's bech32 prefix.
+    pub fn addr_make(&self, input: &str) -> Addr {
+        self.app.api().addr_make(input)
+    }
+
+    pub fn endpoint(&self) -> IbcEndpoint {
+        IbcEndpoint {
+            port_id: format!("wasm.{}", self.ics721),
+            channel_id: self.channel_id.clone(),
+        }
+    }
+
+    pub fn next_block(&mut self) {
+        self.app.update_block(next_block);
+    }
+
+    /// Instantiates a cw721 collection, minted by `minter`.
+    pub fn instantiate_collection(&mut self, minter: &Addr) -> Addr {
+        self.app
+            .instantiate_contract(
+                self.cw721_code_id,
+                minter.clone(),
+                &Cw721InstantiateMsg {
+                    name: "name".to_string(),
+                    symbol: "symbol".to_string(),
+                    minter: Some(minter.to_string()),
+                    withdraw_address: None,
+                },
+                &[],
+                "cw721-base",
+                None,
+            )
+            .unwrap()
+    }
+
+    pub fn mint(&mut self, collection: &Addr, minter: &Addr, token_id: &str, owner: &Addr) {
+        self.app
+            .execute_contract(
+                minter.clone(),
+                collection.clone(),
+                &Cw721ExecuteMsg::<Empty, Empty>::Mint {
+                    token_id: token_id.to_string(),
+                    owner: owner.to_string(),
+                    token_uri: None,
+                    extension: Empty::default(),
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    /// Sends an NFT to `receiver` on the other chain, using the outgoing
+    /// proxy if any. The packet is relayed by `Ics721Harness`.
+    pub fn send_nft(
+        &mut self,
+        collection: &Addr,
+        token_id: &str,
+        owner: &Addr,
+        receiver: &str,
+        memo: Option<String>,
+    ) -> AnyResult<AppResponse> {
+        let contract = self
+            .outgoing_proxy
+            .clone()
+            .unwrap_or_else(|| self.ics721.clone());
+        self.app.execute_contract(
+            owner.clone(),
+            collection.clone(),
+            &Cw721ExecuteMsg::<Empty, Empty>::SendNft {
+                contract: contract.to_string(),
+                token_id: token_id.to_string(),
+                msg: to_json_binary(&IbcOutgoingMsg {
+                    receiver: receiver.to_string(),
+                    channel_id: self.channel_id.clone(),
+                    timeout: None,
+                    relative_timeout_seconds: Some(DEFAULT_TIMEOUT_SECONDS),
+                    memo,
+                })?,
+            },
+            &[],
+        )
+    }
+
+    /// Returns the voucher collection of a local class ID, if any.
+    pub fn nft_contract(&self, class_id: &str) -> Option<Addr> {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.ics721,
+                &QueryMsg::NftContract {
+                    class_id: class_id.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
+    pub fn owner_of(&self, collection: &Addr, token_id: &str) -> StdResult<String> {
+        let res: OwnerOfResponse = self.app.wrap().query_wasm_smart(
+            collection,
+            &Cw721QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                include_expired: None,
+            },
+        )?;
+        Ok(res.owner)
+    }
+
+    pub fn assert_owner(&self, collection: &Addr, token_id: &str, owner: &Addr) {
+        assert_eq!(
+            self.owner_of(collection, token_id).unwrap(),
+            owner.to_string(),
+            "unexpected owner of token {token_id} in {collection}"
+        );
+    }
+
+    /// Asserts the NFT is in custody of ICS721.
+    pub fn assert_escrowed(&self, collection: &Addr, token_id: &str) {
+        self.assert_owner(collection, token_id, &self.ics721);
+    }
+
+    /// Asserts the NFT doesn't exist, e.g. a burned voucher.
+    pub fn assert_not_exists(&self, collection: &Addr, token_id: &str) {
+        assert!(
+            self.owner_of(collection, token_id).is_err(),
+            "token {token_id} exists in {collection}"
+        );
+    }
+}
+
+pub fn cw721_base_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw721_base::entry::execute,
+        cw721_base::entry::instantiate,
+        cw721_base::entry::query,
+    );
+    Box::new(contract)
+}
+
+pub fn incoming_proxy_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw_ics721_incoming_proxy_base::contract::execute,
+        cw_ics721_incoming_proxy_base::contract::instantiate,
+        cw_ics721_incoming_proxy_base::contract::query,
+    );
+    Box::new(contract)
+}
+
+pub fn outgoing_proxy_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw_ics721_outgoing_proxy_rate_limit::contract::execute,
+        cw_ics721_outgoing_proxy_rate_limit::contract::instantiate,
+        cw_ics721_outgoing_proxy_rate_limit::contract::query,
+    );
+    Box::new(contract)
+}
@@ -0,0 +1,212 @@
+use anyhow::{Context, Result as AnyResult};
+use cosmwasm_std::{
+    Addr, IbcAcknowledgement, IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
+};
+use cw_multi_test::AppResponse;
+use ics721::ibc_helpers::get_endpoint_prefix;
+use ics721_types::ack::{ack_fail, AckResult, Ics721Ack};
+
+use crate::{
+    chain::{Chain, ChainConfig},
+    ibc::{IbcSudoMsg, PacketCapturingModule},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    A,
+    B,
+}
+
+impl Side {
+    pub fn other(self) -> Self {
+        match self {
+            Side::A => Side::B,
+            Side::B => Side::A,
+        }
+    }
+}
+
+/// A packet delivered by the relayer.
+#[derive(Debug)]
+pub struct RelayedPacket {
+    pub packet: IbcPacket,
+    /// Response of the receiving chain, `None` on timeout or in case
+    /// receiving failed.
+    pub receive_response: Option<AppResponse>,
+    /// Ack written by the receiving chain, `None` on timeout.
+    pub ack: Option<AckResult>,
+    /// Response of the sending chain handling the ack or timeout.
+    pub source_response: AppResponse,
+}
+
+/// Two chains with ICS721, connected by a channel, and an in-process
+/// relayer delivering packets between them.
+pub struct Ics721Harness {
+    pub a: Chain,
+    pub b: Chain,
+    next_sequence: u64,
+}
+
+impl Default for Ics721Harness {
+    fn default() -> Self {
+        Self::new(
+            ChainConfig::new("juno", "channel-0"),
+            ChainConfig::new("stars", "channel-1"),
+        )
+    }
+}
+
+impl Ics721Harness {
+    pub fn new(a: ChainConfig, b: ChainConfig) -> Self {
+        Self {
+            a: Chain::new(a),
+            b: Chain::new(b),
+            next_sequence: 1,
+        }
+    }
+
+    pub fn chain(&self, side: Side) -> &Chain {
+        match side {
+            Side::A => &self.a,
+            Side::B => &self.b,
+        }
+    }
+
+    pub fn chain_mut(&mut self, side: Side) -> &mut Chain {
+        match side {
+            Side::A => &mut self.a,
+            Side::B => &mut self.b,
+        }
+    }
+
+    /// Returns the class ID of vouchers on chain `to` for a class sent
+    /// from the other chain.
+    pub fn voucher_class_id(&self, to: Side, class_id: &str) -> String {
+        format!(
+            "{}{}",
+            get_endpoint_prefix(&self.chain(to).endpoint()),
+            class_id
+        )
+    }
+
+    /// Returns the voucher collection on chain `to` for a class sent from
+    /// the other chain, if any.
+    pub fn voucher_contract(&self, to: Side, class_id: &str) -> Option<Addr> {
+        self.chain(to)
+            .nft_contract(&self.voucher_class_id(to, class_id))
+    }
+
+    /// Returns the number of packets sent by ICS721 on chain `from` and
+    /// not relayed yet.
+    pub fn pending_packets(&self, from: Side) -> usize {
+        let chain = self.chain(from);
+        chain
+            .app
+            .read_module(|_, _, storage| PacketCapturingModule::sent_packets(storage))
+            .unwrap()
+            .iter()
+            .filter(|p| p.sender == chain.ics721 && p.channel_id == chain.channel_id)
+            .count()
+    }
+
+    /// Delivers the next packet sent by chain `from` to the other chain
+    /// and its ack back to chain `from`. Returns `None` if there is no
+    /// pending packet.
+    pub fn relay_next(&mut self, from: Side) -> AnyResult<Option<RelayedPacket>> {
+        let Some(packet) = self.take_packet(from)? else {
+            return Ok(None);
+        };
+
+        let dest = self.chain_mut(from.other());
+        let relayer = dest.addr_make("relayer");
+        let ics721 = dest.ics721.clone();
+        // like wasmd, a failing receive is acked with an error
+        let (receive_response, ack) = match dest.app.wasm_sudo(
+            ics721,
+            &IbcSudoMsg::Receive(IbcPacketReceiveMsg::new(packet.clone(), relayer)),
+        ) {
+            Ok(res) => {
+                let ack = res.data.clone().context("no ack written")?;
+                (Some(res), ack)
+            }
+            Err(err) => (None, ack_fail(err.to_string())),
+        };
+
+        let source = self.chain_mut(from);
+        let relayer = source.addr_make("relayer");
+        let ics721 = source.ics721.clone();
+        let source_response = source.app.wasm_sudo(
+            ics721,
+            &IbcSudoMsg::Ack(IbcPacketAckMsg::new(
+                IbcAcknowledgement::new(ack.clone()),
+                packet.clone(),
+                relayer,
+            )),
+        )?;
+
+        Ok(Some(RelayedPacket {
+            packet,
+            receive_response,
+            ack: Some(Ics721Ack::decode(&ack)),
+            source_response,
+        }))
+    }
+
+    /// Relays packets in both directions until none are pending.
+    pub fn relay_all(&mut self) -> AnyResult<Vec<RelayedPacket>> {
+        let mut relayed = vec![];
+        loop {
+            let next = match self.relay_next(Side::A)? {
+                Some(packet) => Some(packet),
+                None => self.relay_next(Side::B)?,
+            };
+            match next {
+                Some(packet) => relayed.push(packet),
+                None => return Ok(relayed),
+            }
+        }
+    }
+
+    /// Times out the next packet sent by chain `from`, without
+    /// delivering it. Returns `None` if there is no pending packet.
+    pub fn timeout_next(&mut self, from: Side) -> AnyResult<Option<RelayedPacket>> {
+        let Some(packet) = self.take_packet(from)? else {
+            return Ok(None);
+        };
+        let source = self.chain_mut(from);
+        let relayer = source.addr_make("relayer");
+        let ics721 = source.ics721.clone();
+        let source_response = source.app.wasm_sudo(
+            ics721,
+            &IbcSudoMsg::Timeout(IbcPacketTimeoutMsg::new(packet.clone(), relayer)),
+        )?;
+        Ok(Some(RelayedPacket {
+            packet,
+            receive_response: None,
+            ack: None,
+            source_response,
+        }))
+    }
+
+    fn take_packet(&mut self, from: Side) -> AnyResult<Option<IbcPacket>> {
+        let src = self.chain(from).endpoint();
+        let dest = self.chain(from.other()).endpoint();
+        let source = self.chain_mut(from);
+        let ics721 = source.ics721.clone();
+        let Some(sent) = source.app.init_modules(|_, _, storage| {
+            PacketCapturingModule::take_packet(storage, &ics721, &src.channel_id)
+        })?
+        else {
+            return Ok(None);
+        };
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        Ok(Some(IbcPacket::new(
+            sent.data,
+            src,
+            dest,
+            sequence,
+            sent.timeout,
+        )))
+    }
+}
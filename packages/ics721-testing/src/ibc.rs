@@ -0,0 +1,173 @@
+use std::fmt::Debug;
+
+use anyhow::{bail, Result as AnyResult};
+use cosmwasm_schema::{cw_serde, schemars::JsonSchema};
+use cosmwasm_std::{
+    Addr, Api, Binary, BlockInfo, CustomQuery, DepsMut, Empty, Env, IbcBasicResponse, IbcMsg,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcQuery, IbcTimeout, Querier,
+    Response, Storage,
+};
+use cw_multi_test::{AppResponse, Contract, ContractWrapper, CosmosRouter, Ibc, Module};
+use cw_storage_plus::Item;
+use ics721::ContractError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Packets sent by contracts, not relayed yet. Stored in app storage
+/// (outside of any contract's namespace), so packets sent by a failing
+/// tx are reverted along with it.
+const SENT_PACKETS: Item<Vec<SentPacket>> = Item::new("ics721_testing_sent_packets");
+
+/// An `IbcMsg::SendPacket` dispatched by a contract.
+#[cw_serde]
+pub struct SentPacket {
+    /// Contract sending the packet.
+    pub sender: Addr,
+    pub channel_id: String,
+    pub data: Binary,
+    pub timeout: IbcTimeout,
+}
+
+/// IBC module capturing sent packets for relaying. Other IBC messages
+/// are accepted and ignored, same as `IbcAcceptingModule`.
+#[derive(Default)]
+pub struct PacketCapturingModule;
+
+impl PacketCapturingModule {
+    /// Returns all packets sent and not relayed yet.
+    pub fn sent_packets(storage: &dyn Storage) -> AnyResult<Vec<SentPacket>> {
+        Ok(SENT_PACKETS.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// Removes and returns the first packet sent by `sender` on
+    /// `channel_id`.
+    pub fn take_packet(
+        storage: &mut dyn Storage,
+        sender: &Addr,
+        channel_id: &str,
+    ) -> AnyResult<Option<SentPacket>> {
+        let mut packets = Self::sent_packets(storage)?;
+        let Some(index) = packets
+            .iter()
+            .position(|p| &p.sender == sender && p.channel_id == channel_id)
+        else {
+            return Ok(None);
+        };
+        let packet = packets.remove(index);
+        SENT_PACKETS.save(storage, &packets)?;
+        Ok(Some(packet))
+    }
+}
+
+impl Module for PacketCapturingModule {
+    type ExecT = IbcMsg;
+    type QueryT = IbcQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        sender: Addr,
+        msg: IbcMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        if let IbcMsg::SendPacket {
+            channel_id,
+            data,
+            timeout,
+        } = msg
+        {
+            let mut packets = Self::sent_packets(storage)?;
+            packets.push(SentPacket {
+                sender,
+                channel_id,
+                data,
+                timeout,
+            });
+            SENT_PACKETS.save(storage, &packets)?;
+        }
+        Ok(AppResponse::default())
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        _storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: IbcQuery,
+    ) -> AnyResult<Binary> {
+        bail!("unsupported IBC query: {request:?}")
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("unsupported IBC sudo: {msg:?}")
+    }
+}
+
+impl Ibc for PacketCapturingModule {}
+
+/// cw-multi-test doesn't call IBC entry points, so the relayer calls
+/// them using sudo.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IbcSudoMsg {
+    Receive(IbcPacketReceiveMsg),
+    Ack(IbcPacketAckMsg),
+    Timeout(IbcPacketTimeoutMsg),
+}
+
+fn ibc_sudo(deps: DepsMut, env: Env, msg: IbcSudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        IbcSudoMsg::Receive(msg) => {
+            // never fails, errors are returned as error acks
+            let res = ics721_base::ibc_packet_receive(deps, env, msg).unwrap();
+            Ok(Response::new()
+                .add_submessages(res.messages)
+                .add_attributes(res.attributes)
+                .add_events(res.events)
+                .set_data(res.acknowledgement))
+        }
+        IbcSudoMsg::Ack(msg) => Ok(basic_response(ics721_base::ibc_packet_ack(deps, env, msg)?)),
+        IbcSudoMsg::Timeout(msg) => Ok(basic_response(ics721_base::ibc_packet_timeout(
+            deps, env, msg,
+        )?)),
+    }
+}
+
+fn basic_response(res: IbcBasicResponse) -> Response {
+    Response::new()
+        .add_submessages(res.messages)
+        .add_attributes(res.attributes)
+        .add_events(res.events)
+}
+
+/// ICS721 (`ics721-base`) with IBC entry points callable using
+/// `IbcSudoMsg`.
+pub fn ics721_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        ics721_base::execute,
+        ics721_base::instantiate,
+        ics721_base::query,
+    )
+    .with_migrate(ics721_base::migrate)
+    .with_reply(ics721_base::reply)
+    .with_sudo(ibc_sudo);
+    Box::new(contract)
+}
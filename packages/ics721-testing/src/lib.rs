@@ -0,0 +1,20 @@
+//! Two-chain IBC simulation harness for testing ICS721 round trips
+//! using cw-multi-test.
+//!
+//! Each chain is a cw-multi-test app holding ICS721, cw721 and
+//! optionally proxies. Packets sent by ICS721 are captured and relayed
+//! by `Ics721Harness` to the other chain, which then acks or times out
+//! the packet on the sending chain.
+
+pub mod chain;
+pub mod harness;
+pub mod ibc;
+pub mod mock;
+
+pub use chain::{Chain, ChainConfig};
+pub use harness::{Ics721Harness, RelayedPacket, Side};
+
+#[cfg(test)]
+mod model_tests;
+#[cfg(test)]
+mod tests;
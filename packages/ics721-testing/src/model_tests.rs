@@ -0,0 +1,405 @@
+//! Model based tests of escrow and voucher state. Random sequences of
+//! transfers, relays, timeouts, pauses and migrations are run against
+//! both chains, checking after each step that every NFT is in exactly
+//! one place, and that channel entries and token metadata match cw721
+//! ownership. Failing sequences are shrunk by proptest.
+
+use cosmwasm_std::{from_json, Addr};
+use cw_multi_test::Executor;
+use ics721::msg::{AuditEscrowResponse, ExecuteMsg, MigrateMsg, QueryMsg};
+use ics721_types::{ibc_types::NonFungibleTokenPacketData, token_types::Token};
+use proptest::prelude::*;
+
+use crate::{ibc::PacketCapturingModule, Ics721Harness, Side};
+
+/// NFTs minted on each chain.
+const TOKENS_PER_CHAIN: usize = 3;
+
+/// Not a valid address on either chain, so receiving fails.
+const INVALID_RECEIVER: &str = "invalid";
+
+#[derive(Clone, Debug)]
+enum Action {
+    /// Sends the NFT to the other chain, from whichever chain its
+    /// holder is on. Does nothing while the NFT is in flight.
+    Send {
+        nft: usize,
+        invalid_receiver: bool,
+    },
+    Relay(Side),
+    Timeout(Side),
+    Pause(Side),
+    /// Migrates ICS721, which also unpauses it.
+    Migrate(Side),
+}
+
+fn side() -> impl Strategy<Value = Side> {
+    prop_oneof![Just(Side::A), Just(Side::B)]
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        4 => (0..2 * TOKENS_PER_CHAIN, prop::bool::weighted(0.2)).prop_map(
+            |(nft, invalid_receiver)| Action::Send {
+                nft,
+                invalid_receiver
+            }
+        ),
+        4 => side().prop_map(Action::Relay),
+        1 => side().prop_map(Action::Timeout),
+        1 => side().prop_map(Action::Pause),
+        1 => side().prop_map(Action::Migrate),
+    ]
+}
+
+/// An NFT native to `home`.
+#[derive(Clone, Debug)]
+struct Nft {
+    home: Side,
+    collection: Addr,
+    token_id: String,
+}
+
+/// Where an NFT is, according to cw721 ownership and pending packets.
+struct Location {
+    /// Owner of the NFT on its home chain.
+    home_owner: Option<String>,
+    /// Voucher collection on the other chain, if any.
+    voucher_contract: Option<Addr>,
+    /// Owner of the voucher, `None` if there is no voucher.
+    voucher_owner: Option<String>,
+    /// Number of pending packets sending the NFT away from home.
+    sending: usize,
+    /// Number of pending packets returning the voucher home.
+    returning: usize,
+}
+
+/// Channel entries expected on a chain.
+#[derive(Default)]
+struct Entries {
+    outgoing: Vec<((String, String), String)>,
+    incoming: Vec<((String, String), String)>,
+}
+
+struct Model {
+    harness: Ics721Harness,
+    nfts: Vec<Nft>,
+}
+
+impl Model {
+    fn new() -> Self {
+        let mut harness = Ics721Harness::default();
+        let mut nfts = vec![];
+        for home in [Side::A, Side::B] {
+            let chain = harness.chain_mut(home);
+            let minter = chain.addr_make("minter");
+            let user = chain.addr_make("user");
+            let collection = chain.instantiate_collection(&minter);
+            for token_id in 0..TOKENS_PER_CHAIN {
+                let token_id = token_id.to_string();
+                chain.mint(&collection, &minter, &token_id, &user);
+                nfts.push(Nft {
+                    home,
+                    collection: collection.clone(),
+                    token_id,
+                });
+            }
+        }
+        Self { harness, nfts }
+    }
+
+    /// Owner of all NFTs and vouchers on a chain, unless escrowed.
+    fn user(&self, side: Side) -> Addr {
+        self.harness.chain(side).addr_make("user")
+    }
+
+    fn paused(&self, side: Side) -> bool {
+        let chain = self.harness.chain(side);
+        chain
+            .app
+            .wrap()
+            .query_wasm_smart(&chain.ics721, &QueryMsg::Paused {})
+            .unwrap()
+    }
+
+    fn voucher_class_id(&self, nft: &Nft) -> String {
+        self.harness
+            .voucher_class_id(nft.home.other(), nft.collection.as_str())
+    }
+
+    /// Returns the token IDs of `class_id` in packets sent by chain
+    /// `from` and not relayed yet.
+    fn in_flight(&self, from: Side, class_id: &str) -> Vec<String> {
+        let chain = self.harness.chain(from);
+        chain
+            .app
+            .read_module(|_, _, storage| PacketCapturingModule::sent_packets(storage))
+            .unwrap()
+            .into_iter()
+            .filter(|p| p.sender == chain.ics721)
+            .filter_map(|p| from_json::<NonFungibleTokenPacketData>(&p.data).ok())
+            .filter(|data| data.class_id.to_string() == class_id)
+            .flat_map(|data| data.token_ids)
+            .map(String::from)
+            .collect()
+    }
+
+    fn locate(&self, nft: &Nft) -> Location {
+        let home = self.harness.chain(nft.home);
+        let away = self.harness.chain(nft.home.other());
+        let voucher_class_id = self.voucher_class_id(nft);
+        let voucher_contract = away.nft_contract(&voucher_class_id);
+        let count = |side, class_id: &str| {
+            self.in_flight(side, class_id)
+                .iter()
+                .filter(|token_id| **token_id == nft.token_id)
+                .count()
+        };
+        Location {
+            home_owner: home.owner_of(&nft.collection, &nft.token_id).ok(),
+            voucher_owner: voucher_contract
+                .as_ref()
+                .and_then(|voucher| away.owner_of(voucher, &nft.token_id).ok()),
+            voucher_contract,
+            sending: count(nft.home, nft.collection.as_str()),
+            returning: count(nft.home.other(), &voucher_class_id),
+        }
+    }
+
+    /// Returns the chain and collection the NFT can be sent from, `None`
+    /// while in flight.
+    fn holder(&self, nft: &Nft) -> Option<(Side, Addr)> {
+        let away = nft.home.other();
+        let location = self.locate(nft);
+        if location.home_owner == Some(self.user(nft.home).to_string()) {
+            Some((nft.home, nft.collection.clone()))
+        } else if location.voucher_owner == Some(self.user(away).to_string()) {
+            location.voucher_contract.map(|voucher| (away, voucher))
+        } else {
+            None
+        }
+    }
+
+    fn apply(&mut self, action: &Action) -> Result<(), TestCaseError> {
+        match *action {
+            Action::Send {
+                nft,
+                invalid_receiver,
+            } => {
+                let nft = self.nfts[nft].clone();
+                let Some((from, collection)) = self.holder(&nft) else {
+                    return Ok(());
+                };
+                let paused = self.paused(from);
+                let owner = self.user(from);
+                let receiver = match invalid_receiver {
+                    true => INVALID_RECEIVER.to_string(),
+                    false => self.user(from.other()).to_string(),
+                };
+                let res = self.harness.chain_mut(from).send_nft(
+                    &collection,
+                    &nft.token_id,
+                    &owner,
+                    &receiver,
+                    None,
+                );
+                prop_assert_eq!(res.is_ok(), !paused, "{:?}", res);
+            }
+            Action::Relay(from) => {
+                let paused = self.paused(from.other());
+                if let Some(relayed) = self.harness.relay_next(from).unwrap() {
+                    let data: NonFungibleTokenPacketData = from_json(&relayed.packet.data).unwrap();
+                    let ack = relayed.ack.unwrap();
+                    prop_assert_eq!(
+                        ack.is_success(),
+                        !paused && data.receiver != INVALID_RECEIVER,
+                        "{:?}",
+                        ack
+                    );
+                }
+            }
+            Action::Timeout(from) => {
+                self.harness.timeout_next(from).unwrap();
+            }
+            Action::Pause(side) => {
+                let paused = self.paused(side);
+                let chain = self.harness.chain_mut(side);
+                let admin = chain.addr_make("ics721-admin");
+                let ics721 = chain.ics721.clone();
+                let res = chain
+                    .app
+                    .execute_contract(admin, ics721, &ExecuteMsg::Pause {}, &[]);
+                // the pauser may only pause once, until migrated
+                prop_assert_eq!(res.is_ok(), !paused, "{:?}", res);
+                prop_assert!(self.paused(side));
+            }
+            Action::Migrate(side) => {
+                let chain = self.harness.chain_mut(side);
+                let admin = chain.addr_make("ics721-admin");
+                let ics721 = chain.ics721.clone();
+                let code_id = chain.ics721_code_id;
+                chain
+                    .app
+                    .migrate_contract(
+                        admin.clone(),
+                        ics721,
+                        &MigrateMsg::WithUpdate {
+                            pauser: Some(admin.to_string()),
+                            outgoing_proxy: None,
+                            incoming_proxy: None,
+                            cw721_base_code_id: None,
+                            cw721_admin: None,
+                            contract_addr_length: None,
+                        },
+                        code_id,
+                    )
+                    .unwrap();
+                prop_assert!(!self.paused(side));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_invariants(&self) -> Result<(), TestCaseError> {
+        let mut entries = [Entries::default(), Entries::default()];
+        let index = |side| match side {
+            Side::A => 0,
+            Side::B => 1,
+        };
+        for nft in &self.nfts {
+            let home = self.harness.chain(nft.home);
+            let away = self.harness.chain(nft.home.other());
+            let voucher_class_id = self.voucher_class_id(nft);
+            let location = self.locate(nft);
+
+            // NFTs are never burned on their home chain
+            let Some(home_owner) = location.home_owner.as_deref() else {
+                return Err(TestCaseError::fail(format!("{nft:?} doesn't exist")));
+            };
+            let escrowed = home_owner == home.ics721.as_str();
+            let voucher_escrowed = location.voucher_owner.as_deref() == Some(away.ics721.as_str());
+            let places = usize::from(!escrowed)
+                + usize::from(location.voucher_owner.is_some() && !voucher_escrowed)
+                + location.sending
+                + location.returning;
+            prop_assert_eq!(places, 1, "{:?} is in {} places", nft, places);
+            // escrowed at home, as long as it's away
+            prop_assert_eq!(
+                escrowed,
+                location.voucher_owner.is_some() || location.sending > 0,
+                "{:?}",
+                nft
+            );
+            // vouchers are escrowed only while returning home
+            prop_assert_eq!(voucher_escrowed, location.returning > 0, "{:?}", nft);
+
+            let class_token = |class_id: &str| (class_id.to_string(), nft.token_id.clone());
+            if escrowed {
+                entries[index(nft.home)].outgoing.push((
+                    class_token(nft.collection.as_str()),
+                    home.channel_id.clone(),
+                ));
+            }
+            if location.voucher_owner.is_some() {
+                entries[index(nft.home.other())]
+                    .incoming
+                    .push((class_token(&voucher_class_id), away.channel_id.clone()));
+            }
+            if voucher_escrowed {
+                entries[index(nft.home.other())]
+                    .outgoing
+                    .push((class_token(&voucher_class_id), away.channel_id.clone()));
+            }
+
+            // token metadata is only stored for existing vouchers
+            let has_metadata = |side, class_id: &str| {
+                let chain = self.harness.chain(side);
+                let res: Result<Option<Token>, _> = chain.app.wrap().query_wasm_smart(
+                    &chain.ics721,
+                    &QueryMsg::TokenMetadata {
+                        class_id: class_id.to_string(),
+                        token_id: nft.token_id.clone(),
+                    },
+                );
+                // errors in case the voucher doesn't exist
+                !matches!(res, Ok(None))
+            };
+            prop_assert!(!has_metadata(nft.home, nft.collection.as_str()));
+            prop_assert_eq!(
+                has_metadata(nft.home.other(), &voucher_class_id),
+                location.voucher_owner.is_some(),
+                "{:?}",
+                nft
+            );
+        }
+
+        for side in [Side::A, Side::B] {
+            let chain = self.harness.chain(side);
+            let Entries {
+                mut outgoing,
+                mut incoming,
+            } = std::mem::take(&mut entries[index(side)]);
+            outgoing.sort();
+            incoming.sort();
+            let query = |msg: QueryMsg| -> Vec<((String, String), String)> {
+                chain
+                    .app
+                    .wrap()
+                    .query_wasm_smart(&chain.ics721, &msg)
+                    .unwrap()
+            };
+            prop_assert_eq!(
+                query(QueryMsg::OutgoingChannels {
+                    start_after: None,
+                    limit: None,
+                }),
+                outgoing
+            );
+            prop_assert_eq!(
+                query(QueryMsg::IncomingChannels {
+                    start_after: None,
+                    limit: None,
+                }),
+                incoming
+            );
+
+            let audit: AuditEscrowResponse = chain
+                .app
+                .wrap()
+                .query_wasm_smart(
+                    &chain.ics721,
+                    &QueryMsg::AuditEscrow {
+                        class_id: None,
+                        start_after: None,
+                        limit: Some(100),
+                    },
+                )
+                .unwrap();
+            prop_assert!(audit.findings.is_empty(), "{:?}", audit.findings);
+        }
+        Ok(())
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn test_escrow_and_voucher_invariants(actions in prop::collection::vec(action(), 1..48)) {
+        let mut model = Model::new();
+        model.check_invariants()?;
+        for action in &actions {
+            model.apply(action)?;
+            model.check_invariants()?;
+        }
+
+        // once unpaused and all packets are relayed, nothing is in flight
+        model.apply(&Action::Migrate(Side::A))?;
+        model.apply(&Action::Migrate(Side::B))?;
+        model.harness.relay_all().unwrap();
+        model.check_invariants()?;
+        for nft in &model.nfts {
+            prop_assert!(model.holder(nft).is_some(), "{:?} is in flight", nft);
+        }
+    }
+}
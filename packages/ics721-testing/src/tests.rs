@@ -0,0 +1,110 @@
+use crate::{ChainConfig, Ics721Harness, Side};
+
+#[test]
+fn test_round_trip() {
+    let mut harness = Ics721Harness::default();
+    let minter = harness.a.addr_make("minter");
+    let alice = harness.a.addr_make("alice");
+    let bob = harness.b.addr_make("bob");
+    let collection = harness.a.instantiate_collection(&minter);
+    harness.a.mint(&collection, &minter, "1", &alice);
+
+    // A -> B: NFT is escrowed on A, voucher minted on B
+    harness
+        .a
+        .send_nft(&collection, "1", &alice, bob.as_str(), None)
+        .unwrap();
+    assert_eq!(harness.pending_packets(Side::A), 1);
+    let relayed = harness.relay_all().unwrap();
+    assert_eq!(relayed.len(), 1);
+    assert!(relayed[0].ack.as_ref().unwrap().is_success());
+    assert_eq!(harness.pending_packets(Side::A), 0);
+    harness.a.assert_escrowed(&collection, "1");
+    let voucher = harness
+        .voucher_contract(Side::B, collection.as_str())
+        .unwrap();
+    harness.b.assert_owner(&voucher, "1", &bob);
+
+    // B -> A: voucher is burned on B, NFT released on A
+    let class_id = harness.voucher_class_id(Side::B, collection.as_str());
+    harness
+        .b
+        .send_nft(&voucher, "1", &bob, alice.as_str(), None)
+        .unwrap();
+    let relayed = harness.relay_all().unwrap();
+    assert_eq!(relayed.len(), 1);
+    assert_eq!(
+        relayed[0].packet.src.channel_id,
+        harness.b.channel_id.clone()
+    );
+    assert!(relayed[0].ack.as_ref().unwrap().is_success());
+    harness.a.assert_owner(&collection, "1", &alice);
+    harness.b.assert_not_exists(&voucher, "1");
+    assert_eq!(harness.b.nft_contract(&class_id), Some(voucher));
+}
+
+#[test]
+fn test_timeout_refunds_sender() {
+    let mut harness = Ics721Harness::default();
+    let minter = harness.a.addr_make("minter");
+    let alice = harness.a.addr_make("alice");
+    let bob = harness.b.addr_make("bob");
+    let collection = harness.a.instantiate_collection(&minter);
+    harness.a.mint(&collection, &minter, "1", &alice);
+
+    harness
+        .a
+        .send_nft(&collection, "1", &alice, bob.as_str(), None)
+        .unwrap();
+    harness.a.assert_escrowed(&collection, "1");
+    let timed_out = harness.timeout_next(Side::A).unwrap().unwrap();
+    assert!(timed_out.ack.is_none());
+    assert!(harness.relay_all().unwrap().is_empty());
+    harness.a.assert_owner(&collection, "1", &alice);
+    assert_eq!(harness.voucher_contract(Side::B, collection.as_str()), None);
+}
+
+#[test]
+fn test_error_ack_refunds_sender() {
+    let mut harness = Ics721Harness::default();
+    let minter = harness.a.addr_make("minter");
+    let alice = harness.a.addr_make("alice");
+    let collection = harness.a.instantiate_collection(&minter);
+    harness.a.mint(&collection, &minter, "1", &alice);
+
+    // receiver isn't a valid address on B
+    harness
+        .a
+        .send_nft(&collection, "1", &alice, "invalid", None)
+        .unwrap();
+    let relayed = harness.relay_all().unwrap();
+    assert!(relayed[0].ack.as_ref().unwrap().error().is_some());
+    harness.a.assert_owner(&collection, "1", &alice);
+}
+
+#[test]
+fn test_round_trip_with_proxies() {
+    let mut harness = Ics721Harness::new(
+        ChainConfig::new("juno", "channel-0").with_outgoing_proxy(),
+        ChainConfig::new("stars", "channel-1").with_incoming_proxy(),
+    );
+    assert!(harness.a.outgoing_proxy.is_some());
+    assert!(harness.b.incoming_proxy.is_some());
+    let minter = harness.a.addr_make("minter");
+    let alice = harness.a.addr_make("alice");
+    let bob = harness.b.addr_make("bob");
+    let collection = harness.a.instantiate_collection(&minter);
+    harness.a.mint(&collection, &minter, "1", &alice);
+
+    harness
+        .a
+        .send_nft(&collection, "1", &alice, bob.as_str(), None)
+        .unwrap();
+    let relayed = harness.relay_all().unwrap();
+    assert!(relayed[0].ack.as_ref().unwrap().is_success());
+    harness.a.assert_escrowed(&collection, "1");
+    let voucher = harness
+        .voucher_contract(Side::B, collection.as_str())
+        .unwrap();
+    harness.b.assert_owner(&voucher, "1", &bob);
+}
@@ -0,0 +1,169 @@
+use cosmwasm_std::{from_json, to_json_binary, Binary, IbcAcknowledgement, StdResult};
+use serde::{Deserialize, Serialize};
+
+/// Payload of a successful ICS721 ack, as sent by this implementation.
+pub const ACK_SUCCESS_PAYLOAD: &[u8] = b"1";
+
+/// The ICS721 spec is very vague about how ACKs are suposed to be
+/// encoded. To be honest, I don't think this method is correct at all
+/// if we were to follow the wording of the spec.
+///
+/// The intent of the spec though is to have the same ACK format as
+/// ICS20 which endodes its ACKs like this. This is compatible with
+/// the SDK ACK protobuf defined here:
+/// <https://github.com/cosmos/cosmos-sdk/blob/v0.42.0/proto/ibc/core/channel/v1/channel.proto#L141-L147>
+///
+/// Encoded as JSON, e.g. `{"result":"MQ=="}` or `{"error":"..."}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Ics721Ack {
+    Result(Binary),
+    Error(String),
+}
+
+impl Ics721Ack {
+    /// Success ack with the default `"1"` payload.
+    pub fn success() -> Self {
+        Self::Result(ACK_SUCCESS_PAYLOAD.into())
+    }
+
+    /// Success ack with a custom payload.
+    pub fn success_with(payload: impl Into<Binary>) -> Self {
+        Self::Result(payload.into())
+    }
+
+    /// Error ack with the given message.
+    pub fn error(err: impl Into<String>) -> Self {
+        Self::Error(err.into())
+    }
+
+    /// Encodes the ack as JSON, ready to be set on an IBC receive
+    /// response.
+    pub fn encode(&self) -> StdResult<Binary> {
+        to_json_binary(self)
+    }
+
+    /// Decodes raw ack data. Never fails: data not in ICS721 format is
+    /// classified as `AckResult::SdkError`.
+    pub fn decode(data: &Binary) -> AckResult {
+        match from_json::<Ics721Ack>(data) {
+            Ok(Ics721Ack::Result(payload)) => AckResult::Success(payload),
+            Ok(Ics721Ack::Error(error)) => AckResult::Error(error),
+            // What we can not parse is an ACK fail.
+            Err(_) => AckResult::SdkError(data.clone()),
+        }
+    }
+}
+
+/// Decoded ICS721 acknowledgement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AckResult {
+    /// Success ack holding its payload. ICS721 sends `"1"`, but
+    /// counterparties may send other payloads.
+    Success(Binary),
+    /// Error ack set by the counterparty contract.
+    Error(String),
+    /// Ack that is not in ICS721 format and hence not set by the
+    /// counterparty contract, but by the SDK. Holds the raw ack data.
+    ///
+    /// NOTE(ekez): I do not know all cases this will occur, but I do
+    /// know it happens if a field on the packet data is set to an
+    /// empty string. That being the case, the SDK will return an error
+    /// in the form:
+    ///
+    /// ```json
+    /// {"error":"Empty attribute value. Key: class_id: invalid event"}
+    /// ```
+    SdkError(Binary),
+}
+
+impl AckResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self, AckResult::Success(_))
+    }
+
+    /// Returns the error message, if any. SDK errors are returned base64
+    /// encoded (as this is what we receive). For example, the error
+    /// above is returned as:
+    ///
+    /// ```json
+    /// "eyJlcnJvciI6IkVtcHR5IGF0dHJpYnV0ZSB2YWx1ZS4gS2V5OiBjbGFzc19pZDogaW52YWxpZCBldmVudCJ9"
+    /// ```
+    pub fn error(&self) -> Option<String> {
+        match self {
+            AckResult::Success(_) => None,
+            AckResult::Error(e) => Some(e.clone()),
+            AckResult::SdkError(data) => Some(data.to_base64()),
+        }
+    }
+}
+
+/// Returns true if the ack data is not in ICS721 format, which means it
+/// is an error ack generated by the SDK.
+pub fn is_sdk_error_ack(data: &Binary) -> bool {
+    matches!(Ics721Ack::decode(data), AckResult::SdkError(_))
+}
+
+pub fn ack_success() -> Binary {
+    Ics721Ack::success().encode().unwrap()
+}
+
+pub fn ack_fail(err: String) -> Binary {
+    Ics721Ack::error(err).encode().unwrap()
+}
+
+/// Tries to get the error from an ACK. If an error exists, returns
+/// Some(error_message). Otherwise, returns `None`. See
+/// `AckResult::error` for how SDK errors are returned.
+pub fn try_get_ack_error(ack: &IbcAcknowledgement) -> Option<String> {
+    Ics721Ack::decode(&ack.data).error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(ack_success(), Binary::from(br#"{"result":"MQ=="}"#));
+        assert_eq!(
+            ack_fail("oops".to_string()),
+            Binary::from(br#"{"error":"oops"}"#)
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            Ics721Ack::decode(&ack_success()),
+            AckResult::Success(b"1".into())
+        );
+        assert_eq!(
+            Ics721Ack::decode(
+                &Ics721Ack::success_with(b"payload".to_vec())
+                    .encode()
+                    .unwrap()
+            ),
+            AckResult::Success(b"payload".into())
+        );
+        let result = Ics721Ack::decode(&ack_fail("oops".to_string()));
+        assert_eq!(result, AckResult::Error("oops".to_string()));
+        assert!(!result.is_success());
+        assert_eq!(result.error(), Some("oops".to_string()));
+        assert_eq!(
+            try_get_ack_error(&IbcAcknowledgement::new(ack_success())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_sdk_error() {
+        let data = Binary::from(b"not json".to_vec());
+        assert!(is_sdk_error_ack(&data));
+        assert_eq!(
+            try_get_ack_error(&IbcAcknowledgement::new(data.clone())),
+            Some(data.to_base64())
+        );
+        assert!(!is_sdk_error_ack(&ack_success()));
+    }
+}
@@ -5,6 +5,9 @@ pub enum Ics721Error {
     #[error("empty class ID")]
     EmptyClassId {},
 
+    #[error("empty owner")]
+    EmptyOwner {},
+
     #[error("must transfer at least one token")]
     NoTokens {},
 
@@ -13,4 +16,16 @@ pub enum Ics721Error {
 
     #[error("tokenIds, tokenUris, and tokenData must have the same length")]
     TokenInfoLenMissmatch {},
+
+    #[error("unknown ICS721 event type: {0}")]
+    UnknownEventType(String),
+
+    #[error("unsupported ICS721 event version: {0}")]
+    UnsupportedEventVersion(String),
+
+    #[error("ICS721 event {event_type} is missing attribute {key}")]
+    MissingEventAttribute { event_type: String, key: String },
+
+    #[error("ICS721 event {event_type} has an invalid attribute {key}")]
+    InvalidEventAttribute { event_type: String, key: String },
 }
@@ -0,0 +1,360 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, to_json_string, Event};
+
+use crate::{
+    error::Ics721Error,
+    token_types::{ClassId, TokenId},
+    types::Ics721HookTransfer,
+};
+
+/// Version of the event schema, emitted as `version` attribute of each
+/// event. Bumped on any breaking change of event types or attributes.
+pub const ICS721_EVENT_VERSION: &str = "1";
+
+/// Events emitted by ICS721. Event types are prefixed by `ics721_`, and
+/// on chain additionally by `wasm-`. Attribute keys are snake case and
+/// lists are JSON encoded.
+///
+/// Transfer events hold these attributes: `channel_id` (local channel),
+/// `class_id` (local class ID), `nft_contract`, `token_ids`, `sender`
+/// and `receiver`.
+#[cw_serde]
+pub enum Ics721Event {
+    /// `ics721_send`: NFT escrowed and sent out.
+    Send { transfer: Ics721HookTransfer },
+    /// `ics721_receive_mint`: vouchers minted for an incoming transfer.
+    ReceiveMint { transfer: Ics721HookTransfer },
+    /// `ics721_receive_redeem`: escrowed NFTs released for an incoming
+    /// transfer.
+    ReceiveRedeem { transfer: Ics721HookTransfer },
+    /// `ics721_ack_success`: outgoing transfer acked. Additionally holds
+    /// `burned_token_ids`, the vouchers burned since they returned to
+    /// their source chain.
+    AckSuccess {
+        transfer: Ics721HookTransfer,
+        burned_token_ids: Vec<TokenId>,
+    },
+    /// `ics721_ack_fail`: outgoing transfer acked with an error and
+    /// refunded to sender. Additionally holds `error`.
+    AckFail {
+        transfer: Ics721HookTransfer,
+        error: String,
+    },
+    /// `ics721_timeout`: outgoing transfer timed out and refunded to
+    /// sender.
+    Timeout { transfer: Ics721HookTransfer },
+    /// `ics721_admin_clean_and_burn`: incoming channel entry of a
+    /// voucher removed by admin. Holds `class_id`, `nft_contract`,
+    /// `token_id` and `owner`.
+    AdminCleanAndBurn {
+        class_id: ClassId,
+        nft_contract: String,
+        token_id: TokenId,
+        owner: String,
+    },
+    /// `ics721_admin_clean_and_unescrow`: outgoing channel entry of an
+    /// escrowed NFT removed by admin. Holds `class_id`, `nft_contract`,
+    /// `token_id` and `recipient`.
+    AdminCleanAndUnescrow {
+        class_id: ClassId,
+        nft_contract: String,
+        token_id: TokenId,
+        recipient: String,
+    },
+}
+
+impl Ics721Event {
+    /// The event type, without the `wasm-` prefix added on chain.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            Ics721Event::Send { .. } => "ics721_send",
+            Ics721Event::ReceiveMint { .. } => "ics721_receive_mint",
+            Ics721Event::ReceiveRedeem { .. } => "ics721_receive_redeem",
+            Ics721Event::AckSuccess { .. } => "ics721_ack_success",
+            Ics721Event::AckFail { .. } => "ics721_ack_fail",
+            Ics721Event::Timeout { .. } => "ics721_timeout",
+            Ics721Event::AdminCleanAndBurn { .. } => "ics721_admin_clean_and_burn",
+            Ics721Event::AdminCleanAndUnescrow { .. } => "ics721_admin_clean_and_unescrow",
+        }
+    }
+}
+
+fn token_ids_json(token_ids: &[TokenId]) -> String {
+    // a list of strings always serializes
+    to_json_string(token_ids).unwrap()
+}
+
+fn add_transfer(event: Event, transfer: Ics721HookTransfer) -> Event {
+    event
+        .add_attribute("channel_id", transfer.channel_id)
+        .add_attribute("class_id", transfer.class_id)
+        .add_attribute("nft_contract", transfer.nft_contract)
+        .add_attribute("token_ids", token_ids_json(&transfer.token_ids))
+        .add_attribute("sender", transfer.sender)
+        .add_attribute("receiver", transfer.receiver)
+}
+
+impl From<Ics721Event> for Event {
+    fn from(ics721_event: Ics721Event) -> Self {
+        let event =
+            Event::new(ics721_event.event_type()).add_attribute("version", ICS721_EVENT_VERSION);
+        match ics721_event {
+            Ics721Event::Send { transfer }
+            | Ics721Event::ReceiveMint { transfer }
+            | Ics721Event::ReceiveRedeem { transfer }
+            | Ics721Event::Timeout { transfer } => add_transfer(event, transfer),
+            Ics721Event::AckSuccess {
+                transfer,
+                burned_token_ids,
+            } => add_transfer(event, transfer)
+                .add_attribute("burned_token_ids", token_ids_json(&burned_token_ids)),
+            Ics721Event::AckFail { transfer, error } => {
+                add_transfer(event, transfer).add_attribute("error", error)
+            }
+            Ics721Event::AdminCleanAndBurn {
+                class_id,
+                nft_contract,
+                token_id,
+                owner,
+            } => event
+                .add_attribute("class_id", class_id)
+                .add_attribute("nft_contract", nft_contract)
+                .add_attribute("token_id", token_id)
+                .add_attribute("owner", owner),
+            Ics721Event::AdminCleanAndUnescrow {
+                class_id,
+                nft_contract,
+                token_id,
+                recipient,
+            } => event
+                .add_attribute("class_id", class_id)
+                .add_attribute("nft_contract", nft_contract)
+                .add_attribute("token_id", token_id)
+                .add_attribute("recipient", recipient),
+        }
+    }
+}
+
+/// Reads attributes of an event, by key.
+struct EventAttributes<'a>(&'a Event);
+
+impl EventAttributes<'_> {
+    fn get(&self, key: &str) -> Result<String, Ics721Error> {
+        self.0
+            .attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.clone())
+            .ok_or_else(|| Ics721Error::MissingEventAttribute {
+                event_type: self.0.ty.clone(),
+                key: key.to_string(),
+            })
+    }
+
+    fn get_token_ids(&self, key: &str) -> Result<Vec<TokenId>, Ics721Error> {
+        from_json(self.get(key)?).map_err(|_| Ics721Error::InvalidEventAttribute {
+            event_type: self.0.ty.clone(),
+            key: key.to_string(),
+        })
+    }
+
+    fn get_transfer(&self) -> Result<Ics721HookTransfer, Ics721Error> {
+        Ok(Ics721HookTransfer {
+            channel_id: self.get("channel_id")?,
+            class_id: ClassId::new(self.get("class_id")?),
+            nft_contract: self.get("nft_contract")?,
+            token_ids: self.get_token_ids("token_ids")?,
+            sender: self.get("sender")?,
+            receiver: self.get("receiver")?,
+        })
+    }
+}
+
+/// Decodes an event emitted by ICS721, e.g. for off-chain indexers.
+/// Event types may or may not have the `wasm-` prefix added on chain.
+impl TryFrom<&Event> for Ics721Event {
+    type Error = Ics721Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let event_type = event.ty.strip_prefix("wasm-").unwrap_or(&event.ty);
+        let attributes = EventAttributes(event);
+        let decode: fn(&EventAttributes) -> Result<Ics721Event, Ics721Error> = match event_type {
+            "ics721_send" => |a| {
+                Ok(Ics721Event::Send {
+                    transfer: a.get_transfer()?,
+                })
+            },
+            "ics721_receive_mint" => |a| {
+                Ok(Ics721Event::ReceiveMint {
+                    transfer: a.get_transfer()?,
+                })
+            },
+            "ics721_receive_redeem" => |a| {
+                Ok(Ics721Event::ReceiveRedeem {
+                    transfer: a.get_transfer()?,
+                })
+            },
+            "ics721_ack_success" => |a| {
+                Ok(Ics721Event::AckSuccess {
+                    transfer: a.get_transfer()?,
+                    burned_token_ids: a.get_token_ids("burned_token_ids")?,
+                })
+            },
+            "ics721_ack_fail" => |a| {
+                Ok(Ics721Event::AckFail {
+                    transfer: a.get_transfer()?,
+                    error: a.get("error")?,
+                })
+            },
+            "ics721_timeout" => |a| {
+                Ok(Ics721Event::Timeout {
+                    transfer: a.get_transfer()?,
+                })
+            },
+            "ics721_admin_clean_and_burn" => |a| {
+                Ok(Ics721Event::AdminCleanAndBurn {
+                    class_id: ClassId::new(a.get("class_id")?),
+                    nft_contract: a.get("nft_contract")?,
+                    token_id: TokenId::new(a.get("token_id")?),
+                    owner: a.get("owner")?,
+                })
+            },
+            "ics721_admin_clean_and_unescrow" => |a| {
+                Ok(Ics721Event::AdminCleanAndUnescrow {
+                    class_id: ClassId::new(a.get("class_id")?),
+                    nft_contract: a.get("nft_contract")?,
+                    token_id: TokenId::new(a.get("token_id")?),
+                    recipient: a.get("recipient")?,
+                })
+            },
+            _ => return Err(Ics721Error::UnknownEventType(event.ty.clone())),
+        };
+        let version = attributes.get("version")?;
+        if version != ICS721_EVENT_VERSION {
+            return Err(Ics721Error::UnsupportedEventVersion(version));
+        }
+        decode(&attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer() -> Ics721HookTransfer {
+        Ics721HookTransfer {
+            channel_id: "channel-0".to_string(),
+            class_id: ClassId::new("wasm.ics721/channel-0/class"),
+            nft_contract: "nft".to_string(),
+            token_ids: vec![TokenId::new("1"), TokenId::new("a, \"b\"")],
+            sender: "sender".to_string(),
+            receiver: "receiver".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_event_roundtrip() {
+        let events = vec![
+            Ics721Event::Send {
+                transfer: transfer(),
+            },
+            Ics721Event::ReceiveMint {
+                transfer: transfer(),
+            },
+            Ics721Event::ReceiveRedeem {
+                transfer: transfer(),
+            },
+            Ics721Event::AckSuccess {
+                transfer: transfer(),
+                burned_token_ids: vec![TokenId::new("1")],
+            },
+            Ics721Event::AckFail {
+                transfer: transfer(),
+                error: "error".to_string(),
+            },
+            Ics721Event::Timeout {
+                transfer: transfer(),
+            },
+            Ics721Event::AdminCleanAndBurn {
+                class_id: ClassId::new("class"),
+                nft_contract: "nft".to_string(),
+                token_id: TokenId::new("1"),
+                owner: "owner".to_string(),
+            },
+            Ics721Event::AdminCleanAndUnescrow {
+                class_id: ClassId::new("class"),
+                nft_contract: "nft".to_string(),
+                token_id: TokenId::new("1"),
+                recipient: "recipient".to_string(),
+            },
+        ];
+        for ics721_event in events {
+            let event = Event::from(ics721_event.clone());
+            assert_eq!(Ics721Event::try_from(&event), Ok(ics721_event.clone()));
+
+            // on chain, custom events are prefixed and hold the contract address
+            let mut event = event.add_attribute("_contract_address", "ics721");
+            event.ty = format!("wasm-{}", event.ty);
+            assert_eq!(Ics721Event::try_from(&event), Ok(ics721_event));
+        }
+    }
+
+    #[test]
+    fn test_event_attributes() {
+        let event = Event::from(Ics721Event::AckSuccess {
+            transfer: transfer(),
+            burned_token_ids: vec![TokenId::new("1")],
+        });
+        assert_eq!(
+            event,
+            Event::new("ics721_ack_success")
+                .add_attribute("version", "1")
+                .add_attribute("channel_id", "channel-0")
+                .add_attribute("class_id", "wasm.ics721/channel-0/class")
+                .add_attribute("nft_contract", "nft")
+                .add_attribute("token_ids", r#"["1","a, \"b\""]"#)
+                .add_attribute("sender", "sender")
+                .add_attribute("receiver", "receiver")
+                .add_attribute("burned_token_ids", r#"["1"]"#)
+        );
+    }
+
+    #[test]
+    fn test_invalid_events() {
+        assert_eq!(
+            Ics721Event::try_from(&Event::new("transfer")),
+            Err(Ics721Error::UnknownEventType("transfer".to_string()))
+        );
+        assert_eq!(
+            Ics721Event::try_from(&Event::new("ics721_send").add_attribute("version", "0")),
+            Err(Ics721Error::UnsupportedEventVersion("0".to_string()))
+        );
+        let event = Event::from(Ics721Event::Timeout {
+            transfer: transfer(),
+        });
+        let mut missing = event.clone();
+        missing
+            .attributes
+            .retain(|attribute| attribute.key != "sender");
+        assert_eq!(
+            Ics721Event::try_from(&missing),
+            Err(Ics721Error::MissingEventAttribute {
+                event_type: "ics721_timeout".to_string(),
+                key: "sender".to_string(),
+            })
+        );
+        let mut invalid = event;
+        invalid
+            .attributes
+            .iter_mut()
+            .filter(|attribute| attribute.key == "token_ids")
+            .for_each(|attribute| attribute.value = "[1".to_string());
+        assert_eq!(
+            Ics721Event::try_from(&invalid),
+            Err(Ics721Error::InvalidEventAttribute {
+                event_type: "ics721_timeout".to_string(),
+                key: "token_ids".to_string(),
+            })
+        );
+    }
+}
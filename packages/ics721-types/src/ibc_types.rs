@@ -3,7 +3,7 @@ use cosmwasm_std::{Binary, IbcTimeout};
 
 use crate::{
     error::Ics721Error,
-    token_types::{ClassId, TokenId},
+    token_types::{ClassId, Token, TokenId},
 };
 
 #[cw_serde]
@@ -85,6 +85,63 @@ impl NonFungibleTokenPacketData {
     }
 }
 
+/// Control packets sent between ICS721 contracts on the same channel as
+/// `NonFungibleTokenPacketData`. They don't transfer any NFTs.
+#[cw_serde]
+pub enum Ics721ControlPacketData {
+    /// Sending ICS721 attests that `owner` owns the collection of
+    /// `class_id` on the sending chain (home chain). The receiving chain
+    /// records a controller for the voucher collection of this class.
+    CollectionOwnership {
+        /// Class ID of the collection on the sending chain.
+        class_id: ClassId,
+        /// Owner of the collection on the sending chain.
+        owner: String,
+        /// The address controlling the voucher collection on the
+        /// receiving chain. In case of None, the owner is converted to
+        /// an address of the receiving chain, which must be a wallet
+        /// address.
+        controller: Option<String>,
+    },
+    /// Sending ICS721 propagates updated metadata (e.g. reveals) of NFTs
+    /// it has escrowed on this channel. The receiving chain updates the
+    /// vouchers of these NFTs.
+    UpdateMetadata {
+        /// Class ID of the collection on the sending chain.
+        class_id: ClassId,
+        tokens: Vec<Token>,
+    },
+}
+
+impl Ics721ControlPacketData {
+    pub fn validate(&self) -> Result<(), Ics721Error> {
+        match self {
+            Ics721ControlPacketData::CollectionOwnership {
+                class_id,
+                owner,
+                controller,
+            } => {
+                if class_id.is_empty() {
+                    return Err(Ics721Error::EmptyClassId {});
+                }
+                if owner.is_empty() {
+                    return Err(Ics721Error::EmptyOwner {});
+                }
+                non_empty_optional!(controller.as_ref());
+            }
+            Ics721ControlPacketData::UpdateMetadata { class_id, tokens } => {
+                if class_id.is_empty() {
+                    return Err(Ics721Error::EmptyClassId {});
+                }
+                if tokens.is_empty() {
+                    return Err(Ics721Error::NoTokens {});
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cw_serde]
 pub struct IbcOutgoingMsg {
     /// The address that should receive the NFT being sent on the
@@ -93,8 +150,12 @@ pub struct IbcOutgoingMsg {
     /// The *local* channel ID this ought to be sent away on. This
     /// contract must have a connection on this channel.
     pub channel_id: String,
-    /// Timeout for the IBC message.
-    pub timeout: IbcTimeout,
+    /// Absolute timeout for the IBC message. Takes precedence over
+    /// `relative_timeout_seconds`.
+    pub timeout: Option<IbcTimeout>,
+    /// Timeout in seconds from now for the IBC message. In case both
+    /// timeouts are omitted, the channel's default timeout applies.
+    pub relative_timeout_seconds: Option<u64>,
     /// Memo to add custom string to the msg
     pub memo: Option<String>,
 }
@@ -107,6 +168,8 @@ pub struct IbcOutgoingProxyMsg {
 
 #[cfg(test)]
 mod tests {
+    use cosmwasm_std::{from_json, to_json_binary};
+
     use super::*;
     use crate::token_types::{ClassId, TokenId};
 
@@ -180,4 +243,62 @@ mod tests {
         let err = data_imbalance.validate().unwrap_err();
         assert_eq!(err, Ics721Error::TokenInfoLenMissmatch {});
     }
+
+    #[test]
+    fn test_control_packet_validation() {
+        let default_ownership = Ics721ControlPacketData::CollectionOwnership {
+            class_id: ClassId::new("id"),
+            owner: "violet".to_string(),
+            controller: None,
+        };
+        default_ownership.validate().unwrap();
+
+        let empty_class_id = Ics721ControlPacketData::CollectionOwnership {
+            class_id: ClassId::new(""),
+            owner: "violet".to_string(),
+            controller: None,
+        };
+        let err = empty_class_id.validate().unwrap_err();
+        assert_eq!(err, Ics721Error::EmptyClassId {});
+
+        let empty_owner = Ics721ControlPacketData::CollectionOwnership {
+            class_id: ClassId::new("id"),
+            owner: "".to_string(),
+            controller: None,
+        };
+        let err = empty_owner.validate().unwrap_err();
+        assert_eq!(err, Ics721Error::EmptyOwner {});
+
+        let empty_controller = Ics721ControlPacketData::CollectionOwnership {
+            class_id: ClassId::new("id"),
+            owner: "violet".to_string(),
+            controller: Some("".to_string()),
+        };
+        let err = empty_controller.validate().unwrap_err();
+        assert_eq!(err, Ics721Error::EmptyOptional {});
+
+        let no_tokens = Ics721ControlPacketData::UpdateMetadata {
+            class_id: ClassId::new("id"),
+            tokens: vec![],
+        };
+        let err = no_tokens.validate().unwrap_err();
+        assert_eq!(err, Ics721Error::NoTokens {});
+
+        // control packets and NFT packets can't be confused
+        let nft_packet = to_json_binary(&NonFungibleTokenPacketData {
+            class_id: ClassId::new("id"),
+            class_uri: None,
+            class_data: None,
+            token_ids: vec![TokenId::new("1")],
+            token_uris: None,
+            token_data: None,
+            sender: "violet".to_string(),
+            receiver: "blue".to_string(),
+            memo: None,
+        })
+        .unwrap();
+        assert!(from_json::<Ics721ControlPacketData>(&nft_packet).is_err());
+        let control_packet = to_json_binary(&default_ownership).unwrap();
+        assert!(from_json::<NonFungibleTokenPacketData>(&control_packet).is_err());
+    }
 }
@@ -1,4 +1,6 @@
+pub mod ack;
 pub mod error;
+pub mod events;
 pub mod ibc_types;
 pub mod token_types;
 pub mod types;
@@ -2,7 +2,10 @@ use cosmwasm_schema::{cw_serde, schemars::JsonSchema};
 use cosmwasm_std::{Binary, IbcPacket};
 use serde::{Deserialize, Serialize};
 
-use crate::ibc_types::NonFungibleTokenPacketData;
+use crate::{
+    ibc_types::NonFungibleTokenPacketData,
+    token_types::{ClassId, TokenId},
+};
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -57,6 +60,52 @@ pub enum Ics721Status {
     Failed(String),
 }
 
+/// A transfer a hook subscriber is notified about. Class ID and channel
+/// are the local ones.
+#[cw_serde]
+pub struct Ics721HookTransfer {
+    pub channel_id: String,
+    pub class_id: ClassId,
+    pub nft_contract: String,
+    pub token_ids: Vec<TokenId>,
+    pub sender: String,
+    pub receiver: String,
+}
+
+/// A message broadcasted to all hook subscribers registered by the
+/// ICS721 admin, regardless of the memo of a transfer.
+#[cw_serde]
+pub enum Ics721HookMsg {
+    /// NFTs are escrowed by ICS721 for an outgoing transfer. (source side)
+    Escrow { transfer: Ics721HookTransfer },
+    /// Vouchers are minted for an incoming transfer. (destination side)
+    VoucherMint { transfer: Ics721HookTransfer },
+    /// Escrowed NFTs are released for an incoming transfer returning
+    /// to its source. (destination side)
+    VoucherRedeem { transfer: Ics721HookTransfer },
+    /// Vouchers returning to their source chain are burned on a
+    /// successful ack. (source side)
+    BurnOnAck { transfer: Ics721HookTransfer },
+    /// NFTs are given back to the sender on a failed ack or timeout.
+    /// (source side)
+    Refund {
+        transfer: Ics721HookTransfer,
+        error: String,
+    },
+}
+
+impl Ics721HookMsg {
+    pub fn transfer(&self) -> &Ics721HookTransfer {
+        match self {
+            Ics721HookMsg::Escrow { transfer }
+            | Ics721HookMsg::VoucherMint { transfer }
+            | Ics721HookMsg::VoucherRedeem { transfer }
+            | Ics721HookMsg::BurnOnAck { transfer }
+            | Ics721HookMsg::Refund { transfer, .. } => transfer,
+        }
+    }
+}
+
 /// This is a wrapper for ics721 callbacks
 /// so contracts will be able to recieve both status update and on receive hook.
 #[cw_serde]
@@ -76,4 +125,8 @@ pub enum ReceiverExecuteMsg {
         packet: IbcPacket,
         data: NonFungibleTokenPacketData,
     },
+
+    /// Being called on all hook subscribers on any transfer lifecycle event.
+    /// Note - Failing this message will NOT fail the transfer.
+    Ics721Hook(Ics721HookMsg),
 }
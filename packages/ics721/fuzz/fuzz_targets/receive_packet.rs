@@ -0,0 +1,94 @@
+//! Fuzzes `ibc_packet_receive` with arbitrary packet data. Run with
+//! `cargo fuzz run receive_packet` from `packages/ics721`.
+#![no_main]
+
+use cosmwasm_std::{
+    from_json,
+    testing::{mock_dependencies, mock_env},
+    to_json_binary, Addr, Binary, CodeInfoResponse, ContractResult, HexBinary, IbcEndpoint,
+    IbcPacket, IbcPacketReceiveMsg, IbcTimeout, Order, Storage, SystemError, SystemResult,
+    Timestamp, WasmQuery,
+};
+use ics721::{
+    ibc_helpers::{get_endpoint_prefix, try_pop_source_prefix},
+    state::{CW721_CODE_ID, PO},
+};
+use ics721_types::{
+    ack::{AckResult, Ics721Ack},
+    ibc_types::{Ics721ControlPacketData, NonFungibleTokenPacketData},
+};
+use libfuzzer_sys::fuzz_target;
+
+fn snapshot(storage: &dyn Storage) -> Vec<(Vec<u8>, Vec<u8>)> {
+    storage.range(None, None, Order::Ascending).collect()
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID.save(&mut deps.storage, &0).unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    // code info is needed for instantiate2 voucher addresses
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::CodeInfo { code_id } => SystemResult::Ok(ContractResult::Ok(
+            to_json_binary(&CodeInfoResponse::new(
+                *code_id,
+                "creator".to_string(),
+                HexBinary::from(vec![1u8; 32]),
+            ))
+            .unwrap(),
+        )),
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+    let before = snapshot(&deps.storage);
+
+    let src = IbcEndpoint {
+        port_id: "wasm.source".to_string(),
+        channel_id: "channel-0".to_string(),
+    };
+    let dest = IbcEndpoint {
+        port_id: "wasm.dest".to_string(),
+        channel_id: "channel-1".to_string(),
+    };
+    let packet = IbcPacket::new(
+        Binary::from(data),
+        src.clone(),
+        dest,
+        1,
+        IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+    );
+    let res = ics721_base::ibc_packet_receive(
+        deps.as_mut(),
+        mock_env(),
+        IbcPacketReceiveMsg::new(packet, Addr::unchecked("relayer")),
+    )
+    .unwrap();
+
+    match Ics721Ack::decode(&res.acknowledgement) {
+        // errors are acked with an ICS721 error ack, leaving state untouched
+        AckResult::Error(_) => {
+            assert!(res.messages.is_empty());
+            assert_eq!(snapshot(&deps.storage), before);
+        }
+        // control packets are acked right away
+        _ if res.messages.is_empty() => {
+            assert!(from_json::<Ics721ControlPacketData>(data).is_ok());
+        }
+        // only valid transfers are processed, acked by the reply
+        _ => {
+            assert!(res.acknowledgement.is_empty());
+            let data: NonFungibleTokenPacketData = from_json(data).unwrap();
+            assert!(data.validate().is_ok());
+        }
+    }
+
+    // class ID prefixing is lossless
+    if let Ok(data) = from_json::<NonFungibleTokenPacketData>(data) {
+        let prefixed = format!("{}{}", get_endpoint_prefix(&src), data.class_id);
+        assert_eq!(
+            try_pop_source_prefix(&src, &prefixed),
+            Some(data.class_id.as_str())
+        );
+    }
+});
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Binary, Instantiate2AddressError, StdError};
+use cosmwasm_std::{Binary, Instantiate2AddressError, StdError, Timestamp};
 use cw_pause_once::PauseError;
 use cw_utils::ParseReplyError;
 use ics721_types::error::Ics721Error;
@@ -68,4 +68,77 @@ pub enum ContractError {
 
     #[error("Couldn't find class id for nft contract: {0}")]
     NoClassIdForNftContract(String),
+
+    #[error("ICS721 is not minter of nft contract {nft_contract}. Minter: {minter}")]
+    NotMinterOfNftContract {
+        nft_contract: String,
+        minter: String,
+    },
+
+    #[error("Class id {class_id} already registered for nft contract: {nft_contract}")]
+    ClassIdAlreadyRegistered {
+        class_id: String,
+        nft_contract: String,
+    },
+
+    #[error("Nft contract {nft_contract} already registered for class id: {class_id}")]
+    NftContractAlreadyRegistered {
+        nft_contract: String,
+        class_id: String,
+    },
+
+    #[error("{sender} not owner of collection {collection}! Owner: {owner}")]
+    NotOwnerOfCollection {
+        sender: String,
+        collection: String,
+        owner: String,
+    },
+
+    #[error("Collection ownership of class id {0} can only be sent by its home chain")]
+    NotHomeCollection(String),
+
+    #[error("No controller given and owner {0} is not a wallet address")]
+    OwnerNotWalletAddress(String),
+
+    #[error(
+        "Token {token_id} of class id {class_id} has not been transferred on channel {channel}"
+    )]
+    TokenNotOnChannel {
+        class_id: String,
+        token_id: String,
+        channel: String,
+    },
+
+    #[error("No pending claim for token {token_id} of class id {class_id}")]
+    NoPendingClaim { class_id: String, token_id: String },
+
+    #[error("Claim for token {token_id} of class id {class_id} expires at {expires_at}")]
+    ClaimNotExpired {
+        class_id: String,
+        token_id: String,
+        expires_at: Timestamp,
+    },
+
+    #[error("No parked transfer for sequence {sequence} on channel {channel_id}")]
+    NoParkedTransfer { channel_id: String, sequence: u64 },
+
+    #[error("Invalid receiver for channel {channel}, expected bech32 prefix {expected_prefix}")]
+    InvalidReceiver {
+        channel: String,
+        expected_prefix: String,
+    },
+
+    #[error("No timeout given and no default timeout for channel {channel}")]
+    NoTimeout { channel: String },
+
+    #[error(
+        "Timeout for channel {channel} must be a timestamp at most {max_seconds} seconds from now"
+    )]
+    TimeoutTooFar { channel: String, max_seconds: u64 },
+
+    #[error("Max {max} hook subscribers reached")]
+    TooManyHookSubscribers { max: usize },
+
+    #[error("Class id mismatch, expected: {expected}, actual: {actual}")]
+    ClassIdMismatch { expected: String, actual: String },
 }
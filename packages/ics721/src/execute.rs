@@ -1,38 +1,67 @@
 use std::fmt::Debug;
 
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Binary, ContractInfoResponse, Deps, DepsMut, Empty, Env,
-    Event, IbcMsg, MessageInfo, Order, Response, StdResult, SubMsg, WasmMsg,
+    from_json, to_json_binary, to_json_string, Addr, Binary, ContractInfoResponse, Deps, DepsMut,
+    Empty, Env, Event, IbcMsg, IbcTimeout, MessageInfo, Order, Response, StdResult, Storage,
+    SubMsg, WasmMsg,
 };
-use cw_storage_plus::Map;
+use cw_storage_plus::{Bound, IndexedMap, Map};
 use ics721_types::{
-    ibc_types::{IbcOutgoingMsg, IbcOutgoingProxyMsg, NonFungibleTokenPacketData},
+    events::Ics721Event,
+    ibc_types::{
+        IbcOutgoingMsg, IbcOutgoingProxyMsg, Ics721ControlPacketData, NonFungibleTokenPacketData,
+    },
     token_types::{Class, ClassId, Token, TokenId},
+    types::{Ics721HookMsg, Ics721HookTransfer},
 };
 use serde::{de::DeserializeOwned, Serialize};
 use sha2::{Digest, Sha256};
 
 use crate::{
-    helpers::get_instantiate2_address,
+    helpers::{
+        assert_admin, get_instantiate2_address, hook_msgs, is_stats_seeded, park_tokens,
+        resolve_outgoing_timeout, update_stats, validate_counterparty_receiver,
+        MAX_HOOK_SUBSCRIBERS,
+    },
     ibc::{
         INSTANTIATE_CW721_REPLY_ID, INSTANTIATE_INCOMING_PROXY_REPLY_ID,
-        INSTANTIATE_OUTGOING_PROXY_REPLY_ID,
+        INSTANTIATE_OUTGOING_PROXY_REPLY_ID, MIGRATE_CW721_REPLY_ID,
     },
-    msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, MigrateMsg},
+    msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, VoucherCollectionRuleKey},
     query::{
-        load_class_id_for_nft_contract, load_nft_contract_for_class_id,
-        query_nft_contract_for_class_id, query_nft_contracts,
+        load_class_id_for_nft_contract, load_nft_contract_for_class_id, load_parked_transfer,
+        load_pending_claim, load_voucher_collection_rule, query_nft_contract_for_class_id,
+        query_nft_contracts, resolve_voucher_cw721_code_id,
     },
     state::{
-        ClassIdInfo, CollectionData, UniversalAllNftInfoResponse, ADMIN_USED_FOR_CW721,
-        CLASS_ID_AND_NFT_CONTRACT_INFO, CLASS_ID_TO_CLASS, CONTRACT_ADDR_LENGTH, CW721_CODE_ID,
-        INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL,
-        OUTGOING_PROXY, PO, TOKEN_METADATA,
+        ChannelIndexCursor, ChannelIndexes, ChannelTimeoutConfig, ClaimableReceiptConfig,
+        ClassIdInfo, CollectionData, CounterpartyAddressPolicy, Cw2981Config, Cw2981Metadata,
+        HookSubscription, ParkedTransfer, ParkedTransferAction, PendingClaim, RoyaltyData,
+        RoyaltyPaymentPolicy, TokenData, TransferStats, UniversalAllNftInfoResponse,
+        UniversalNftInfoResponse, VoucherCollectionRule, ADMIN_USED_FOR_CW721,
+        CHANNEL_INDEX_CURSOR, CHANNEL_TIMEOUT_CONFIGS, CLAIMABLE_RECEIPT_CONFIG,
+        CLAIMABLE_RECEIPT_CONFIG_BY_CHANNEL, CLASS_ID_AND_NFT_CONTRACT_INFO, CLASS_ID_TO_CLASS,
+        CONTRACT_ADDR_LENGTH, COUNTERPARTY_ADDRESS_POLICIES, CW2981_CONFIG, CW2981_VOUCHERS,
+        CW721_CODE_ID, GLOBAL_STATS, HOOK_SUBSCRIBERS, INCOMING_CLASS_TOKEN_TO_CHANNEL,
+        INCOMING_PROXY, MIGRATING_VOUCHER_COLLECTIONS, OUTGOING_CLASS_TOKEN_TO_CHANNEL,
+        OUTGOING_PROXY, PARKED_TOKENS, PARKED_TRANSFERS, PARK_ON_FAILURE, PENDING_CLAIMS, PO,
+        RECEIVER_TRANSLATION_CHANNELS, RETURNING_PARKED_TOKENS, RETURNING_PENDING_CLAIMS,
+        TOKEN_METADATA, VOUCHER_CLASS_OVERRIDES, VOUCHER_COLLECTION_CONTROLLERS,
+        VOUCHER_COLLECTION_RULES_BY_CLASS_ID, VOUCHER_COLLECTION_RULES_BY_PREFIX,
     },
     token_types::{VoucherCreation, VoucherRedemption},
+    utils::{
+        convert_wallet_address, get_collection_owner, get_token_data,
+        resolve_royalty_payment_address, royalty_percentage,
+    },
     ContractError,
 };
 
+const DEFAULT_MIGRATE_VOUCHER_COLLECTIONS_LIMIT: u32 = 10;
+const MAX_MIGRATE_VOUCHER_COLLECTIONS_LIMIT: u32 = 30;
+const DEFAULT_BUILD_CHANNEL_INDEXES_LIMIT: u32 = 100;
+const MAX_BUILD_CHANNEL_INDEXES_LIMIT: u32 = 500;
+
 pub trait Ics721Execute<T = Empty>
 where
     T: Serialize + DeserializeOwned + Clone,
@@ -130,6 +159,100 @@ where
             } => self.execute_admin_clean_and_unescrow_nft(
                 deps, env, info, recipient, token_id, class_id, collection,
             ),
+            ExecuteMsg::RegisterVoucherCollection {
+                class_id,
+                nft_contract,
+                class,
+            } => self.execute_register_voucher_collection(
+                deps,
+                env,
+                info,
+                class_id,
+                nft_contract,
+                class,
+            ),
+            ExecuteMsg::MigrateVoucherCollections {
+                new_code_id,
+                msg,
+                start_after,
+                limit,
+            } => self.execute_migrate_voucher_collections(
+                deps,
+                env,
+                info,
+                new_code_id,
+                msg,
+                start_after,
+                limit,
+            ),
+            ExecuteMsg::SetVoucherCollectionRule { key, rule } => {
+                self.execute_set_voucher_collection_rule(deps, env, info, key, rule)
+            }
+            ExecuteMsg::SetCw2981Config { config } => {
+                self.execute_set_cw2981_config(deps, env, info, config)
+            }
+            ExecuteMsg::SendCollectionOwnership {
+                collection,
+                channel_id,
+                controller,
+                timeout,
+            } => self.execute_send_collection_ownership(
+                deps, info, collection, channel_id, controller, timeout,
+            ),
+            ExecuteMsg::UpdateVoucherClassMetadata {
+                class_id,
+                uri,
+                data,
+            } => self.execute_update_voucher_class_metadata(deps, env, info, class_id, uri, data),
+            ExecuteMsg::SendMetadataUpdate {
+                collection,
+                token_ids,
+                channel_id,
+                timeout,
+            } => self.execute_send_metadata_update(
+                deps, env, info, collection, token_ids, channel_id, timeout,
+            ),
+            ExecuteMsg::SetClaimableReceiptConfig { channel_id, config } => {
+                self.execute_set_claimable_receipt_config(deps, env, info, channel_id, config)
+            }
+            ExecuteMsg::ClaimNfts {
+                class_id,
+                token_ids,
+            } => self.execute_claim_nfts(deps, info, class_id, token_ids),
+            ExecuteMsg::RejectNfts {
+                class_id,
+                token_ids,
+            } => self.return_pending_claims(deps, env, Some(info.sender), class_id, token_ids),
+            ExecuteMsg::ReturnExpiredClaims {
+                class_id,
+                token_ids,
+            } => self.return_pending_claims(deps, env, None, class_id, token_ids),
+            ExecuteMsg::SetParkOnFailure { enabled } => {
+                self.execute_set_park_on_failure(deps, env, info, enabled)
+            }
+            ExecuteMsg::ResolveParkedTransfer {
+                channel_id,
+                sequence,
+                action,
+            } => {
+                self.execute_resolve_parked_transfer(deps, env, info, channel_id, sequence, action)
+            }
+            ExecuteMsg::SetReceiverTranslation {
+                channel_id,
+                enabled,
+            } => self.execute_set_receiver_translation(deps, env, info, channel_id, enabled),
+            ExecuteMsg::SetCounterpartyAddressPolicy { channel_id, policy } => {
+                self.execute_set_counterparty_address_policy(deps, env, info, channel_id, policy)
+            }
+            ExecuteMsg::SetChannelTimeoutConfig { channel_id, config } => {
+                self.execute_set_channel_timeout_config(deps, env, info, channel_id, config)
+            }
+            ExecuteMsg::SetHookSubscriber { addr, subscription } => {
+                self.execute_set_hook_subscriber(deps, env, info, addr, subscription)
+            }
+            ExecuteMsg::BuildChannelIndexes { limit } => {
+                self.execute_build_channel_indexes(deps, limit)
+            }
         }
     }
 
@@ -176,8 +299,17 @@ where
         }
 
         // remove incoming channel entry and metadata
-        INCOMING_CLASS_TOKEN_TO_CHANNEL
-            .remove(deps.storage, (child_class_id.clone(), token_id.clone()));
+        let key = (child_class_id.clone(), token_id.clone());
+        if let Some(channel_id) =
+            INCOMING_CLASS_TOKEN_TO_CHANNEL.may_load(deps.storage, key.clone())?
+        {
+            if is_stats_seeded(deps.storage, true, &key)? {
+                update_stats(deps.storage, &child_class_id, &channel_id, |stats| {
+                    stats.vouchers_burned += 1
+                })?;
+            }
+        }
+        INCOMING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key)?;
         TOKEN_METADATA.remove(deps.storage, (child_class_id.clone(), token_id.clone()));
 
         // check NFT on child collection owned by recipient
@@ -192,8 +324,17 @@ where
             )
             .ok();
 
-        let mut response =
-            Response::default().add_attribute("method", "execute_admin_clean_and_burn_nft");
+        let mut response = Response::default()
+            .add_attribute("method", "execute_admin_clean_and_burn_nft")
+            .add_event(
+                Ics721Event::AdminCleanAndBurn {
+                    class_id: child_class_id,
+                    nft_contract: child_collection.to_string(),
+                    token_id: token_id.clone(),
+                    owner: owner.clone(),
+                }
+                .into(),
+            );
         if let Some(UniversalAllNftInfoResponse { access, .. }) = maybe_nft_info {
             if access.owner != owner {
                 return Err(ContractError::NotOwnerOfNft {
@@ -260,8 +401,17 @@ where
 
         // remove outgoing channel entry
         let token_id = TokenId::new(token_id);
-        OUTGOING_CLASS_TOKEN_TO_CHANNEL
-            .remove(deps.storage, (home_class_id.clone(), token_id.clone()));
+        let key = (home_class_id.clone(), token_id.clone());
+        if let Some(channel_id) =
+            OUTGOING_CLASS_TOKEN_TO_CHANNEL.may_load(deps.storage, key.clone())?
+        {
+            if is_stats_seeded(deps.storage, false, &key)? {
+                update_stats(deps.storage, &home_class_id, &channel_id, |stats| {
+                    stats.escrowed = stats.escrowed.saturating_sub(1)
+                })?;
+            }
+        }
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key)?;
 
         // check NFT on home collection owned by ics721 contract
         let maybe_nft_info: Option<UniversalAllNftInfoResponse> = deps
@@ -275,8 +425,17 @@ where
             )
             .ok();
 
-        let mut response =
-            Response::default().add_attribute("method", "execute_admin_clean_and_unescrow_nft");
+        let mut response = Response::default()
+            .add_attribute("method", "execute_admin_clean_and_unescrow_nft")
+            .add_event(
+                Ics721Event::AdminCleanAndUnescrow {
+                    class_id: home_class_id,
+                    nft_contract: home_collection.to_string(),
+                    token_id: token_id.clone(),
+                    recipient: recipient.clone(),
+                }
+                .into(),
+            );
         if let Some(UniversalAllNftInfoResponse { access, .. }) = maybe_nft_info {
             if access.owner != env.contract.address {
                 return Err(ContractError::NotEscrowedByIcs721(access.owner.to_string()));
@@ -297,6 +456,754 @@ where
         Ok(response)
     }
 
+    /// Links an existing cw721 contract to a class ID, so incoming NFTs
+    /// of this class ID are minted on it.
+    fn execute_register_voucher_collection(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        nft_contract: String,
+        class: Option<Class>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+
+        let class_id = ClassId::new(class_id);
+        let nft_contract = deps.api.addr_validate(&nft_contract)?;
+        let class = match class {
+            Some(class) => {
+                if class.id != class_id {
+                    return Err(ContractError::ClassIdMismatch {
+                        expected: class_id.to_string(),
+                        actual: class.id.to_string(),
+                    });
+                }
+                class
+            }
+            None => Class {
+                id: class_id.clone(),
+                uri: None,
+                data: None,
+            },
+        };
+
+        // class id and nft contract must both be unknown
+        if let Some(cw721_addr) = query_nft_contract_for_class_id(deps.storage, class_id.clone())? {
+            return Err(ContractError::ClassIdAlreadyRegistered {
+                class_id: class_id.to_string(),
+                nft_contract: cw721_addr.to_string(),
+            });
+        }
+        if let Some(registered_class_id) =
+            load_class_id_for_nft_contract(deps.storage, &nft_contract)?
+        {
+            return Err(ContractError::NftContractAlreadyRegistered {
+                nft_contract: nft_contract.to_string(),
+                class_id: registered_class_id.to_string(),
+            });
+        }
+
+        // ics721 mints vouchers, so it must be minter
+        let minter = get_collection_owner(deps.as_ref(), &nft_contract)?;
+        if minter.as_deref() != Some(env.contract.address.as_str()) {
+            return Err(ContractError::NotMinterOfNftContract {
+                nft_contract: nft_contract.to_string(),
+                minter: minter.unwrap_or_default(),
+            });
+        }
+
+        let class_id_info = ClassIdInfo {
+            class_id: class_id.clone(),
+            address: nft_contract.clone(),
+        };
+        CLASS_ID_AND_NFT_CONTRACT_INFO.save(deps.storage, &class_id, &class_id_info)?;
+        CLASS_ID_TO_CLASS.save(deps.storage, class_id.clone(), &class)?;
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_register_voucher_collection")
+            .add_attribute("class_id", class_id)
+            .add_attribute("nft_contract", nft_contract))
+    }
+
+    /// Migrates a page of voucher collections to a new cw721 code ID.
+    /// Each migration is a submessage, so a failing collection doesn't
+    /// revert the others.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_migrate_voucher_collections(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        new_code_id: u64,
+        msg: Binary,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+
+        let limit = limit
+            .unwrap_or(DEFAULT_MIGRATE_VOUCHER_COLLECTIONS_LIMIT)
+            .clamp(1, MAX_MIGRATE_VOUCHER_COLLECTIONS_LIMIT);
+        // native classes are keyed by their collection address, they are
+        // skipped without querying them
+        let start = start_after.map(|s| Bound::ExclusiveRaw(s.into_bytes()));
+        let nft_contracts = CLASS_ID_AND_NFT_CONTRACT_INFO
+            .range(deps.storage, start, None, Order::Ascending)
+            .filter(|item| {
+                item.as_ref().map_or(true, |(class_id, info)| {
+                    class_id.as_str() != info.address.as_str()
+                })
+            })
+            .take(limit as usize)
+            .map(|item| item.map(|(class_id, info)| (class_id, info.address)))
+            .collect::<StdResult<Vec<_>>>()?;
+        let next_start_after = match nft_contracts.last() {
+            Some((class_id, _)) if nft_contracts.len() == limit as usize => Some(class_id.clone()),
+            _ => None,
+        };
+
+        let mut migrating = vec![];
+        let mut submessages = vec![];
+        let mut events = vec![];
+        for (class_id, nft_contract) in nft_contracts {
+            // only ics721 as admin can migrate
+            let ContractInfoResponse { admin, .. } =
+                deps.querier.query_wasm_contract_info(&nft_contract)?;
+            if admin.as_deref() != Some(env.contract.address.as_str()) {
+                events.push(
+                    Event::new("ics721_migrate_voucher_collection")
+                        .add_attribute("class_id", class_id)
+                        .add_attribute("nft_contract", nft_contract)
+                        .add_attribute("result", "skipped: ics721 not admin"),
+                );
+                continue;
+            }
+            submessages.push(SubMsg::<T>::reply_always(
+                WasmMsg::Migrate {
+                    contract_addr: nft_contract.to_string(),
+                    new_code_id,
+                    msg: msg.clone(),
+                },
+                MIGRATE_CW721_REPLY_ID,
+            ));
+            migrating.push(ClassIdInfo {
+                class_id: ClassId::new(class_id),
+                address: nft_contract,
+            });
+        }
+        if !migrating.is_empty() {
+            MIGRATING_VOUCHER_COLLECTIONS.save(deps.storage, &migrating)?;
+        }
+
+        let response = Response::default()
+            .add_attribute("method", "execute_migrate_voucher_collections")
+            .add_attribute("new_code_id", new_code_id.to_string())
+            .add_attribute("done", next_start_after.is_none().to_string());
+        Ok(match next_start_after {
+            Some(next_start_after) => response.add_attribute("next_start_after", next_start_after),
+            None => response,
+        }
+        .add_events(events)
+        .add_submessages(submessages))
+    }
+
+    fn execute_set_voucher_collection_rule(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        key: VoucherCollectionRuleKey,
+        rule: Option<VoucherCollectionRule>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+
+        if let Some(admin) = rule.as_ref().and_then(|r| r.admin.as_ref()) {
+            if !admin.is_empty() {
+                deps.api.addr_validate(admin)?;
+            }
+        }
+        let (key_type, key) = match key {
+            VoucherCollectionRuleKey::ClassId(class_id) => {
+                let class_id = ClassId::new(class_id);
+                match &rule {
+                    Some(rule) => VOUCHER_COLLECTION_RULES_BY_CLASS_ID.save(
+                        deps.storage,
+                        class_id.clone(),
+                        rule,
+                    )?,
+                    None => {
+                        VOUCHER_COLLECTION_RULES_BY_CLASS_ID.remove(deps.storage, class_id.clone())
+                    }
+                }
+                ("class_id", class_id.to_string())
+            }
+            VoucherCollectionRuleKey::Prefix(prefix) => {
+                match &rule {
+                    Some(rule) => {
+                        VOUCHER_COLLECTION_RULES_BY_PREFIX.save(deps.storage, &prefix, rule)?
+                    }
+                    None => VOUCHER_COLLECTION_RULES_BY_PREFIX.remove(deps.storage, &prefix),
+                }
+                ("prefix", prefix)
+            }
+        };
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_voucher_collection_rule")
+            .add_attribute(key_type, key)
+            .add_attribute(
+                "code_id",
+                rule.map_or_else(|| "removed".to_string(), |r| r.code_id.to_string()),
+            ))
+    }
+
+    fn execute_set_cw2981_config(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        config: Option<Cw2981Config>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+
+        match &config {
+            Some(config) => {
+                match &config.payment_policy {
+                    RoyaltyPaymentPolicy::Fixed { address } => deps.api.addr_validate(address)?,
+                    RoyaltyPaymentPolicy::ConvertWalletAddress { fallback } => {
+                        deps.api.addr_validate(fallback)?
+                    }
+                };
+                CW2981_CONFIG.save(deps.storage, config)?;
+            }
+            None => CW2981_CONFIG.remove(deps.storage),
+        }
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_cw2981_config")
+            .add_attribute(
+                "cw2981_code_id",
+                config.map_or_else(|| "none".to_string(), |c| c.code_id.to_string()),
+            ))
+    }
+
+    fn execute_send_collection_ownership(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        collection: String,
+        channel_id: String,
+        controller: Option<String>,
+        timeout: IbcTimeout,
+    ) -> Result<Response<T>, ContractError> {
+        let collection = deps.api.addr_validate(&collection)?;
+        // voucher collections have a different class id, only home chain may attest ownership
+        if let Some(class_id) = load_class_id_for_nft_contract(deps.storage, &collection)? {
+            if class_id.as_str() != collection.as_str() {
+                return Err(ContractError::NotHomeCollection(class_id.to_string()));
+            }
+        }
+        let owner = get_collection_owner(deps.as_ref(), &collection)?;
+        if owner.as_deref() != Some(info.sender.as_str()) {
+            return Err(ContractError::NotOwnerOfCollection {
+                sender: info.sender.to_string(),
+                collection: collection.to_string(),
+                owner: owner.unwrap_or_else(|| "none".to_string()),
+            });
+        }
+
+        let packet = Ics721ControlPacketData::CollectionOwnership {
+            class_id: ClassId::new(collection.to_string()),
+            owner: info.sender.to_string(),
+            controller: controller.clone(),
+        };
+        packet.validate()?;
+        let ibc_message = IbcMsg::SendPacket {
+            channel_id: channel_id.clone(),
+            data: to_json_binary(&packet)?,
+            timeout,
+        };
+
+        Ok(Response::default()
+            .add_message(ibc_message)
+            .add_attribute("method", "execute_send_collection_ownership")
+            .add_attribute("collection", collection)
+            .add_attribute("owner", info.sender)
+            .add_attribute(
+                "controller",
+                controller.unwrap_or_else(|| "none".to_string()),
+            )
+            .add_attribute("channel_id", channel_id))
+    }
+
+    fn execute_update_voucher_class_metadata(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        class_id: String,
+        uri: Option<String>,
+        data: Option<Binary>,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let controller = VOUCHER_COLLECTION_CONTROLLERS.may_load(deps.storage, class_id.clone())?;
+        if controller.map_or(true, |c| c.controller != info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        // class is stored once voucher collection has been created
+        let Some(old_class) = CLASS_ID_TO_CLASS.may_load(deps.storage, class_id.clone())? else {
+            return Err(ContractError::NoNftContractForClassId(class_id.to_string()));
+        };
+        let class = Class {
+            id: class_id.clone(),
+            uri,
+            data,
+        };
+        CLASS_ID_TO_CLASS.save(deps.storage, class_id.clone(), &class)?;
+        // from now on, incoming transfers don't overwrite the class
+        VOUCHER_CLASS_OVERRIDES.save(deps.storage, class_id.clone(), &Empty {})?;
+        let class_update = match query_nft_contract_for_class_id(deps.storage, class_id.clone())? {
+            Some(nft_contract) => {
+                self.update_class_msg(deps.as_ref(), &env, &nft_contract, &old_class, &class)?
+            }
+            None => None,
+        };
+
+        Ok(Response::default()
+            .add_messages(class_update)
+            .add_event(class_updated_event(&old_class, &class))
+            .add_attribute("method", "execute_update_voucher_class_metadata")
+            .add_attribute("class_id", class_id)
+            .add_attribute("controller", info.sender)
+            .add_attribute("uri", class.uri.unwrap_or_else(|| "none".to_string())))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_send_metadata_update(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        collection: String,
+        token_ids: Vec<String>,
+        channel_id: String,
+        timeout: IbcTimeout,
+    ) -> Result<Response<T>, ContractError> {
+        let collection = deps.api.addr_validate(&collection)?;
+        // only ICS721 admin or collection owner may push metadata to vouchers,
+        // collections without a queryable owner can only be updated by the admin
+        if assert_admin(deps.as_ref(), &env, &info.sender).is_err() {
+            let owner = get_collection_owner(deps.as_ref(), &collection)
+                .ok()
+                .flatten();
+            if owner.as_deref() != Some(info.sender.as_str()) {
+                return Err(ContractError::Unauthorized {});
+            }
+        }
+        let class_id = load_class_id_for_nft_contract(deps.storage, &collection)?
+            .ok_or_else(|| ContractError::NoClassIdForNftContract(collection.to_string()))?;
+
+        let tokens = token_ids
+            .into_iter()
+            .map(|token_id| -> Result<Token, ContractError> {
+                let token_id = TokenId::new(token_id);
+                // only NFTs escrowed for this channel have vouchers on the receiving chain
+                let outgoing_channel = OUTGOING_CLASS_TOKEN_TO_CHANNEL
+                    .may_load(deps.storage, (class_id.clone(), token_id.clone()))?;
+                if outgoing_channel.as_deref() != Some(channel_id.as_str()) {
+                    return Err(ContractError::TokenNotOnChannel {
+                        class_id: class_id.to_string(),
+                        token_id: token_id.into(),
+                        channel: channel_id.clone(),
+                    });
+                }
+                let UniversalNftInfoResponse { token_uri, .. } = deps.querier.query_wasm_smart(
+                    &collection,
+                    &cw721::Cw721QueryMsg::NftInfo {
+                        token_id: token_id.clone().into(),
+                    },
+                )?;
+                let data = get_token_data(deps.as_ref(), &class_id, &collection, &token_id)?;
+                Ok(Token {
+                    id: token_id,
+                    uri: token_uri,
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let token_ids = to_json_string(&tokens.iter().map(|t| t.id.clone()).collect::<Vec<_>>())?;
+
+        let packet = Ics721ControlPacketData::UpdateMetadata {
+            class_id: class_id.clone(),
+            tokens,
+        };
+        packet.validate()?;
+        let ibc_message = IbcMsg::SendPacket {
+            channel_id: channel_id.clone(),
+            data: to_json_binary(&packet)?,
+            timeout,
+        };
+
+        Ok(Response::default()
+            .add_message(ibc_message)
+            .add_attribute("method", "execute_send_metadata_update")
+            .add_attribute("class_id", class_id)
+            .add_attribute("token_ids", token_ids)
+            .add_attribute("channel_id", channel_id))
+    }
+
+    fn execute_set_claimable_receipt_config(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        channel_id: Option<String>,
+        config: Option<ClaimableReceiptConfig>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+
+        match (&channel_id, &config) {
+            (Some(channel_id), Some(config)) => {
+                CLAIMABLE_RECEIPT_CONFIG_BY_CHANNEL.save(deps.storage, channel_id, config)?
+            }
+            (Some(channel_id), None) => {
+                CLAIMABLE_RECEIPT_CONFIG_BY_CHANNEL.remove(deps.storage, channel_id)
+            }
+            (None, Some(config)) => CLAIMABLE_RECEIPT_CONFIG.save(deps.storage, config)?,
+            (None, None) => CLAIMABLE_RECEIPT_CONFIG.remove(deps.storage),
+        }
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_claimable_receipt_config")
+            .add_attribute(
+                "channel_id",
+                channel_id.unwrap_or_else(|| "global".to_string()),
+            )
+            .add_attribute(
+                "enabled",
+                config.map_or_else(|| "none".to_string(), |c| c.enabled.to_string()),
+            ))
+    }
+
+    fn execute_claim_nfts(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        class_id: String,
+        token_ids: Vec<String>,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+        let messages = token_ids
+            .iter()
+            .map(|token_id| -> Result<WasmMsg, ContractError> {
+                let key = (class_id.clone(), TokenId::new(token_id));
+                let claim = load_pending_claim(deps.storage, key.clone())?;
+                if claim.receiver != info.sender {
+                    return Err(ContractError::Unauthorized {});
+                }
+                PENDING_CLAIMS.remove(deps.storage, key);
+                Ok(WasmMsg::Execute {
+                    contract_addr: nft_contract.to_string(),
+                    msg: to_json_binary(&cw721::Cw721ExecuteMsg::TransferNft {
+                        recipient: info.sender.to_string(),
+                        token_id: token_id.clone(),
+                    })?,
+                    funds: vec![],
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Response::default()
+            .add_messages(messages)
+            .add_attribute("method", "execute_claim_nfts")
+            .add_attribute("class_id", class_id)
+            .add_attribute("token_ids", to_json_string(&token_ids)?)
+            .add_attribute("receiver", info.sender))
+    }
+
+    /// Sends vouchers pending in claimable receipt mode back to the sender
+    /// on the source chain. In case of a receiver, only the receiver may
+    /// return them. Otherwise anyone may return expired claims. ICS721 is
+    /// the sender, so in case the return fails, the claims are pending
+    /// again. The timeout is the channel's default timeout, since anyone
+    /// may return expired claims.
+    fn return_pending_claims(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        receiver: Option<Addr>,
+        class_id: String,
+        token_ids: Vec<String>,
+    ) -> Result<Response<T>, ContractError> {
+        let class_id = ClassId::new(class_id);
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+        let method = match receiver {
+            Some(_) => "execute_reject_nfts",
+            None => "execute_return_expired_claims",
+        };
+        let mut response = Response::default()
+            .add_attribute("method", method)
+            .add_attribute("class_id", class_id.clone())
+            .add_attribute("token_ids", to_json_string(&token_ids)?);
+        for token_id in token_ids {
+            let key = (class_id.clone(), TokenId::new(token_id.clone()));
+            let claim = load_pending_claim(deps.storage, key.clone())?;
+            match &receiver {
+                Some(receiver) if &claim.receiver != receiver => {
+                    return Err(ContractError::Unauthorized {})
+                }
+                None if env.block.time < claim.expires_at => {
+                    return Err(ContractError::ClaimNotExpired {
+                        class_id: class_id.to_string(),
+                        token_id,
+                        expires_at: claim.expires_at,
+                    })
+                }
+                _ => {}
+            }
+            let timeout =
+                resolve_outgoing_timeout(deps.storage, &env, &claim.channel_id, None, None)?;
+            PENDING_CLAIMS.remove(deps.storage, key.clone());
+            RETURNING_PENDING_CLAIMS.save(deps.storage, key, &claim)?;
+
+            // voucher is held by ICS721, so it is sent back like any other outgoing NFT
+            let res = self.send_nft(
+                deps.branch(),
+                env.clone(),
+                &nft_contract,
+                TokenId::new(token_id),
+                env.contract.address.to_string(),
+                IbcOutgoingMsg {
+                    receiver: claim.sender,
+                    channel_id: claim.channel_id,
+                    timeout: Some(timeout.clone()),
+                    relative_timeout_seconds: None,
+                    memo: None,
+                },
+                timeout,
+            )?;
+            response = response
+                .add_submessages(res.messages)
+                .add_events(res.events);
+        }
+        Ok(response)
+    }
+
+    fn execute_set_park_on_failure(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        enabled: bool,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+        PARK_ON_FAILURE.save(deps.storage, &enabled)?;
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_park_on_failure")
+            .add_attribute("enabled", enabled.to_string()))
+    }
+
+    /// Delivers or returns NFTs held in ICS721 custody by a parked
+    /// transfer. On return, ICS721 is the sender of the NFTs, so in case
+    /// the return fails, they are parked again.
+    fn execute_resolve_parked_transfer(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        channel_id: String,
+        sequence: u64,
+        action: ParkedTransferAction,
+    ) -> Result<Response<T>, ContractError> {
+        let transfer = load_parked_transfer(deps.storage, &channel_id, sequence)?;
+        // the sender is on the source chain, so it resolves using its account on this chain
+        let is_sender = convert_wallet_address(deps.as_ref(), &env, &transfer.data.sender)
+            .map_or(false, |sender| sender == info.sender.as_str());
+        if !is_sender {
+            assert_admin(deps.as_ref(), &env, &info.sender)?;
+        }
+        PARKED_TRANSFERS.remove(deps.storage, (&channel_id, sequence));
+        for token_id in &transfer.data.token_ids {
+            PARKED_TOKENS.remove(deps.storage, (transfer.class_id.clone(), token_id.clone()));
+        }
+
+        let nft_contract =
+            load_nft_contract_for_class_id(deps.storage, transfer.class_id.to_string())?;
+        let response = Response::default()
+            .add_attribute("method", "execute_resolve_parked_transfer")
+            .add_attribute("channel_id", channel_id.clone())
+            .add_attribute("sequence", sequence.to_string())
+            .add_attribute("class_id", transfer.class_id.clone())
+            .add_attribute("token_ids", to_json_string(&transfer.data.token_ids)?);
+        match action {
+            ParkedTransferAction::Deliver { receiver } => {
+                let receiver = deps.api.addr_validate(&receiver)?;
+                let messages = transfer
+                    .data
+                    .token_ids
+                    .into_iter()
+                    .map(|token_id| -> StdResult<WasmMsg> {
+                        Ok(WasmMsg::Execute {
+                            contract_addr: nft_contract.to_string(),
+                            msg: to_json_binary(&cw721::Cw721ExecuteMsg::TransferNft {
+                                recipient: receiver.to_string(),
+                                token_id: token_id.into(),
+                            })?,
+                            funds: vec![],
+                        })
+                    })
+                    .collect::<StdResult<Vec<_>>>()?;
+                Ok(response
+                    .add_messages(messages)
+                    .add_attribute("receiver", receiver))
+            }
+            ParkedTransferAction::Return { timeout } => {
+                let mut response = response.add_attribute("receiver", &transfer.data.sender);
+                for token_id in transfer.data.token_ids.clone() {
+                    RETURNING_PARKED_TOKENS.save(
+                        deps.storage,
+                        (transfer.class_id.clone(), token_id.clone()),
+                        &((channel_id.clone(), sequence), transfer.clone()),
+                    )?;
+                    // NFT is held by ICS721, so it is sent back like any other outgoing NFT
+                    let res = self.send_nft(
+                        deps.branch(),
+                        env.clone(),
+                        &nft_contract,
+                        token_id,
+                        env.contract.address.to_string(),
+                        IbcOutgoingMsg {
+                            receiver: transfer.data.sender.clone(),
+                            channel_id: channel_id.clone(),
+                            timeout: Some(timeout.clone()),
+                            relative_timeout_seconds: None,
+                            memo: None,
+                        },
+                        timeout.clone(),
+                    )?;
+                    response = response
+                        .add_submessages(res.messages)
+                        .add_events(res.events);
+                }
+                Ok(response)
+            }
+        }
+    }
+
+    fn execute_set_receiver_translation(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        channel_id: String,
+        enabled: bool,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+        match enabled {
+            true => RECEIVER_TRANSLATION_CHANNELS.save(deps.storage, &channel_id, &Empty {})?,
+            false => RECEIVER_TRANSLATION_CHANNELS.remove(deps.storage, &channel_id),
+        }
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_receiver_translation")
+            .add_attribute("channel_id", channel_id)
+            .add_attribute("enabled", enabled.to_string()))
+    }
+
+    fn execute_set_counterparty_address_policy(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        channel_id: String,
+        policy: Option<CounterpartyAddressPolicy>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+        match &policy {
+            Some(policy) => {
+                COUNTERPARTY_ADDRESS_POLICIES.save(deps.storage, &channel_id, policy)?
+            }
+            None => COUNTERPARTY_ADDRESS_POLICIES.remove(deps.storage, &channel_id),
+        }
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_counterparty_address_policy")
+            .add_attribute("channel_id", channel_id)
+            .add_attribute(
+                "prefix",
+                policy.map_or_else(|| "none".to_string(), |policy| policy.prefix),
+            ))
+    }
+
+    fn execute_set_channel_timeout_config(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        channel_id: String,
+        config: Option<ChannelTimeoutConfig>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+        match &config {
+            Some(config) => CHANNEL_TIMEOUT_CONFIGS.save(deps.storage, &channel_id, config)?,
+            None => CHANNEL_TIMEOUT_CONFIGS.remove(deps.storage, &channel_id),
+        }
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_channel_timeout_config")
+            .add_attribute("channel_id", channel_id)
+            .add_attribute("config", format!("{config:?}")))
+    }
+
+    fn execute_set_hook_subscriber(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        addr: String,
+        subscription: Option<HookSubscription>,
+    ) -> Result<Response<T>, ContractError> {
+        assert_admin(deps.as_ref(), &env, &info.sender)?;
+        let addr = deps.api.addr_validate(&addr)?;
+        match &subscription {
+            Some(subscription) => {
+                let count = HOOK_SUBSCRIBERS
+                    .keys(deps.storage, None, None, Order::Ascending)
+                    .count();
+                if !HOOK_SUBSCRIBERS.has(deps.storage, &addr) && count >= MAX_HOOK_SUBSCRIBERS {
+                    return Err(ContractError::TooManyHookSubscribers {
+                        max: MAX_HOOK_SUBSCRIBERS,
+                    });
+                }
+                HOOK_SUBSCRIBERS.save(deps.storage, &addr, subscription)?
+            }
+            None => HOOK_SUBSCRIBERS.remove(deps.storage, &addr),
+        }
+
+        Ok(Response::default()
+            .add_attribute("method", "execute_set_hook_subscriber")
+            .add_attribute("addr", addr)
+            .add_attribute("subscription", format!("{subscription:?}")))
+    }
+
+    fn execute_build_channel_indexes(
+        &self,
+        deps: DepsMut,
+        limit: Option<u32>,
+    ) -> Result<Response<T>, ContractError> {
+        let limit = limit
+            .unwrap_or(DEFAULT_BUILD_CHANNEL_INDEXES_LIMIT)
+            .clamp(1, MAX_BUILD_CHANNEL_INDEXES_LIMIT);
+        let (indexed, done) = build_channel_indexes(deps.storage, limit as usize)?;
+        Ok(Response::default()
+            .add_attribute("method", "execute_build_channel_indexes")
+            .add_attribute("indexed_channel_entries", indexed.to_string())
+            .add_attribute("done", done.to_string()))
+    }
+
     /// ICS721 may receive an NFT from 2 sources:
     /// 1. From a local cw721 contract (e.g. cw721-base)
     /// 2. From a(n outgoing) proxy contract.
@@ -366,9 +1273,36 @@ where
         nft_owner: String,
         msg: Binary,
     ) -> Result<Response<T>, ContractError> {
-        let nft_owner = deps.api.addr_validate(&nft_owner)?;
         let msg: IbcOutgoingMsg = from_json(msg)?;
+        validate_counterparty_receiver(deps.storage, &msg.channel_id, &msg.receiver)?;
+        let timeout = resolve_outgoing_timeout(
+            deps.storage,
+            &env,
+            &msg.channel_id,
+            msg.timeout.clone(),
+            msg.relative_timeout_seconds,
+        )?;
+        self.send_nft(deps, env, nft_contract, token_id, nft_owner, msg, timeout)
+    }
 
+    /// Sends an NFT escrowed by ICS721 to the receiver on the other
+    /// chain. Unlike `receive_nft`, the receiver isn't checked against
+    /// the counterparty address policy, since refunds of pending claims
+    /// and parked transfers go back to the original sender. The timeout
+    /// of a returned parked transfer isn't checked against the channel's
+    /// timeout config either.
+    #[allow(clippy::too_many_arguments)]
+    fn send_nft(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        nft_contract: &Addr,
+        token_id: TokenId,
+        nft_owner: String,
+        msg: IbcOutgoingMsg,
+        timeout: IbcTimeout,
+    ) -> Result<Response<T>, ContractError> {
+        let nft_owner = deps.api.addr_validate(&nft_owner)?;
         let class = match load_class_id_for_nft_contract(deps.as_ref().storage, nft_contract)? {
             Some(class_id) => CLASS_ID_TO_CLASS.load(deps.storage, class_id)?,
             // No class ID being present means that this is a local NFT
@@ -413,10 +1347,22 @@ where
         // cw721 doesn't support on-chain metadata yet
         // here NFT is transferred to another chain, NFT itself may have been transferred to his chain before
         // in this case ICS721 may have metadata stored
-        let token_metadata = TOKEN_METADATA
-            .may_load(deps.storage, (class.id.clone(), token_id.clone()))?
-            .flatten();
+        let token_metadata = get_token_data(deps.as_ref(), &class.id, nft_contract, &token_id)?;
 
+        let transfer = Ics721HookTransfer {
+            channel_id: msg.channel_id.clone(),
+            class_id: class.id.clone(),
+            nft_contract: nft_contract.to_string(),
+            token_ids: vec![token_id.clone()],
+            sender: nft_owner.to_string(),
+            receiver: msg.receiver.clone(),
+        };
+        let hooks = hook_msgs(
+            deps.storage,
+            &Ics721HookMsg::Escrow {
+                transfer: transfer.clone(),
+            },
+        )?;
         let ibc_message = NonFungibleTokenPacketData {
             class_id: class.id.clone(),
             class_uri: class.uri,
@@ -433,19 +1379,23 @@ where
         let ibc_message = IbcMsg::SendPacket {
             channel_id: msg.channel_id.clone(),
             data: to_json_binary(&ibc_message)?,
-            timeout: msg.timeout,
+            timeout,
         };
 
-        OUTGOING_CLASS_TOKEN_TO_CHANNEL.save(
-            deps.storage,
-            (class.id.clone(), token_id.clone()),
-            &msg.channel_id,
-        )?;
+        let key = (class.id.clone(), token_id.clone());
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL.save(deps.storage, key.clone(), &msg.channel_id)?;
+        let is_seeded = is_stats_seeded(deps.storage, false, &key)?;
+        update_stats(deps.storage, &class.id, &msg.channel_id, |stats| {
+            if is_seeded {
+                stats.escrowed += 1;
+            }
+            stats.in_flight += 1;
+        })?;
         // class_data might be collection data (if it comes from ICS721 contract) or some custom data (e.g. coming from nft-transfer module)
-        // so only can output binary here
+        // so only can output binary here, base64 encoded
         let class_data_string = class
             .data
-            .map_or("none".to_string(), |data| format!("{data:?}"));
+            .map_or("none".to_string(), |data| data.to_base64());
 
         Ok(Response::default()
             .add_attribute("method", "execute_receive_nft")
@@ -453,7 +1403,9 @@ where
             .add_attribute("class_id", class.id)
             .add_attribute("class_data", class_data_string)
             .add_attribute("channel_id", msg.channel_id)
-            .add_message(ibc_message))
+            .add_event(Ics721Event::Send { transfer }.into())
+            .add_message(ibc_message)
+            .add_submessages(hooks))
     }
 
     fn execute_pause(
@@ -476,23 +1428,52 @@ where
             Err(ContractError::Unauthorized {})
         } else {
             match msg {
-                CallbackMsg::CreateVouchers { receiver, create } => {
-                    self.callback_create_vouchers(deps, env, receiver, create)
-                }
-                CallbackMsg::RedeemVouchers { receiver, redeem } => {
-                    self.callback_redeem_vouchers(deps, receiver, redeem)
-                }
+                // receive events are emitted once vouchers are minted or
+                // redeemed
+                CallbackMsg::CreateVouchers {
+                    receiver,
+                    create,
+                    transfer,
+                } => Ok(self
+                    .callback_create_vouchers(deps, env, receiver, create)?
+                    .add_events(
+                        transfer.map(|transfer| Event::from(Ics721Event::ReceiveMint { transfer })),
+                    )),
+                CallbackMsg::RedeemVouchers {
+                    receiver,
+                    redeem,
+                    transfer,
+                } => Ok(self
+                    .callback_redeem_vouchers(deps, receiver, redeem)?
+                    .add_events(
+                        transfer
+                            .map(|transfer| Event::from(Ics721Event::ReceiveRedeem { transfer })),
+                    )),
                 CallbackMsg::Mint {
                     class_id,
                     tokens,
                     receiver,
-                } => self.callback_mint(deps, class_id, tokens, receiver),
+                } => self.callback_mint(deps, env, class_id, tokens, receiver),
                 CallbackMsg::RedeemOutgoingChannelEntries(entries) => {
                     self.callback_redeem_outgoing_channel_entries(deps, entries)
                 }
                 CallbackMsg::AddIncomingChannelEntries(entries) => {
                     self.callback_save_incoming_channel_entries(deps, entries)
                 }
+                CallbackMsg::UpdateTokenMetadata { class_id, tokens } => {
+                    self.callback_update_token_metadata(deps, class_id, tokens)
+                }
+                CallbackMsg::AddPendingClaims(entries) => {
+                    self.callback_add_pending_claims(deps, entries)
+                }
+                CallbackMsg::ParkTransfer {
+                    channel_id,
+                    sequence,
+                    transfer,
+                } => self.callback_park_transfer(deps, channel_id, sequence, transfer),
+                CallbackMsg::DispatchHook(hook) => Ok(Response::default()
+                    .add_attribute("method", "callback_dispatch_hook")
+                    .add_submessages(hook_msgs(deps.storage, &hook)?)),
                 CallbackMsg::Conjunction { operands } => {
                     Ok(Response::default().add_messages(operands))
                 }
@@ -500,6 +1481,76 @@ where
         }
     }
 
+    fn callback_park_transfer(
+        &self,
+        deps: DepsMut,
+        channel_id: String,
+        sequence: u64,
+        transfer: ParkedTransfer,
+    ) -> Result<Response<T>, ContractError> {
+        park_tokens(
+            deps.storage,
+            &channel_id,
+            sequence,
+            &transfer,
+            &transfer.data.token_ids,
+        )?;
+        Ok(Response::default()
+            .add_attribute("method", "callback_park_transfer")
+            .add_attribute("channel_id", channel_id)
+            .add_attribute("sequence", sequence.to_string())
+            .add_attribute("reason", transfer.reason))
+    }
+
+    fn callback_add_pending_claims(
+        &self,
+        deps: DepsMut,
+        entries: Vec<((ClassId, TokenId), PendingClaim)>,
+    ) -> Result<Response<T>, ContractError> {
+        for (key, claim) in &entries {
+            PENDING_CLAIMS.save(deps.storage, key.clone(), claim)?;
+        }
+        Ok(Response::default()
+            .add_attribute("method", "callback_add_pending_claims")
+            .add_attribute("count", entries.len().to_string()))
+    }
+
+    /// Updates token metadata of vouchers. Token URIs are updated on the
+    /// voucher collection only in case they changed, and the collection
+    /// supports it (see `update_token_uri_msg`).
+    fn callback_update_token_metadata(
+        &self,
+        deps: DepsMut,
+        class_id: ClassId,
+        tokens: Vec<Token>,
+    ) -> Result<Response<T>, ContractError> {
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, class_id.to_string())?;
+        let token_ids = to_json_string(&tokens.iter().map(|t| t.id.clone()).collect::<Vec<_>>())?;
+        let messages = tokens
+            .into_iter()
+            .map(|Token { id, uri, data }| -> StdResult<Option<WasmMsg>> {
+                TOKEN_METADATA.save(deps.storage, (class_id.clone(), id.clone()), &data)?;
+                let UniversalNftInfoResponse { token_uri, .. } = deps.querier.query_wasm_smart(
+                    &nft_contract,
+                    &cw721::Cw721QueryMsg::NftInfo {
+                        token_id: id.clone().into(),
+                    },
+                )?;
+                if token_uri == uri {
+                    return Ok(None);
+                }
+                self.update_token_uri_msg(deps.as_ref(), &nft_contract, &id, uri)
+            })
+            .filter_map(Result::transpose)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(Response::default()
+            .add_messages(messages)
+            .add_attribute("method", "callback_update_token_metadata")
+            .add_attribute("class_id", class_id)
+            .add_attribute("token_ids", token_ids))
+    }
+
     /// Creates the specified debt vouchers by minting cw721 debt-voucher
     /// tokens for the receiver. If no debt-voucher collection yet exists
     /// a new collection is instantiated before minting the vouchers.
@@ -516,8 +1567,33 @@ where
         // if this has already been set. If a new NFT belonging to a class
         // ID we have already seen comes in with new metadata, we assume
         // that the metadata has been updated on the source chain and
-        // update it for the class ID locally as well.
-        CLASS_ID_TO_CLASS.save(deps.storage, class.id.clone(), &class)?;
+        // update it for the class ID locally as well. Unless the
+        // controller of the voucher collection has set it.
+        let old_class = match VOUCHER_CLASS_OVERRIDES.has(deps.storage, class.id.clone()) {
+            true => None,
+            false => {
+                let old_class = CLASS_ID_TO_CLASS.may_load(deps.storage, class.id.clone())?;
+                CLASS_ID_TO_CLASS.save(deps.storage, class.id.clone(), &class)?;
+                old_class
+            }
+        };
+        let (class_update, class_updated_event) = match old_class {
+            Some(old_class) if old_class != class => {
+                let update = match query_nft_contract_for_class_id(deps.storage, class.id.clone())?
+                {
+                    Some(nft_contract) => self.update_class_msg(
+                        deps.as_ref(),
+                        &env,
+                        &nft_contract,
+                        &old_class,
+                        &class,
+                    )?,
+                    None => None,
+                };
+                (update, Some(class_updated_event(&old_class, &class)))
+            }
+            _ => (None, None),
+        };
 
         let mint = WasmMsg::Execute {
             contract_addr: env.contract.address.to_string(),
@@ -532,17 +1608,45 @@ where
         let (class_id_info, instantiate) =
             self.create_instantiate_msg(deps, &env, class.clone())?;
 
-        let token_ids = format!("{:?}", tokens);
-        let event = Event::new("ics721_receive_create_vouchers")
-            .add_attribute("class_id", class_id_info.class_id)
-            .add_attribute("nft_contract", class_id_info.address)
-            .add_attribute("token_ids", token_ids);
-
         Ok(Response::<T>::default()
             .add_attribute("method", "callback_create_vouchers")
-            .add_event(event)
+            .add_attribute("class_id", class_id_info.class_id)
+            .add_attribute("nft_contract", class_id_info.address)
+            .add_events(class_updated_event)
             .add_submessages(instantiate)
-            .add_message(mint))
+            .add_message(mint)
+            .add_messages(class_update))
+    }
+
+    /// Optional hook, called when an incoming packet carries a changed
+    /// class for an existing voucher collection, e.g. for updating name
+    /// and symbol or collection info of the voucher collection. Default
+    /// implementation does nothing, since cw721-base doesn't support it.
+    fn update_class_msg(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        _nft_contract: &Addr,
+        _old_class: &Class,
+        _new_class: &Class,
+    ) -> StdResult<Option<WasmMsg>> {
+        Ok(None)
+    }
+
+    /// Optional hook, called when an `UpdateMetadata` control packet
+    /// carries a changed token URI for a voucher. Default implementation
+    /// skips the URI, since cw721-base v0.18 voucher collections don't
+    /// support `UpdateNftInfo`. Token data is stored regardless.
+    /// Contracts using cw721 v0.19 or higher may return a
+    /// `Cw721UpdateNftInfoMsg` here.
+    fn update_token_uri_msg(
+        &self,
+        _deps: Deps,
+        _nft_contract: &Addr,
+        _token_id: &TokenId,
+        _uri: Option<String>,
+    ) -> StdResult<Option<WasmMsg>> {
+        Ok(None)
     }
 
     fn create_instantiate_msg(
@@ -557,7 +1661,12 @@ where
             Ok((nft_contract, vec![]))
         } else {
             let class_id = ClassId::new(class.id.clone());
-            let cw721_code_id = CW721_CODE_ID.load(deps.storage)?;
+            let rule = load_voucher_collection_rule(deps.storage, &class_id)?;
+            let (cw721_code_id, is_cw2981) =
+                resolve_voucher_cw721_code_id(deps.storage, &class_id)?;
+            if is_cw2981 {
+                CW2981_VOUCHERS.save(deps.storage, class_id.clone(), &Empty {})?;
+            }
             // for creating a predictable nft contract using, using instantiate2, we need: checksum, creator, and salt:
             // - using class id as salt for instantiating nft contract guarantees a) predictable address and b) uniqueness
             // for this salt must be of length 32 bytes, so we use sha256 to hash class id
@@ -579,9 +1688,24 @@ where
             };
             CLASS_ID_AND_NFT_CONTRACT_INFO.save(deps.storage, &class.id, &class_id_info)?;
 
-            let admin = ADMIN_USED_FOR_CW721
-                .load(deps.storage)?
-                .map(|a| a.to_string());
+            let (admin, label) = match rule {
+                Some(VoucherCollectionRule { admin, label, .. }) => (
+                    match admin {
+                        // empty admin makes collection immutable
+                        Some(admin) => Some(admin).filter(|a| !a.is_empty()),
+                        None => ADMIN_USED_FOR_CW721
+                            .load(deps.storage)?
+                            .map(|a| a.to_string()),
+                    },
+                    label.map(|l| l.replace("{class_id}", &class_id)),
+                ),
+                None => (
+                    ADMIN_USED_FOR_CW721
+                        .load(deps.storage)?
+                        .map(|a| a.to_string()),
+                    None,
+                ),
+            };
             let message = SubMsg::<T>::reply_on_success(
                 WasmMsg::Instantiate2 {
                     admin,
@@ -591,7 +1715,7 @@ where
                     // Attempting to fit the class ID in the label field
                     // can make this field too long which causes data
                     // errors in the SDK.
-                    label: "ics-721 debt-voucher cw-721".to_string(),
+                    label: label.unwrap_or_else(|| "ics-721 debt-voucher cw-721".to_string()),
                     salt: salt.into(),
                 },
                 INSTANTIATE_CW721_REPLY_ID,
@@ -640,15 +1764,24 @@ where
         let nft_contract = load_nft_contract_for_class_id(deps.storage, class.id.to_string())?;
         let receiver = deps.api.addr_validate(&receiver)?;
 
-        let token_ids_string = format!("{:?}", token_ids);
-        let event = Event::new("ics721_receive_redeem_vouchers")
-            .add_attribute("class_id", class.id)
-            .add_attribute("nft_contract", nft_contract.clone())
-            .add_attribute("token_ids", token_ids_string);
+        // outgoing channel entries are removed after redemption
+        for token_id in &token_ids {
+            let key = (class.id.clone(), token_id.clone());
+            if let Some(channel_id) =
+                OUTGOING_CLASS_TOKEN_TO_CHANNEL.may_load(deps.storage, key.clone())?
+            {
+                if is_stats_seeded(deps.storage, false, &key)? {
+                    update_stats(deps.storage, &class.id, &channel_id, |stats| {
+                        stats.escrowed = stats.escrowed.saturating_sub(1)
+                    })?;
+                }
+            }
+        }
 
         Ok(Response::default()
             .add_attribute("method", "callback_redeem_vouchers")
-            .add_event(event)
+            .add_attribute("class_id", class.id)
+            .add_attribute("nft_contract", nft_contract.clone())
             .add_messages(
                 token_ids
                     .into_iter()
@@ -669,6 +1802,7 @@ where
     fn callback_mint(
         &self,
         deps: DepsMut,
+        env: Env,
         class_id: ClassId,
         tokens: Vec<Token>,
         receiver: String,
@@ -676,6 +1810,12 @@ where
         let receiver = deps.api.addr_validate(&receiver)?;
         let nft_contract =
             load_nft_contract_for_class_id(deps.as_ref().storage, class_id.to_string())?;
+        // royalties are only minted on CW2981 vouchers
+        let cw2981_config = if CW2981_VOUCHERS.has(deps.storage, class_id.clone()) {
+            CW2981_CONFIG.may_load(deps.storage)?
+        } else {
+            None
+        };
 
         let mint = tokens
             .into_iter()
@@ -686,15 +1826,45 @@ where
                 // Also note that this is set for every token, regardless of if data is None.
                 TOKEN_METADATA.save(deps.storage, (class_id.clone(), id.clone()), &data)?;
 
-                let msg = cw721_base::msg::ExecuteMsg::<Empty, Empty>::Mint {
-                    token_id: id.into(),
-                    token_uri: uri,
-                    owner: receiver.to_string(),
-                    extension: Empty::default(),
+                let msg = match &cw2981_config {
+                    Some(Cw2981Config { payment_policy, .. }) => {
+                        let royalty_info = data
+                            .and_then(|data| from_json::<TokenData>(data).ok())
+                            .and_then(|token_data| token_data.royalty_info);
+                        let extension = match royalty_info {
+                            Some(RoyaltyData {
+                                payment_address,
+                                share,
+                            }) => Cw2981Metadata {
+                                royalty_percentage: Some(royalty_percentage(share)),
+                                royalty_payment_address: Some(resolve_royalty_payment_address(
+                                    deps.as_ref(),
+                                    &env,
+                                    payment_policy,
+                                    &payment_address,
+                                )),
+                            },
+                            None => Cw2981Metadata::default(),
+                        };
+                        to_json_binary(
+                            &cw721_base::msg::ExecuteMsg::<Cw2981Metadata, Empty>::Mint {
+                                token_id: id.into(),
+                                token_uri: uri,
+                                owner: receiver.to_string(),
+                                extension,
+                            },
+                        )?
+                    }
+                    None => to_json_binary(&cw721_base::msg::ExecuteMsg::<Empty, Empty>::Mint {
+                        token_id: id.into(),
+                        token_uri: uri,
+                        owner: receiver.to_string(),
+                        extension: Empty::default(),
+                    })?,
                 };
                 Ok(WasmMsg::Execute {
                     contract_addr: nft_contract.to_string(),
-                    msg: to_json_binary(&msg)?,
+                    msg,
                     funds: vec![],
                 })
             })
@@ -711,7 +1881,7 @@ where
         entries: Vec<(ClassId, TokenId)>,
     ) -> Result<Response<T>, ContractError> {
         for (class_id, token_id) in entries {
-            OUTGOING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, (class_id, token_id));
+            OUTGOING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, (class_id, token_id))?;
         }
         Ok(Response::default().add_attribute("method", "callback_redeem_outgoing_channel_entries"))
     }
@@ -722,6 +1892,12 @@ where
         entries: Vec<((ClassId, TokenId), String)>,
     ) -> Result<Response<T>, ContractError> {
         for (key, channel) in entries {
+            // vouchers are counted here, since minting doesn't know the channel
+            if is_stats_seeded(deps.storage, true, &key)? {
+                update_stats(deps.storage, &key.0, &channel, |stats| {
+                    stats.vouchers_minted += 1
+                })?;
+            }
             INCOMING_CLASS_TOKEN_TO_CHANNEL.save(deps.storage, key, &channel)?;
         }
         Ok(Response::default().add_attribute("method", "callback_save_incoming_channel_entries"))
@@ -729,7 +1905,7 @@ where
 
     fn migrate(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         _env: Env,
         msg: MigrateMsg,
     ) -> Result<Response<T>, ContractError> {
@@ -811,11 +1987,47 @@ where
                             .map_or_else(|| "none".to_string(), |or| or.to_string()),
                     );
 
-                self.migrate_legacy(deps, response)
+                let response = self.migrate_legacy(deps.branch(), response)?;
+                self.migrate_channel_entries(deps, response)
             }
         }
     }
 
+    /// Starts a pass over outgoing and incoming channel entries, in
+    /// case an index is still empty (they were plain maps before) or
+    /// there are no transfer stats yet. The pass builds the channel
+    /// indexes and seeds the stats in batches, by
+    /// `ExecuteMsg::BuildChannelIndexes`, so migrate stays bounded.
+    fn migrate_channel_entries(
+        &self,
+        deps: DepsMut,
+        response: Response<T>,
+    ) -> Result<Response<T>, ContractError> {
+        let seed_stats = GLOBAL_STATS.may_load(deps.storage)?.is_none();
+        if seed_stats {
+            GLOBAL_STATS.save(deps.storage, &TransferStats::default())?;
+        }
+        let has_entries = has_entries(deps.storage, &OUTGOING_CLASS_TOKEN_TO_CHANNEL)
+            || has_entries(deps.storage, &INCOMING_CLASS_TOKEN_TO_CHANNEL);
+        let is_unindexed = is_unindexed(deps.storage, &OUTGOING_CLASS_TOKEN_TO_CHANNEL)
+            || is_unindexed(deps.storage, &INCOMING_CLASS_TOKEN_TO_CHANNEL);
+        if CHANNEL_INDEX_CURSOR.may_load(deps.storage)?.is_none()
+            && has_entries
+            && (is_unindexed || seed_stats)
+        {
+            CHANNEL_INDEX_CURSOR.save(
+                deps.storage,
+                &ChannelIndexCursor {
+                    incoming: false,
+                    start_after: None,
+                    seed_stats,
+                },
+            )?;
+        }
+        let done = CHANNEL_INDEX_CURSOR.may_load(deps.storage)?.is_none();
+        Ok(response.add_attribute("channel_indexes_done", done.to_string()))
+    }
+
     // TODO once migrated:
     // - this complete block can be deleted
     // - legacy map 'e' and 'f' can be deleted
@@ -862,3 +2074,111 @@ where
         }
     }
 }
+
+fn has_entries(
+    storage: &dyn Storage,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ChannelIndexes>,
+) -> bool {
+    class_token_to_channel
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+/// Whether there are channel entries, but no index entries.
+fn is_unindexed(
+    storage: &dyn Storage,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ChannelIndexes>,
+) -> bool {
+    let has_index = class_token_to_channel
+        .idx
+        .channel
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    has_entries(storage, class_token_to_channel) && !has_index
+}
+
+/// Re-saves up to `limit` channel entries, so their index entries are
+/// created, continuing at the stored cursor. Outgoing entries are
+/// indexed first, then incoming entries. In case of seeding stats,
+/// outgoing entries are counted as escrowed (NFTs in flight can't be
+/// told apart) and incoming entries as minted vouchers, while stats
+/// updates of entries not yet reached are skipped (see
+/// `is_stats_seeded`). Returns the number of indexed entries and whether
+/// all entries are indexed.
+fn build_channel_indexes(storage: &mut dyn Storage, limit: usize) -> StdResult<(usize, bool)> {
+    let mut cursor = match CHANNEL_INDEX_CURSOR.may_load(storage)? {
+        Some(cursor) => cursor,
+        None => return Ok((0, true)),
+    };
+    let mut indexed = 0;
+    while indexed < limit {
+        let class_token_to_channel = match cursor.incoming {
+            false => &OUTGOING_CLASS_TOKEN_TO_CHANNEL,
+            true => &INCOMING_CLASS_TOKEN_TO_CHANNEL,
+        };
+        let start = cursor.start_after.clone().map(|(class_id, token_id)| {
+            Bound::exclusive((ClassId::new(class_id), TokenId::new(token_id)))
+        });
+        let batch = limit - indexed;
+        let entries = class_token_to_channel
+            .range(storage, start, None, Order::Ascending)
+            .take(batch)
+            .collect::<StdResult<Vec<_>>>()?;
+        let incoming = cursor.incoming;
+        for ((class_id, token_id), channel) in &entries {
+            class_token_to_channel.save(
+                storage,
+                (ClassId::new(class_id), TokenId::new(token_id)),
+                channel,
+            )?;
+            if cursor.seed_stats {
+                update_stats(
+                    storage,
+                    &ClassId::new(class_id),
+                    channel,
+                    |stats| match incoming {
+                        false => stats.escrowed += 1,
+                        true => stats.vouchers_minted += 1,
+                    },
+                )?;
+            }
+        }
+        indexed += entries.len();
+        match entries.last() {
+            Some((class_token, _)) if entries.len() == batch => {
+                cursor.start_after = Some(class_token.clone())
+            }
+            _ if !cursor.incoming => {
+                cursor = ChannelIndexCursor {
+                    incoming: true,
+                    start_after: None,
+                    seed_stats: cursor.seed_stats,
+                }
+            }
+            _ => {
+                CHANNEL_INDEX_CURSOR.remove(storage);
+                return Ok((indexed, true));
+            }
+        }
+    }
+    CHANNEL_INDEX_CURSOR.save(storage, &cursor)?;
+    Ok((indexed, false))
+}
+
+fn class_updated_event(old_class: &Class, new_class: &Class) -> Event {
+    let uri = |class: &Class| class.uri.clone().unwrap_or_else(|| "none".to_string());
+    let data = |class: &Class| {
+        class
+            .data
+            .as_ref()
+            .map_or_else(|| "none".to_string(), Binary::to_base64)
+    };
+    Event::new("ics721_class_updated")
+        .add_attribute("class_id", new_class.id.to_string())
+        .add_attribute("old_uri", uri(old_class))
+        .add_attribute("new_uri", uri(new_class))
+        .add_attribute("old_data", data(old_class))
+        .add_attribute("new_data", data(new_class))
+}
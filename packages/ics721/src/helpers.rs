@@ -1,22 +1,37 @@
+use bech32::FromBase32;
 use cosmwasm_std::{
-    from_json, instantiate2_address, to_json_binary, Addr, Binary, CodeInfoResponse, Deps,
-    IbcPacket, StdResult, Storage, SubMsg, WasmMsg,
+    from_json, instantiate2_address, to_json_binary, Addr, Binary, CanonicalAddr, CodeInfoResponse,
+    ContractInfoResponse, Deps, Env, IbcPacket, IbcTimeout, Order, StdError, StdResult, Storage,
+    SubMsg, WasmMsg,
 };
+use cw_storage_plus::PrimaryKey;
 use serde::Deserialize;
 
 use crate::{
-    ibc::ACK_CALLBACK_REPLY_ID,
-    state::{CONTRACT_ADDR_LENGTH, INCOMING_PROXY},
+    ibc::{ACK_CALLBACK_REPLY_ID, HOOK_REPLY_ID},
+    state::{
+        ParkedTransfer, TransferStats, CHANNEL_INDEX_CURSOR, CHANNEL_STATS,
+        CHANNEL_TIMEOUT_CONFIGS, CLASS_STATS, CONTRACT_ADDR_LENGTH, COUNTERPARTY_ADDRESS_POLICIES,
+        GLOBAL_STATS, HOOK_SUBSCRIBERS, INCOMING_PROXY, PARKED_TOKENS, PARKED_TRANSFERS,
+    },
     ContractError,
 };
 use ics721_types::{
     ibc_types::NonFungibleTokenPacketData,
+    token_types::{ClassId, TokenId},
     types::{
-        Ics721AckCallbackMsg, Ics721Callbacks, Ics721Memo, Ics721ReceiveCallbackMsg, Ics721Status,
-        ReceiverExecuteMsg,
+        Ics721AckCallbackMsg, Ics721Callbacks, Ics721HookMsg, Ics721HookTransfer, Ics721Memo,
+        Ics721ReceiveCallbackMsg, Ics721Status, ReceiverExecuteMsg,
     },
 };
 
+/// Max number of hook subscribers, so dispatching hooks on transfers
+/// stays bounded.
+pub(crate) const MAX_HOOK_SUBSCRIBERS: usize = 10;
+/// Gas limit of each hook submessage, so a subscriber can't use up the
+/// gas of a transfer. Running out of gas fails the hook only.
+pub(crate) const HOOK_GAS_LIMIT: u64 = 1_000_000;
+
 /// Parse the memo field into the type we want
 /// Ideally it would be `Ics721Memo` type or any type that extends it
 fn parse_memo<T: for<'de> Deserialize<'de>>(memo: Option<String>) -> Option<T> {
@@ -71,6 +86,95 @@ pub(crate) fn ack_callback_msg(
     ))
 }
 
+/// Creates a subMsg for each hook subscriber interested in the class and
+/// channel of the transfer. Same as ack callbacks, hooks are replied on
+/// error, so a failing hook never fails the transfer.
+pub(crate) fn hook_msgs<T>(
+    storage: &dyn Storage,
+    hook: &Ics721HookMsg,
+) -> StdResult<Vec<SubMsg<T>>> {
+    let msg = to_json_binary(&ReceiverExecuteMsg::Ics721Hook(hook.clone()))?;
+    Ok(hook_subscribers(storage, hook.transfer())?
+        .into_iter()
+        .map(|subscriber| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: subscriber,
+                    msg: msg.clone(),
+                    funds: vec![],
+                },
+                HOOK_REPLY_ID,
+            )
+            .with_gas_limit(HOOK_GAS_LIMIT)
+        })
+        .collect())
+}
+
+/// Gets all hook subscribers interested in the class and channel of the
+/// transfer.
+pub(crate) fn hook_subscribers(
+    storage: &dyn Storage,
+    transfer: &Ics721HookTransfer,
+) -> StdResult<Vec<String>> {
+    HOOK_SUBSCRIBERS
+        .range(storage, None, None, Order::Ascending)
+        .take(MAX_HOOK_SUBSCRIBERS)
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, subscription)| {
+                (subscription.class_ids.is_empty()
+                    || subscription.class_ids.contains(&transfer.class_id))
+                    && (subscription.channel_ids.is_empty()
+                        || subscription.channel_ids.contains(&transfer.channel_id))
+            })
+        })
+        .map(|item| item.map(|(subscriber, _)| subscriber.to_string()))
+        .collect()
+}
+
+/// Parks given tokens of a transfer in ICS721 custody. Tokens are added
+/// to the transfer already parked under the same key, e.g. in case
+/// tokens of a returned transfer are parked again.
+pub(crate) fn park_tokens(
+    storage: &mut dyn Storage,
+    channel_id: &str,
+    sequence: u64,
+    transfer: &ParkedTransfer,
+    token_ids: &[TokenId],
+) -> StdResult<()> {
+    let mut parked = match PARKED_TRANSFERS.may_load(storage, (channel_id, sequence))? {
+        Some(parked) => parked,
+        None => {
+            let mut parked = transfer.clone();
+            parked.data.token_ids = vec![];
+            parked.data.token_uris = parked.data.token_uris.map(|_| vec![]);
+            parked.data.token_data = parked.data.token_data.map(|_| vec![]);
+            parked
+        }
+    };
+    for (index, token_id) in transfer.data.token_ids.iter().enumerate() {
+        if !token_ids.contains(token_id) || parked.data.token_ids.contains(token_id) {
+            continue;
+        }
+        parked.data.token_ids.push(token_id.clone());
+        if let (Some(uris), Some(parked_uris)) =
+            (&transfer.data.token_uris, &mut parked.data.token_uris)
+        {
+            parked_uris.extend(uris.get(index).cloned());
+        }
+        if let (Some(data), Some(parked_data)) =
+            (&transfer.data.token_data, &mut parked.data.token_data)
+        {
+            parked_data.extend(data.get(index).cloned());
+        }
+        PARKED_TOKENS.save(
+            storage,
+            (transfer.class_id.clone(), token_id.clone()),
+            &(channel_id.to_string(), sequence),
+        )?;
+    }
+    PARKED_TRANSFERS.save(storage, (channel_id, sequence), &parked)
+}
+
 /// If there is an incoming proxy, let proxy validate the packet, in case it fails, we fail the transfer
 /// This proxy for example whitelist channels that can send to this contract:
 /// https://github.com/arkprotocol/cw-ics721-proxy/tree/main/contracts/cw-ics721-incoming-proxy-base
@@ -138,6 +242,126 @@ pub(crate) fn generate_receive_callback_msg(
     })
 }
 
+/// Checks sender is the CosmWasm level admin of ICS721. In case
+/// ICS721 has no admin, nobody is allowed. Note, the older admin clean
+/// messages keep their own check, which allows anyone in that case.
+pub(crate) fn assert_admin(deps: Deps, env: &Env, sender: &Addr) -> Result<(), ContractError> {
+    let ContractInfoResponse { admin, .. } = deps
+        .querier
+        .query_wasm_contract_info(env.contract.address.to_string())?;
+    match admin {
+        Some(admin) if admin == *sender => Ok(()),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+/// Checks an outgoing receiver against the address policy of the
+/// counterparty chain, in case there is one for the channel.
+pub(crate) fn validate_counterparty_receiver(
+    storage: &dyn Storage,
+    channel_id: &str,
+    receiver: &str,
+) -> Result<(), ContractError> {
+    let Some(policy) = COUNTERPARTY_ADDRESS_POLICIES.may_load(storage, channel_id)? else {
+        return Ok(());
+    };
+    let valid = bech32::decode(receiver)
+        .ok()
+        .filter(|(prefix, _, _)| prefix == &policy.prefix)
+        .and_then(|(_, data, _)| Vec::<u8>::from_base32(&data).ok())
+        .map_or(false, |data| {
+            policy.address_lengths.is_empty()
+                || policy.address_lengths.contains(&(data.len() as u32))
+        });
+    match valid {
+        true => Ok(()),
+        false => Err(ContractError::InvalidReceiver {
+            channel: channel_id.to_string(),
+            expected_prefix: policy.prefix,
+        }),
+    }
+}
+
+/// Resolves the timeout of an outgoing transfer. Relative and omitted
+/// timeouts are filled in using the channel's timeout config.
+pub(crate) fn resolve_outgoing_timeout(
+    storage: &dyn Storage,
+    env: &Env,
+    channel_id: &str,
+    timeout: Option<IbcTimeout>,
+    relative_timeout_seconds: Option<u64>,
+) -> Result<IbcTimeout, ContractError> {
+    let config = CHANNEL_TIMEOUT_CONFIGS
+        .may_load(storage, channel_id)?
+        .unwrap_or_default();
+    let timeout = match (timeout, relative_timeout_seconds.or(config.default_seconds)) {
+        (Some(timeout), _) => timeout,
+        (None, Some(seconds)) => IbcTimeout::with_timestamp(env.block.time.plus_seconds(seconds)),
+        (None, None) => {
+            return Err(ContractError::NoTimeout {
+                channel: channel_id.to_string(),
+            })
+        }
+    };
+    if let Some(max_seconds) = config.max_seconds {
+        let max_timestamp = env.block.time.plus_seconds(max_seconds);
+        if !timeout
+            .timestamp()
+            .map_or(false, |timestamp| timestamp <= max_timestamp)
+        {
+            return Err(ContractError::TimeoutTooFar {
+                channel: channel_id.to_string(),
+                max_seconds,
+            });
+        }
+    }
+    Ok(timeout)
+}
+
+/// Applies an update to the transfer stats of a class, of a channel and
+/// to the global stats. Decrements saturate, since transfers in flight
+/// while stats were introduced aren't counted as in flight.
+pub(crate) fn update_stats(
+    storage: &mut dyn Storage,
+    class_id: &ClassId,
+    channel_id: &str,
+    update: impl Fn(&mut TransferStats),
+) -> StdResult<()> {
+    let apply = |stats: Option<TransferStats>| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        update(&mut stats);
+        Ok(stats)
+    };
+    CLASS_STATS.update(storage, class_id.clone(), apply)?;
+    CHANNEL_STATS.update(storage, channel_id, apply)?;
+    let global = apply(GLOBAL_STATS.may_load(storage)?)?;
+    GLOBAL_STATS.save(storage, &global)
+}
+
+/// Whether the transfer stats of an outgoing or incoming channel entry
+/// are up to date. While stats are seeded from channel entries, entries
+/// not yet reached by the cursor are counted once reached, so live
+/// updates of their escrowed or voucher counts must be skipped.
+pub(crate) fn is_stats_seeded(
+    storage: &dyn Storage,
+    incoming: bool,
+    key: &(ClassId, TokenId),
+) -> StdResult<bool> {
+    let cursor = match CHANNEL_INDEX_CURSOR.may_load(storage)? {
+        Some(cursor) if cursor.seed_stats => cursor,
+        _ => return Ok(true),
+    };
+    // outgoing entries are seeded first, then incoming entries
+    Ok(match (incoming, cursor.incoming) {
+        (false, true) => true,
+        (true, false) => false,
+        _ => cursor.start_after.map_or(false, |(class_id, token_id)| {
+            // same order as ranging over the entries
+            key.joined_key() <= (ClassId::new(class_id), TokenId::new(token_id)).joined_key()
+        }),
+    })
+}
+
 pub fn get_instantiate2_address(
     deps: Deps,
     creator: &str,
@@ -150,14 +374,33 @@ pub fn get_instantiate2_address(
     // get the checksum of the contract we're going to instantiate
     let CodeInfoResponse { checksum, .. } = deps.querier.query_wasm_code_info(code_id)?;
 
-    let canonical_cw721_addr = instantiate2_address(&checksum, &canonical_creator, salt)?;
-    if let Some(contract_addr_length) = CONTRACT_ADDR_LENGTH.may_load(deps.storage)? {
-        let contract_addr_length = contract_addr_length as usize;
-        Ok(deps
-            .api
-            .addr_humanize(&canonical_cw721_addr[..contract_addr_length].into())?)
-    } else {
-        Ok(deps.api.addr_humanize(&canonical_cw721_addr)?)
+    let canonical_cw721_addr = get_instantiate2_canonical_address(
+        &checksum,
+        &canonical_creator,
+        salt,
+        CONTRACT_ADDR_LENGTH.may_load(deps.storage)?,
+    )?;
+    Ok(deps.api.addr_humanize(&canonical_cw721_addr)?)
+}
+
+/// Computes the instantiate2 address without querying the chain, so it
+/// can be used offline as well. In case `contract_addr_length` is set,
+/// the address is truncated to that length.
+pub fn get_instantiate2_canonical_address(
+    checksum: &[u8],
+    canonical_creator: &CanonicalAddr,
+    salt: &[u8],
+    contract_addr_length: Option<u32>,
+) -> Result<CanonicalAddr, ContractError> {
+    let canonical_addr = instantiate2_address(checksum, canonical_creator, salt)?;
+    match contract_addr_length.map(|length| length as usize) {
+        Some(length) if length > canonical_addr.len() => Err(StdError::generic_err(format!(
+            "Contract address length {length} exceeds address length {}",
+            canonical_addr.len()
+        ))
+        .into()),
+        Some(length) => Ok(canonical_addr[..length].into()),
+        None => Ok(canonical_addr),
     }
 }
 
@@ -1,21 +1,29 @@
 use cosmwasm_std::{
-    from_json, to_json_binary, DepsMut, Empty, Env, Event, IbcBasicResponse, IbcChannelCloseMsg,
-    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacket, IbcPacketAckMsg,
-    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Never, Reply, Response,
-    StdResult, SubMsgResult, WasmMsg,
+    from_json, to_json_binary, to_json_string, DepsMut, Empty, Env, Event, IbcBasicResponse,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcPacket,
+    IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Never, Reply,
+    Response, StdResult, SubMsg, SubMsgResult, WasmMsg,
 };
 use cw_utils::parse_reply_instantiate_data;
-use ics721_types::{ibc_types::NonFungibleTokenPacketData, types::Ics721Status};
+use ics721_types::{
+    ack::{ack_fail, ack_success, try_get_ack_error},
+    events::Ics721Event,
+    ibc_types::{Ics721ControlPacketData, NonFungibleTokenPacketData},
+    token_types::TokenId,
+    types::{Ics721HookMsg, Ics721HookTransfer, Ics721Status},
+};
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    helpers::ack_callback_msg,
-    ibc_helpers::{ack_fail, ack_success, try_get_ack_error, validate_order_and_version},
-    ibc_packet_receive::receive_ibc_packet,
+    helpers::{ack_callback_msg, hook_msgs, is_stats_seeded, park_tokens, update_stats},
+    ibc_helpers::validate_order_and_version,
+    ibc_packet_receive::{park_ibc_packet, receive_ibc_packet},
     query::{load_class_id_for_nft_contract, load_nft_contract_for_class_id},
     state::{
-        INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL,
-        OUTGOING_PROXY, TOKEN_METADATA,
+        ClassIdInfo, INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY,
+        MIGRATING_VOUCHER_COLLECTIONS, OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY,
+        PARKING_ERROR, PENDING_CLAIMS, RECEIVING_PACKET, RETURNING_PARKED_TOKENS,
+        RETURNING_PENDING_CLAIMS, TOKEN_METADATA,
     },
     ContractError,
 };
@@ -32,8 +40,22 @@ pub(crate) const INSTANTIATE_OUTGOING_PROXY_REPLY_ID: u64 = 2;
 pub(crate) const ACK_AND_DO_NOTHING_REPLY_ID: u64 = 3;
 /// Reply on callback
 pub(crate) const ACK_CALLBACK_REPLY_ID: u64 = 4;
+/// Submessage reply ID used for migrating voucher collections.
+pub(crate) const MIGRATE_CW721_REPLY_ID: u64 = 5;
+/// Submessage reply ID used for receiving in park on failure mode. Same
+/// as `ACK_AND_DO_NOTHING_REPLY_ID`, but on failure the transfer is
+/// parked in ICS721 custody.
+pub(crate) const PARK_ON_FAILURE_REPLY_ID: u64 = 6;
+/// Reply on hook of a subscriber contract
+pub(crate) const HOOK_REPLY_ID: u64 = 7;
+/// Submessage reply ID used for parking a transfer. Same as
+/// `ACK_AND_DO_NOTHING_REPLY_ID`, but on failure the error of the
+/// transfer is acked as well.
+pub(crate) const PARK_REPLY_ID: u64 = 8;
 /// The IBC version this contract expects to communicate with.
 pub const IBC_VERSION: &str = "ics721-1";
+/// Error outgoing transfers are rolled back with on timeout.
+const TIMEOUT_ERROR: &str = "timeout";
 
 pub trait Ics721Ibc<T = Empty>
 where
@@ -95,19 +117,26 @@ where
 
     fn ibc_packet_receive(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         msg: IbcPacketReceiveMsg,
     ) -> Result<IbcReceiveResponse, Never> {
         // Regardless of if our processing of this packet works we need to
         // commit an ACK to the chain. As such, we wrap all handling logic
         // in a seprate function and on error write out an error ack.
-        match receive_ibc_packet(deps, env, msg.packet) {
+        match receive_ibc_packet(deps.branch(), env.clone(), msg.packet.clone()) {
             Ok(response) => Ok(response),
-            Err(error) => Ok(IbcReceiveResponse::new()
-                .add_attribute("method", "ibc_packet_receive")
-                .add_attribute("error", error.to_string())
-                .set_ack(ack_fail(error.to_string()))),
+            // in park on failure mode, undeliverable transfers are parked in ICS721 custody
+            Err(error) => match park_ibc_packet(deps, &env, &msg.packet, error.to_string()) {
+                Some((message, attributes, events)) => Ok(IbcReceiveResponse::new()
+                    .add_submessage(SubMsg::reply_always(message, PARK_REPLY_ID))
+                    .add_attributes(attributes)
+                    .add_events(events)),
+                None => Ok(IbcReceiveResponse::new()
+                    .add_attribute("method", "ibc_packet_receive")
+                    .add_attribute("error", error.to_string())
+                    .set_ack(ack_fail(error.to_string()))),
+            },
         }
     }
 
@@ -117,6 +146,10 @@ where
         _env: Env,
         ack: IbcPacketAckMsg,
     ) -> Result<IbcBasicResponse, ContractError> {
+        if let Ok(control) = from_json::<Ics721ControlPacketData>(&ack.original_packet.data) {
+            let error = try_get_ack_error(&ack.acknowledgement);
+            return self.handle_control_packet_ack(ack.original_packet, control, error.as_deref());
+        }
         if let Some(error) = try_get_ack_error(&ack.acknowledgement) {
             self.handle_packet_fail(deps, ack.original_packet, &error)
         } else {
@@ -126,31 +159,70 @@ where
                 load_nft_contract_for_class_id(deps.storage, msg.class_id.to_string())?;
             // Burn all of the tokens being transfered out that were
             // previously transfered in on this channel.
-            let burn_notices = msg.token_ids.iter().cloned().try_fold(
-                Vec::<WasmMsg>::new(),
-                |mut messages, token| -> StdResult<_> {
+            let (burn_notices, burned_token_ids) = msg.token_ids.iter().cloned().try_fold(
+                (Vec::<WasmMsg>::new(), Vec::<TokenId>::new()),
+                |(mut messages, mut burned_token_ids), token| -> StdResult<_> {
                     let key = (msg.class_id.clone(), token.clone());
+                    // returned parked token or claim made it back to its source chain
+                    RETURNING_PARKED_TOKENS.remove(deps.storage, key.clone());
+                    RETURNING_PENDING_CLAIMS.remove(deps.storage, key.clone());
                     let source_channel =
                         INCOMING_CLASS_TOKEN_TO_CHANNEL.may_load(deps.storage, key.clone())?;
                     let returning_to_source = source_channel.map_or(false, |source_channel| {
                         source_channel == ack.original_packet.src.channel_id
                     });
+                    let is_outgoing_seeded = is_stats_seeded(deps.storage, false, &key)?;
+                    let is_incoming_seeded = is_stats_seeded(deps.storage, true, &key)?;
+                    update_stats(
+                        deps.storage,
+                        &msg.class_id,
+                        &ack.original_packet.src.channel_id,
+                        |stats| {
+                            stats.in_flight = stats.in_flight.saturating_sub(1);
+                            if returning_to_source && is_outgoing_seeded {
+                                stats.escrowed = stats.escrowed.saturating_sub(1);
+                            }
+                            if returning_to_source && is_incoming_seeded {
+                                stats.vouchers_burned += 1;
+                            }
+                        },
+                    )?;
                     if returning_to_source {
                         // This token's journey is complete, for now.
-                        INCOMING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key);
+                        INCOMING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key.clone())?;
+                        // Burned vouchers aren't escrowed, so their outgoing
+                        // entry would be stale.
+                        OUTGOING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key)?;
                         TOKEN_METADATA.remove(deps.storage, (msg.class_id.clone(), token.clone()));
 
                         messages.push(WasmMsg::Execute {
                             contract_addr: nft_contract.to_string(),
                             msg: to_json_binary(&cw721::Cw721ExecuteMsg::Burn {
-                                token_id: token.into(),
+                                token_id: token.clone().into(),
                             })?,
                             funds: vec![],
-                        })
+                        });
+                        burned_token_ids.push(token);
                     }
-                    Ok(messages)
+                    Ok((messages, burned_token_ids))
                 },
             )?;
+            let hooks = match burned_token_ids.is_empty() {
+                true => vec![],
+                false => hook_msgs(
+                    deps.storage,
+                    &Ics721HookMsg::BurnOnAck {
+                        transfer: Ics721HookTransfer {
+                            channel_id: ack.original_packet.src.channel_id.clone(),
+                            class_id: msg.class_id.clone(),
+                            nft_contract: nft_contract.to_string(),
+                            token_ids: burned_token_ids.clone(),
+                            sender: msg.sender.clone(),
+                            receiver: msg.receiver.clone(),
+                        },
+                    },
+                )?,
+            };
 
             let callback = match ack_callback_msg(
                 deps.as_ref(),
@@ -162,11 +234,18 @@ where
                 None => vec![],
             };
 
-            let token_ids = format!("{:?}", msg.token_ids);
-            let event = Event::new("ics721_ack_burn_vouchers")
-                .add_attribute("nft_contract", nft_contract.clone())
-                .add_attribute("class_id", msg.class_id.to_string())
-                .add_attribute("token_ids", token_ids.clone());
+            let token_ids = to_json_string(&msg.token_ids)?;
+            let event = Ics721Event::AckSuccess {
+                transfer: Ics721HookTransfer {
+                    channel_id: ack.original_packet.src.channel_id,
+                    class_id: msg.class_id.clone(),
+                    nft_contract: nft_contract.to_string(),
+                    token_ids: msg.token_ids,
+                    sender: msg.sender.clone(),
+                    receiver: msg.receiver.clone(),
+                },
+                burned_token_ids,
+            };
 
             Ok(IbcBasicResponse::new()
                 .add_attribute("burn_notices", (!burn_notices.is_empty()).to_string())
@@ -174,11 +253,12 @@ where
                 .add_attribute("sender", msg.sender)
                 .add_attribute("receiver", msg.receiver)
                 .add_attribute("nft_contract", nft_contract)
-                .add_attribute("classId", msg.class_id)
+                .add_attribute("class_id", msg.class_id)
                 .add_attribute("token_ids", token_ids)
                 .add_messages(burn_notices)
                 .add_submessages(callback)
-                .add_event(event))
+                .add_submessages(hooks)
+                .add_event(event.into()))
         }
     }
 
@@ -188,7 +268,37 @@ where
         _env: Env,
         msg: IbcPacketTimeoutMsg,
     ) -> Result<IbcBasicResponse, ContractError> {
-        self.handle_packet_fail(deps, msg.packet, "timeout")
+        if let Ok(control) = from_json::<Ics721ControlPacketData>(&msg.packet.data) {
+            return self.handle_control_packet_ack(msg.packet, control, Some(TIMEOUT_ERROR));
+        }
+        self.handle_packet_fail(deps, msg.packet, TIMEOUT_ERROR)
+    }
+
+    /// Control packets don't escrow anything, so there is nothing to
+    /// roll back on failure. The result is only reported.
+    fn handle_control_packet_ack(
+        &self,
+        packet: IbcPacket,
+        control: Ics721ControlPacketData,
+        error: Option<&str>,
+    ) -> Result<IbcBasicResponse, ContractError> {
+        let (packet_type, class_id) = match control {
+            Ics721ControlPacketData::CollectionOwnership { class_id, .. } => {
+                ("collection_ownership", class_id)
+            }
+            Ics721ControlPacketData::UpdateMetadata { class_id, .. } => {
+                ("update_metadata", class_id)
+            }
+        };
+        let response = IbcBasicResponse::new()
+            .add_attribute("method", "ibc_packet_ack_control")
+            .add_attribute("packet_type", packet_type)
+            .add_attribute("class_id", class_id)
+            .add_attribute("channel_id", packet.src.channel_id);
+        Ok(match error {
+            Some(error) => response.add_attribute("error", error),
+            None => response,
+        })
     }
 
     /// Return the NFT locked in the ICS721 contract to sender; roll back.
@@ -208,18 +318,54 @@ where
             .iter()
             .cloned()
             .map(|token_id| -> StdResult<_> {
-                OUTGOING_CLASS_TOKEN_TO_CHANNEL
-                    .remove(deps.storage, (message.class_id.clone(), token_id.clone()));
-                Ok(WasmMsg::Execute {
+                let key = (message.class_id.clone(), token_id.clone());
+                OUTGOING_CLASS_TOKEN_TO_CHANNEL.remove(deps.storage, key.clone())?;
+                // returned parked token is still held by ICS721, so it is parked again
+                if let Some(((channel_id, sequence), transfer)) =
+                    RETURNING_PARKED_TOKENS.may_load(deps.storage, key.clone())?
+                {
+                    RETURNING_PARKED_TOKENS.remove(deps.storage, key);
+                    park_tokens(deps.storage, &channel_id, sequence, &transfer, &[token_id])?;
+                    return Ok(None);
+                }
+                // returned voucher is still held by ICS721, so its claim is pending again
+                if let Some(claim) = RETURNING_PENDING_CLAIMS.may_load(deps.storage, key.clone())? {
+                    RETURNING_PENDING_CLAIMS.remove(deps.storage, key.clone());
+                    PENDING_CLAIMS.save(deps.storage, key, &claim)?;
+                    return Ok(None);
+                }
+                Ok(Some(WasmMsg::Execute {
                     contract_addr: nft_contract.to_string(),
                     msg: to_json_binary(&cw721::Cw721ExecuteMsg::TransferNft {
                         recipient: sender.to_string(),
                         token_id: token_id.into(),
                     })?,
                     funds: vec![],
-                })
+                }))
             })
+            .filter_map(Result::transpose)
             .collect::<StdResult<Vec<_>>>()?;
+        let token_count = message.token_ids.len() as u64;
+        let mut seeded_count = 0;
+        for token_id in &message.token_ids {
+            let key = (message.class_id.clone(), token_id.clone());
+            if is_stats_seeded(deps.storage, false, &key)? {
+                seeded_count += 1;
+            }
+        }
+        update_stats(
+            deps.storage,
+            &message.class_id,
+            &packet.src.channel_id,
+            |stats| {
+                stats.escrowed = stats.escrowed.saturating_sub(seeded_count);
+                stats.in_flight = stats.in_flight.saturating_sub(token_count);
+                match error {
+                    TIMEOUT_ERROR => stats.timed_out += 1,
+                    _ => stats.failed += 1,
+                }
+            },
+        )?;
 
         let callback = match ack_callback_msg(
             deps.as_ref(),
@@ -230,19 +376,43 @@ where
             Some(msg) => vec![msg],
             None => vec![],
         };
+        let transfer = Ics721HookTransfer {
+            channel_id: packet.src.channel_id.clone(),
+            class_id: message.class_id.clone(),
+            nft_contract: nft_contract.to_string(),
+            token_ids: message.token_ids.clone(),
+            sender: message.sender.clone(),
+            receiver: message.receiver.clone(),
+        };
+        let hooks = hook_msgs(
+            deps.storage,
+            &Ics721HookMsg::Refund {
+                transfer: transfer.clone(),
+                error: error.to_string(),
+            },
+        )?;
+        let event = match error {
+            TIMEOUT_ERROR => Ics721Event::Timeout { transfer },
+            _ => Ics721Event::AckFail {
+                transfer,
+                error: error.to_string(),
+            },
+        };
 
         Ok(IbcBasicResponse::new()
             .add_messages(messages)
             .add_submessages(callback)
+            .add_submessages(hooks)
             .add_attribute("method", "ibc_packet_ack_fail")
-            .add_attribute("token_ids", format!("{:?}", message.token_ids))
+            .add_attribute("token_ids", to_json_string(&message.token_ids)?)
             .add_attribute("class_id", message.class_id)
             .add_attribute("channel_id", packet.src.channel_id)
             .add_attribute("address_refunded", message.sender)
-            .add_attribute("error", error))
+            .add_attribute("error", error)
+            .add_event(event.into()))
     }
 
-    fn reply(&self, deps: DepsMut, _env: Env, reply: Reply) -> Result<Response<T>, ContractError> {
+    fn reply(&self, deps: DepsMut, env: Env, reply: Reply) -> Result<Response<T>, ContractError> {
         match reply.id {
             INSTANTIATE_CW721_REPLY_ID => {
                 // Don't need to add an ack or check for an error here as this
@@ -297,10 +467,62 @@ where
                     SubMsgResult::Err(err) => Ok(Response::new().set_data(ack_fail(err))),
                 }
             }
+            PARK_ON_FAILURE_REPLY_ID => {
+                let packet = RECEIVING_PACKET.load(deps.storage)?;
+                RECEIVING_PACKET.remove(deps.storage);
+                match reply.result {
+                    SubMsgResult::Ok(_) => Ok(Response::new().set_data(ack_success())),
+                    // Receiving into ICS721 custody is acked by its own
+                    // submessage, overriding the data field in turn.
+                    SubMsgResult::Err(err) => {
+                        match park_ibc_packet(deps, &env, &packet, err.clone()) {
+                            Some((message, attributes, events)) => Ok(Response::new()
+                                .add_submessage(SubMsg::reply_always(message, PARK_REPLY_ID))
+                                .add_attributes(attributes)
+                                .add_events(events)),
+                            None => Ok(Response::new().set_data(ack_fail(err))),
+                        }
+                    }
+                }
+            }
+            PARK_REPLY_ID => {
+                let receive_error = PARKING_ERROR.load(deps.storage)?;
+                PARKING_ERROR.remove(deps.storage);
+                match reply.result {
+                    SubMsgResult::Ok(_) => Ok(Response::new().set_data(ack_success())),
+                    // sender needs to know why the transfer wasn't received in the first place
+                    SubMsgResult::Err(err) => Ok(Response::new()
+                        .set_data(ack_fail(format!("{receive_error}; parking failed: {err}")))),
+                }
+            }
             ACK_CALLBACK_REPLY_ID => {
                 let err = reply.result.unwrap_err();
                 Ok(Response::new().add_attribute("error", err))
             }
+            HOOK_REPLY_ID => {
+                let err = reply.result.unwrap_err();
+                Ok(Response::new().add_attribute("hook_error", err))
+            }
+            MIGRATE_CW721_REPLY_ID => {
+                let mut migrating = MIGRATING_VOUCHER_COLLECTIONS.load(deps.storage)?;
+                let ClassIdInfo { class_id, address } = migrating.remove(0);
+                if migrating.is_empty() {
+                    MIGRATING_VOUCHER_COLLECTIONS.remove(deps.storage);
+                } else {
+                    MIGRATING_VOUCHER_COLLECTIONS.save(deps.storage, &migrating)?;
+                }
+                // a failed migration is reported, but doesn't revert others
+                let result = match reply.result {
+                    SubMsgResult::Ok(_) => "migrated".to_string(),
+                    SubMsgResult::Err(err) => format!("failed: {err}"),
+                };
+                Ok(Response::new().add_event(
+                    Event::new("ics721_migrate_voucher_collection")
+                        .add_attribute("class_id", class_id)
+                        .add_attribute("nft_contract", address)
+                        .add_attribute("result", result),
+                ))
+            }
             _ => Err(ContractError::UnrecognisedReplyId {}),
         }
     }
@@ -1,17 +1,14 @@
-use cosmwasm_std::{
-    from_json, to_json_binary, Binary, IbcAcknowledgement, IbcChannel, IbcEndpoint, IbcOrder,
-};
-use serde::{Deserialize, Serialize};
+use cosmwasm_std::{IbcChannel, IbcEndpoint, IbcOrder};
 
 use crate::{ibc::IBC_VERSION, ContractError};
 
+// Ack types moved to `ics721_types::ack`, re-exported for compatibility.
+pub use ics721_types::ack::{ack_fail, ack_success, try_get_ack_error, Ics721Ack};
+
 /// Tries to remove the source prefix from a given class_id. If the
 /// class_id does not begin with the given prefix, returns
 /// `None`. Otherwise, returns `Some(unprefixed)`.
-pub(crate) fn try_pop_source_prefix<'a>(
-    source: &IbcEndpoint,
-    class_id: &'a str,
-) -> Option<&'a str> {
+pub fn try_pop_source_prefix<'a>(source: &IbcEndpoint, class_id: &'a str) -> Option<&'a str> {
     let source_prefix = get_endpoint_prefix(source);
     // This must not panic in the face of non-ascii, or empty
     // strings. We can not trust classID as it comes from an external
@@ -20,66 +17,10 @@ pub(crate) fn try_pop_source_prefix<'a>(
 }
 
 /// Gets the classID prefix for a given IBC endpoint.
-pub(crate) fn get_endpoint_prefix(source: &IbcEndpoint) -> String {
+pub fn get_endpoint_prefix(source: &IbcEndpoint) -> String {
     format!("{}/{}/", source.port_id, source.channel_id)
 }
 
-/// The ICS721 spec is very vague about how ACKs are suposed to be
-/// encoded. To be honest, I don't think this method is correct at all
-/// if we were to follow the wording of the spec.
-///
-/// The intent of the spec though is to have the same ACK format as
-/// ICS20 which endodes its ACKs like this. This is compatible with
-/// the SDK ACK protobuf defined here:
-/// <https://github.com/cosmos/cosmos-sdk/blob/v0.42.0/proto/ibc/core/channel/v1/channel.proto#L141-L147>
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum Ics721Ack {
-    Result(Binary),
-    Error(String),
-}
-
-pub fn ack_success() -> Binary {
-    let res = Ics721Ack::Result(b"1".into());
-    to_json_binary(&res).unwrap()
-}
-
-pub fn ack_fail(err: String) -> Binary {
-    let res = Ics721Ack::Error(err);
-    to_json_binary(&res).unwrap()
-}
-
-/// Tries to get the error from an ACK. If an error exists, returns
-/// Some(error_message). Otherwise, returns `None`.
-///
-/// NOTE(ekez): there is a special case here where the contents of the
-/// ACK we receive are set by the SDK, and not by our counterparty
-/// contract. I do not know all cases this will occur, but I do know
-/// it happens if a field on the packet data is set to an empty
-/// string. That being the case, the SDK will return an error in the
-/// form:
-///
-/// ```json
-/// {"error":"Empty attribute value. Key: class_id: invalid event"}
-/// ```
-///
-/// Should this method encounter such an error, it will return a
-/// base64 encoded version of the error (as this is what it
-/// receives). For example, the above error is returned as:
-///
-/// ```json
-/// "eyJlcnJvciI6IkVtcHR5IGF0dHJpYnV0ZSB2YWx1ZS4gS2V5OiBjbGFzc19pZDogaW52YWxpZCBldmVudCJ9"
-/// ```
-pub fn try_get_ack_error(ack: &IbcAcknowledgement) -> Option<String> {
-    let ack: Ics721Ack =
-	// What we can not parse is an ACK fail.
-        from_json(&ack.data).unwrap_or_else(|_| Ics721Ack::Error(ack.data.to_base64()));
-    match ack {
-        Ics721Ack::Error(e) => Some(e),
-        _ => None,
-    }
-}
-
 /// Validates order and version information for ics721. We expect
 /// ics721-1 as the version and an unordered channel.
 pub(crate) fn validate_order_and_version(
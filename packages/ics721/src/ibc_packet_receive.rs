@@ -1,25 +1,37 @@
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Binary, Deps, DepsMut, Empty, Env, IbcPacket,
-    IbcReceiveResponse, StdResult, SubMsg, WasmMsg,
+    attr, from_json, to_json_binary, to_json_string, Addr, Attribute, Binary, Deps, DepsMut, Env,
+    Event, IbcEndpoint, IbcPacket, IbcReceiveResponse, IbcTimeout, StdResult, SubMsg, WasmMsg,
 };
 use zip_optional::Zippable;
 
 use crate::{
-    helpers::{generate_receive_callback_msg, get_incoming_proxy_msg, get_receive_callback},
-    ibc::ACK_AND_DO_NOTHING_REPLY_ID,
+    helpers::{
+        generate_receive_callback_msg, get_incoming_proxy_msg, get_receive_callback,
+        hook_subscribers,
+    },
+    ibc::{ACK_AND_DO_NOTHING_REPLY_ID, PARK_ON_FAILURE_REPLY_ID},
     ibc_helpers::{get_endpoint_prefix, try_pop_source_prefix},
-    msg::{CallbackMsg, ExecuteMsg},
+    msg::{CallbackMsg, ExecuteMsg, ReceivePlan, SimulateReceiveResponse},
     query::{
-        load_nft_contract_for_class_id, query_get_instantiate2_nft_contract,
-        query_nft_contract_for_class_id,
+        load_claimable_receipt_config, load_nft_contract_for_class_id,
+        query_get_instantiate2_nft_contract, query_nft_contract_for_class_id,
+        resolve_voucher_cw721_code_id,
+    },
+    state::{
+        ClaimableReceiptConfig, CollectionController, ParkedTransfer, PendingClaim,
+        INCOMING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_CLASS_TOKEN_TO_CHANNEL, PARKING_ERROR,
+        PARK_ON_FAILURE, PO, RECEIVER_TRANSLATION_CHANNELS, RECEIVING_PACKET,
+        VOUCHER_COLLECTION_CONTROLLERS,
     },
-    state::{CW721_CODE_ID, OUTGOING_CLASS_TOKEN_TO_CHANNEL, PO},
     token_types::{VoucherCreation, VoucherRedemption},
+    utils::{convert_wallet_address, translate_receiver},
     ContractError,
 };
 use ics721_types::{
-    ibc_types::NonFungibleTokenPacketData,
+    ack::ack_success,
+    ibc_types::{Ics721ControlPacketData, NonFungibleTokenPacketData},
     token_types::{Class, ClassId, Token, TokenId},
+    types::{Ics721HookMsg, Ics721HookTransfer},
 };
 
 pub(crate) fn receive_ibc_packet(
@@ -28,12 +40,135 @@ pub(crate) fn receive_ibc_packet(
     packet: IbcPacket,
 ) -> Result<IbcReceiveResponse, ContractError> {
     PO.error_if_paused(deps.storage)?;
+    if let Ok(control) = from_json::<Ics721ControlPacketData>(&packet.data) {
+        return receive_control_packet(deps, env, packet, control);
+    }
     let data: NonFungibleTokenPacketData = from_json(&packet.data)?;
     data.validate()?;
 
+    // in park on failure mode, packet is kept until its submessage is done, so it can be
+    // parked in case of failure
+    let park_on_failure = PARK_ON_FAILURE.may_load(deps.storage)?.unwrap_or(false);
+    let reply_id = match park_on_failure {
+        true => PARK_ON_FAILURE_REPLY_ID,
+        false => ACK_AND_DO_NOTHING_REPLY_ID,
+    };
+    let (_, message, attributes, events) =
+        receive_nft_packet(deps.as_ref(), &env, &packet, data, None)?;
+    if park_on_failure {
+        RECEIVING_PACKET.save(deps.storage, &packet)?;
+    }
+
+    Ok(IbcReceiveResponse::default()
+        .add_submessage(SubMsg::reply_always(message, reply_id))
+        .add_attributes(attributes)
+        .add_events(events))
+}
+
+/// Receives an undeliverable transfer into ICS721 custody, in case park on
+/// failure mode is enabled. Returns None, if it can't be parked either.
+pub(crate) fn park_ibc_packet(
+    deps: DepsMut,
+    env: &Env,
+    packet: &IbcPacket,
+    reason: String,
+) -> Option<(WasmMsg, Vec<Attribute>, Vec<Event>)> {
+    let (_, message, attributes, events) =
+        try_park_nft_packet(deps.as_ref(), env, packet, reason.clone())?;
+    PARKING_ERROR.save(deps.storage, &reason).ok()?;
+    Some((message, attributes, events))
+}
+
+/// Dry-runs receiving a transfer without any state changes, returning
+/// the plan or the error that would be acked. Only checks done before
+/// dispatching the receive submessage are simulated, e.g. the incoming
+/// proxy, cw721 and callback contracts aren't executed.
+pub(crate) fn simulate_receive(
+    deps: Deps,
+    env: &Env,
+    data: NonFungibleTokenPacketData,
+    src: IbcEndpoint,
+    dest: IbcEndpoint,
+) -> StdResult<SimulateReceiveResponse> {
+    let packet = IbcPacket::new(
+        to_json_binary(&data)?,
+        src,
+        dest,
+        0,
+        IbcTimeout::with_timestamp(env.block.time),
+    );
+    let result = PO
+        .error_if_paused(deps.storage)
+        .map_err(ContractError::from)
+        .and_then(|_| Ok(data.validate()?))
+        .and_then(|_| receive_nft_packet(deps, env, &packet, data, None))
+        .or_else(|error| try_park_nft_packet(deps, env, &packet, error.to_string()).ok_or(error));
+    Ok(match result {
+        Ok((plan, ..)) => SimulateReceiveResponse {
+            plan: Some(plan),
+            error: None,
+        },
+        Err(error) => SimulateReceiveResponse {
+            plan: None,
+            error: Some(error.to_string()),
+        },
+    })
+}
+
+fn try_park_nft_packet(
+    deps: Deps,
+    env: &Env,
+    packet: &IbcPacket,
+    reason: String,
+) -> Option<(ReceivePlan, WasmMsg, Vec<Attribute>, Vec<Event>)> {
+    if !PARK_ON_FAILURE
+        .may_load(deps.storage)
+        .ok()?
+        .unwrap_or(false)
+        || PO.error_if_paused(deps.storage).is_err()
+    {
+        return None;
+    }
+    let data: NonFungibleTokenPacketData = from_json(&packet.data).ok()?;
+    data.validate().ok()?;
+    receive_nft_packet(deps, env, packet, data, Some(reason)).ok()
+}
+
+/// Returns the plan and conjunction message for receiving a transfer,
+/// its attributes and events. Parked transfers are received into ICS721
+/// custody.
+fn receive_nft_packet(
+    deps: Deps,
+    env: &Env,
+    packet: &IbcPacket,
+    data: NonFungibleTokenPacketData,
+    parked_reason: Option<String>,
+) -> Result<(ReceivePlan, WasmMsg, Vec<Attribute>, Vec<Event>), ContractError> {
+    // receiver using the bech32 prefix of the source chain is translated, in case the
+    // channel is opted in
+    let original_receiver = data.receiver.clone();
+    let mut events = vec![];
+    let data = match deps.api.addr_validate(&data.receiver) {
+        Err(_) if RECEIVER_TRANSLATION_CHANNELS.has(deps.storage, &packet.dest.channel_id) => {
+            let receiver = translate_receiver(deps, env, &data.receiver)?;
+            events.push(
+                Event::new("ics721_receiver_translated")
+                    .add_attribute("channel_id", &packet.dest.channel_id)
+                    .add_attribute("original_receiver", &data.receiver)
+                    .add_attribute("receiver", &receiver),
+            );
+            NonFungibleTokenPacketData { receiver, ..data }
+        }
+        _ => data,
+    };
+
     // Check if NFT is local if not get the local class id
     let maybe_local_class_id = try_pop_source_prefix(&packet.src, &data.class_id);
-    let callback = get_receive_callback(&data);
+    // parked transfers aren't delivered, so receive callback isn't called
+    let callback = match parked_reason {
+        Some(_) => None,
+        None => get_receive_callback(&data),
+    };
     let local_class_id = if let Some(local_class_id) = maybe_local_class_id {
         ClassId::new(local_class_id)
     } else {
@@ -41,26 +176,18 @@ pub(crate) fn receive_ibc_packet(
         ClassId::new(format!("{}{}", local_prefix, data.class_id))
     };
 
-    // sub message holds 2 to 4 messages:
+    // sub message holds 2 to 5 messages:
     // - one message for voucher creation or redemption, another message for updating incoming or outgoing channel
-    let (is_redemption, voucher_and_channel_messages) = create_voucher_and_channel_messages(
-        deps.as_ref(),
-        env.clone(),
-        data.clone(),
-        maybe_local_class_id,
-        local_class_id.clone(),
-        packet.clone(),
-    )?;
-    // - one optional incoming proxy message
-    let incoming_proxy_msg =
-        get_incoming_proxy_msg(deps.as_ref().storage, packet.clone(), data.clone())?;
-    // - one optional callback message
-    // callback require the nft contract, get it using the class id from the action
-    let nft_contract = if is_redemption {
+    let redemption_or_create =
+        get_redemption_or_create(deps, &data, maybe_local_class_id, &local_class_id, packet)?;
+    let is_redemption = !redemption_or_create.0.is_empty();
+    // callback and receive event require the nft contract, get it using the class id from the action
+    let (nft_contract, instantiates_nft_contract) = if is_redemption {
         // If its a redemption, it means we already have the contract address in storage
 
-        load_nft_contract_for_class_id(deps.storage, local_class_id.to_string())
-            .map_err(|_| ContractError::NoNftContractForClassId(local_class_id.to_string()))
+        let nft_contract = load_nft_contract_for_class_id(deps.storage, local_class_id.to_string())
+            .map_err(|_| ContractError::NoNftContractForClassId(local_class_id.to_string()))?;
+        (nft_contract, false)
     } else {
         let nft_contract =
             match query_nft_contract_for_class_id(deps.storage, local_class_id.clone()) {
@@ -68,61 +195,249 @@ pub(crate) fn receive_ibc_packet(
                 Err(_) => None, // not found, occurs on initial transfer when we don't have the contract address
             };
         match nft_contract {
-            Some(nft_contract) => Ok(nft_contract),
+            Some(nft_contract) => (nft_contract, false),
             None => {
                 // contract not yet instantiated, so we use instantiate2 to get the contract address
-                let cw721_code_id = CW721_CODE_ID.load(deps.storage)?;
-                query_get_instantiate2_nft_contract(
-                    deps.as_ref(),
-                    &env,
+                // (code id is resolved by voucher collection rule or CW2981 config, default is CW721_CODE_ID)
+                let (cw721_code_id, _) =
+                    resolve_voucher_cw721_code_id(deps.storage, &local_class_id)?;
+                let nft_contract = query_get_instantiate2_nft_contract(
+                    deps,
+                    env,
                     local_class_id.clone(),
                     Some(cw721_code_id),
-                )
+                )?;
+                (nft_contract, true)
             }
         }
-    }?;
+    };
 
-    let callback_msg =
-        create_callback_msg(deps.as_ref(), &data, nft_contract.to_string(), callback)?;
+    let transfer = Ics721HookTransfer {
+        channel_id: packet.dest.channel_id.clone(),
+        class_id: local_class_id.clone(),
+        nft_contract: nft_contract.to_string(),
+        token_ids: data.token_ids.clone(),
+        sender: data.sender.clone(),
+        receiver: data.receiver.clone(),
+    };
+    let (voucher_message, channel_message) = create_voucher_and_channel_messages(
+        deps,
+        env.clone(),
+        data.clone(),
+        local_class_id.clone(),
+        packet.clone(),
+        redemption_or_create,
+        // parked transfers aren't delivered, so no receive event is emitted
+        parked_reason.is_none().then(|| transfer.clone()),
+    )?;
+    // - parked transfers are saved, once all other messages are done
+    let channel_message = match parked_reason.clone() {
+        Some(reason) => {
+            let park_msg = WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::ParkTransfer {
+                    channel_id: packet.dest.channel_id.clone(),
+                    sequence: packet.sequence,
+                    transfer: ParkedTransfer {
+                        data: data.clone(),
+                        class_id: local_class_id.clone(),
+                        reason,
+                    },
+                }))?,
+                funds: vec![],
+            };
+            WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::Conjunction {
+                    operands: vec![channel_message, park_msg],
+                }))?,
+                funds: vec![],
+            }
+        }
+        None => channel_message,
+    };
+    // - one optional incoming proxy message
+    let incoming_proxy_msg = get_incoming_proxy_msg(deps.storage, packet.clone(), data.clone())?;
+    // - one optional callback message
+    // in claimable receipt mode, receive callback isn't called, since vouchers are held by ICS721
+    let is_claimable = !is_redemption
+        && parked_reason.is_none()
+        && load_claimable_receipt_config(deps.storage, &packet.dest.channel_id)?.is_some();
+    let callback = if is_claimable { None } else { callback };
+    let callback_msg = create_callback_msg(deps, &data, nft_contract.to_string(), callback)?;
+    // - one optional hook message, parked transfers aren't delivered, so subscribers aren't
+    // notified
+    let hook_subscribers = match parked_reason {
+        Some(_) => vec![],
+        None => hook_subscribers(deps.storage, &transfer)?,
+    };
+    let hook_msg = match hook_subscribers.is_empty() {
+        true => None,
+        false => {
+            let hook = match is_redemption {
+                true => Ics721HookMsg::VoucherRedeem { transfer },
+                false => Ics721HookMsg::VoucherMint { transfer },
+            };
+            Some(WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::DispatchHook(hook)))?,
+                funds: vec![],
+            })
+        }
+    };
 
-    let submessage = into_submessage(
-        env.contract.address,
-        voucher_and_channel_messages.0,
-        voucher_and_channel_messages.1,
+    let plan = ReceivePlan {
+        local_class_id: local_class_id.clone(),
+        is_redemption,
+        nft_contract: nft_contract.clone(),
+        instantiates_nft_contract,
+        receiver: data.receiver.clone(),
+        receiver_translated: data.receiver != original_receiver,
+        is_claimable,
+        parked_reason: parked_reason.clone(),
+        incoming_proxy: match &incoming_proxy_msg {
+            Some(WasmMsg::Execute { contract_addr, .. }) => Some(contract_addr.clone()),
+            _ => None,
+        },
+        receive_callback: match &callback_msg {
+            Some(WasmMsg::Execute { contract_addr, .. }) => Some(contract_addr.clone()),
+            _ => None,
+        },
+        hook_subscribers,
+    };
+
+    let message = into_conjunction_msg(
+        env.contract.address.clone(),
+        voucher_message,
+        channel_message,
         callback_msg,
         incoming_proxy_msg,
+        hook_msg,
     )?;
 
-    let response = if let Some(memo) = data.memo {
-        IbcReceiveResponse::default().add_attribute("ics721_memo", memo)
-    } else {
-        IbcReceiveResponse::default()
+    let mut attributes = match data.memo {
+        Some(memo) => vec![attr("ics721_memo", memo)],
+        None => vec![],
     };
+    attributes.extend([
+        attr("method", "receive_ibc_packet"),
+        attr("nft_contract", nft_contract.to_string()),
+        attr("is_redemption", is_redemption.to_string()),
+        attr("is_claimable", is_claimable.to_string()),
+        attr("class_id", data.class_id),
+        attr("local_channel", packet.dest.channel_id.clone()),
+        attr("counterparty_channel", packet.src.channel_id.clone()),
+    ]);
+    if let Some(reason) = parked_reason {
+        attributes.push(attr("parked_reason", reason));
+    }
+    Ok((plan, message, attributes, events))
+}
 
-    Ok(response
-        .add_submessage(submessage)
-        .add_attribute("method", "receive_ibc_packet")
-        .add_attribute("nft_contract", nft_contract.to_string())
-        .add_attribute("is_redemption", is_redemption.to_string())
-        .add_attribute("class_id", data.class_id)
-        .add_attribute("local_channel", packet.dest.channel_id)
-        .add_attribute("counterparty_channel", packet.src.channel_id))
+fn receive_control_packet(
+    deps: DepsMut,
+    env: Env,
+    packet: IbcPacket,
+    data: Ics721ControlPacketData,
+) -> Result<IbcReceiveResponse, ContractError> {
+    data.validate()?;
+    match data {
+        Ics721ControlPacketData::CollectionOwnership {
+            class_id,
+            owner,
+            controller,
+        } => {
+            // class id with our prefix is a voucher on the sending chain, so it isn't its home chain
+            if try_pop_source_prefix(&packet.src, &class_id).is_some() {
+                return Err(ContractError::NotHomeCollection(class_id.to_string()));
+            }
+            let local_class_id =
+                ClassId::new(format!("{}{}", get_endpoint_prefix(&packet.dest), class_id));
+            // contract and multisig owners have no account on this chain
+            let controller = match controller {
+                Some(controller) => controller,
+                None => convert_wallet_address(deps.as_ref(), &env, &owner)
+                    .ok_or_else(|| ContractError::OwnerNotWalletAddress(owner.clone()))?,
+            };
+            let controller = deps.api.addr_validate(&controller)?;
+            VOUCHER_COLLECTION_CONTROLLERS.save(
+                deps.storage,
+                local_class_id.clone(),
+                &CollectionController {
+                    controller: controller.clone(),
+                    source_owner: owner.clone(),
+                    channel_id: packet.dest.channel_id.clone(),
+                },
+            )?;
+
+            Ok(IbcReceiveResponse::new()
+                .set_ack(ack_success())
+                .add_attribute("method", "receive_collection_ownership")
+                .add_attribute("local_channel", packet.dest.channel_id)
+                .add_attribute("counterparty_channel", packet.src.channel_id)
+                .add_event(
+                    Event::new("ics721_collection_ownership")
+                        .add_attribute("class_id", local_class_id)
+                        .add_attribute("source_owner", owner)
+                        .add_attribute("controller", controller),
+                ))
+        }
+        Ics721ControlPacketData::UpdateMetadata { class_id, tokens } => {
+            let local_class_id =
+                ClassId::new(format!("{}{}", get_endpoint_prefix(&packet.dest), class_id));
+            // only vouchers received on this channel may be updated
+            for token in &tokens {
+                let incoming_channel = INCOMING_CLASS_TOKEN_TO_CHANNEL
+                    .may_load(deps.storage, (local_class_id.clone(), token.id.clone()))?;
+                if incoming_channel.as_deref() != Some(packet.dest.channel_id.as_str()) {
+                    return Err(ContractError::TokenNotOnChannel {
+                        class_id: local_class_id.to_string(),
+                        token_id: token.id.clone().into(),
+                        channel: packet.dest.channel_id,
+                    });
+                }
+            }
+            let token_ids =
+                to_json_string(&tokens.iter().map(|t| t.id.clone()).collect::<Vec<_>>())?;
+            let update_msg = WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::UpdateTokenMetadata {
+                    class_id: local_class_id.clone(),
+                    tokens,
+                }))?,
+                funds: vec![],
+            };
+
+            Ok(IbcReceiveResponse::new()
+                .add_submessage(SubMsg::reply_always(
+                    update_msg,
+                    ACK_AND_DO_NOTHING_REPLY_ID,
+                ))
+                .add_attribute("method", "receive_metadata_update")
+                .add_attribute("class_id", local_class_id)
+                .add_attribute("token_ids", token_ids)
+                .add_attribute("local_channel", packet.dest.channel_id)
+                .add_attribute("counterparty_channel", packet.src.channel_id))
+        }
+    }
 }
 
-fn create_voucher_and_channel_messages(
+/// Splits the tokens of a transfer into tokens that are redeemed and
+/// tokens that vouchers are created for. A transfer can't have both.
+fn get_redemption_or_create(
     deps: Deps,
-    env: Env,
-    data: NonFungibleTokenPacketData,
+    data: &NonFungibleTokenPacketData,
     maybe_local_class_id: Option<&str>,
-    local_class_id: ClassId,
-    packet: IbcPacket,
-) -> Result<(bool, (WasmMsg, WasmMsg)), ContractError> {
+    local_class_id: &ClassId,
+    packet: &IbcPacket,
+) -> Result<(Vec<TokenId>, Vec<Token>), ContractError> {
     let token_count = data.token_ids.len();
     let redemption_or_create = data
         .token_ids
+        .clone()
         .into_iter()
-        .zip_optional(data.token_uris)
-        .zip_optional(data.token_data)
+        .zip_optional(data.token_uris.clone())
+        .zip_optional(data.token_data.clone())
         .try_fold(
             (
                 Vec::<TokenId>::with_capacity(token_count),
@@ -158,22 +473,36 @@ fn create_voucher_and_channel_messages(
                 Ok(redemption_or_create)
             },
         )?;
-    let is_redemption = if !redemption_or_create.0.is_empty() && !redemption_or_create.1.is_empty()
-    {
+    if !redemption_or_create.0.is_empty() && !redemption_or_create.1.is_empty() {
         // All token ids in the transfer must be either a redeption or creation
         // they can't be both, if they are both something is wrong.
         return Err(ContractError::InvalidTransferBothActions);
-    } else if !redemption_or_create.0.is_empty() {
-        true
-    } else if !redemption_or_create.1.is_empty() {
-        false
-    } else {
+    } else if redemption_or_create.0.is_empty() && redemption_or_create.1.is_empty() {
         // This should never happen, as we must have at least 1 of the above actions
         return Err(ContractError::InvalidTransferNoAction);
-    };
+    }
+    Ok(redemption_or_create)
+}
 
-    let receiver = deps.api.addr_validate(&data.receiver)?;
-    let voucher_and_channel_messages = match is_redemption {
+/// Returns the voucher creation or redemption message and the channel
+/// message. `transfer` is the delivered transfer, for emitting the
+/// receive event. Parked transfers have none, their NFTs are received
+/// into ICS721 custody.
+fn create_voucher_and_channel_messages(
+    deps: Deps,
+    env: Env,
+    data: NonFungibleTokenPacketData,
+    local_class_id: ClassId,
+    packet: IbcPacket,
+    redemption_or_create: (Vec<TokenId>, Vec<Token>),
+    transfer: Option<Ics721HookTransfer>,
+) -> Result<(WasmMsg, WasmMsg), ContractError> {
+    let custody = transfer.is_none();
+    let receiver = match custody {
+        true => env.contract.address.clone(),
+        false => deps.api.addr_validate(&data.receiver)?,
+    };
+    let voucher_and_channel_messages = match !redemption_or_create.0.is_empty() {
         true => {
             let redemption = VoucherRedemption {
                 class: Class {
@@ -197,11 +526,24 @@ fn create_voucher_and_channel_messages(
                 funds: vec![],
             };
             (
-                redemption.into_wasm_msg(env.contract.address.clone(), receiver.to_string())?,
+                redemption.into_wasm_msg(
+                    env.contract.address.clone(),
+                    receiver.to_string(),
+                    transfer,
+                )?,
                 redeem_outgoing_channels_msg,
             )
         }
         false => {
+            // in claimable receipt mode, vouchers are held by ICS721 until claimed by receiver
+            let claimable_receipt = match custody {
+                true => None,
+                false => load_claimable_receipt_config(deps.storage, &packet.dest.channel_id)?,
+            };
+            let voucher_receiver = match claimable_receipt {
+                Some(_) => env.contract.address.clone(),
+                None => receiver.clone(),
+            };
             let creation = VoucherCreation {
                 class: Class {
                     id: local_class_id.clone(),
@@ -228,14 +570,52 @@ fn create_voucher_and_channel_messages(
                 ))?,
                 funds: vec![],
             };
+            let channel_msg = match claimable_receipt {
+                Some(ClaimableReceiptConfig { expiry, .. }) => {
+                    let pending_claims: Vec<((ClassId, TokenId), PendingClaim)> = creation
+                        .tokens
+                        .iter()
+                        .map(|token| {
+                            (
+                                (local_class_id.clone(), token.id.clone()),
+                                PendingClaim {
+                                    receiver: receiver.clone(),
+                                    sender: data.sender.clone(),
+                                    channel_id: packet.dest.channel_id.clone(),
+                                    expires_at: env.block.time.plus_seconds(expiry),
+                                },
+                            )
+                        })
+                        .collect();
+                    let add_pending_claims_msg = WasmMsg::Execute {
+                        contract_addr: env.contract.address.to_string(),
+                        msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::AddPendingClaims(
+                            pending_claims,
+                        )))?,
+                        funds: vec![],
+                    };
+                    WasmMsg::Execute {
+                        contract_addr: env.contract.address.to_string(),
+                        msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::Conjunction {
+                            operands: vec![add_incoming_channels_msg, add_pending_claims_msg],
+                        }))?,
+                        funds: vec![],
+                    }
+                }
+                None => add_incoming_channels_msg,
+            };
             (
-                creation.into_wasm_msg(env.contract.address.clone(), receiver.to_string())?,
-                add_incoming_channels_msg,
+                creation.into_wasm_msg(
+                    env.contract.address.clone(),
+                    voucher_receiver.to_string(),
+                    transfer,
+                )?,
+                channel_msg,
             )
         }
     };
 
-    Ok((is_redemption, voucher_and_channel_messages))
+    Ok(voucher_and_channel_messages)
 }
 
 fn create_callback_msg(
@@ -257,14 +637,15 @@ fn create_callback_msg(
     }
 }
 
-pub fn into_submessage(
+pub fn into_conjunction_msg(
     contract: Addr,
     voucher_message: WasmMsg,
     channel_message: WasmMsg,
     callback_msg: Option<WasmMsg>,
     incoming_proxy_msg: Option<WasmMsg>,
-) -> StdResult<SubMsg<Empty>> {
-    let mut operands = Vec::with_capacity(4); // 4 is the max number of submessages we can have
+    hook_msg: Option<WasmMsg>,
+) -> StdResult<WasmMsg> {
+    let mut operands = Vec::with_capacity(5); // 5 is the max number of submessages we can have
     if let Some(incoming_proxy_msg) = incoming_proxy_msg {
         operands.push(incoming_proxy_msg)
     }
@@ -278,10 +659,14 @@ pub fn into_submessage(
     // once all other submessages are done, we can update incoming or outgoing channel
     operands.push(channel_message);
 
-    let message = WasmMsg::Execute {
+    // hook subscribers are notified last, once the transfer is done
+    if let Some(hook_msg) = hook_msg {
+        operands.push(hook_msg)
+    }
+
+    Ok(WasmMsg::Execute {
         contract_addr: contract.into_string(),
         msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }))?,
         funds: vec![],
-    };
-    Ok(SubMsg::reply_always(message, ACK_AND_DO_NOTHING_REPLY_ID))
+    })
 }
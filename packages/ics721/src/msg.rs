@@ -1,9 +1,20 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, WasmMsg};
+use cosmwasm_std::{Addr, Binary, IbcEndpoint, IbcTimeout, WasmMsg};
 use cw_cii::ContractInstantiateInfo;
 
-use crate::token_types::{VoucherCreation, VoucherRedemption};
-use ics721_types::token_types::{Class, ClassId, ClassToken, Token, TokenId};
+use crate::{
+    state::{
+        ChannelTimeoutConfig, ClaimableReceiptConfig, CollectionController,
+        CounterpartyAddressPolicy, Cw2981Config, HookSubscription, ParkedTransfer,
+        ParkedTransferAction, PendingClaim, TransferStats, VoucherCollectionRule,
+    },
+    token_types::{VoucherCreation, VoucherRedemption},
+};
+use ics721_types::{
+    ibc_types::NonFungibleTokenPacketData,
+    token_types::{Class, ClassId, ClassToken, Token, TokenId},
+    types::{Ics721HookMsg, Ics721HookTransfer},
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -67,6 +78,195 @@ pub enum ExecuteMsg {
         class_id: String,
         collection: String,
     },
+
+    /// Admin msg for linking an existing cw721 contract as voucher
+    /// collection of a class ID (e.g. collections created by a
+    /// previous bridge). ICS721 must be the minter of the collection.
+    /// Afterwards incoming NFTs of this class ID are minted on the
+    /// collection instead of instantiating a new one.
+    RegisterVoucherCollection {
+        class_id: String,
+        nft_contract: String,
+        /// Class metadata stored for the class ID. Its ID must match
+        /// `class_id`. In case of None, a class without URI and data
+        /// is stored.
+        class: Option<Class>,
+    },
+
+    /// Admin msg for migrating voucher collections to a new cw721
+    /// code ID. Only collections where ICS721 is the (CosmWasm level)
+    /// admin are migrated, others are skipped. Each collection emits an
+    /// `ics721_migrate_voucher_collection` event with the result.
+    /// Native collections are not migrated. The `done` attribute is
+    /// `false` in case there may be more collections, then the
+    /// `next_start_after` attribute holds the class ID for the next
+    /// call.
+    MigrateVoucherCollections {
+        new_code_id: u64,
+        /// The migrate msg passed to each collection.
+        msg: Binary,
+        /// Class ID to start after.
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Admin msg for setting a rule for instantiating new voucher
+    /// collections matching an exact class ID or class ID prefix. In
+    /// case of None, the rule is removed. Existing voucher collections
+    /// are not affected.
+    SetVoucherCollectionRule {
+        key: VoucherCollectionRuleKey,
+        rule: Option<VoucherCollectionRule>,
+    },
+
+    /// Admin msg for setting the CW2981 config. New voucher collections
+    /// are then instantiated as CW2981 collections, unless a voucher
+    /// collection rule with another code ID applies. In case of None,
+    /// vouchers are minted without royalties.
+    SetCw2981Config { config: Option<Cw2981Config> },
+
+    /// Sends a `CollectionOwnership` control packet, attesting that the
+    /// sender owns the collection on this chain. Only the collection
+    /// owner may call this, and only for collections originating from
+    /// this chain (not for voucher collections).
+    SendCollectionOwnership {
+        collection: String,
+        /// The *local* channel ID the packet is sent on.
+        channel_id: String,
+        /// The address controlling the voucher collection on the
+        /// *receiving chain*. In case of None, the receiving chain
+        /// converts the sender to an address of its chain. This only
+        /// works for wallets, so it must be provided in case the sender
+        /// is a contract or multisig, otherwise the packet is acked with
+        /// an error.
+        controller: Option<String>,
+        timeout: IbcTimeout,
+    },
+
+    /// Updates the class metadata of a voucher collection. Only
+    /// callable by the controller of the voucher collection, as
+    /// attested by its home chain. Afterwards, incoming transfers no
+    /// longer overwrite the class metadata.
+    UpdateVoucherClassMetadata {
+        class_id: String,
+        uri: Option<String>,
+        data: Option<Binary>,
+    },
+
+    /// Sends an `UpdateMetadata` control packet with the current token
+    /// URI and data of NFTs escrowed on the channel, so the receiving
+    /// chain updates their vouchers. Only callable by the collection
+    /// owner or the ICS721 admin. Token URIs are only applied if the
+    /// voucher collection supports it.
+    SendMetadataUpdate {
+        collection: String,
+        token_ids: Vec<String>,
+        /// The *local* channel ID the NFTs have been sent out on.
+        channel_id: String,
+        timeout: IbcTimeout,
+    },
+
+    /// Admin msg for setting the claimable receipt config, globally or
+    /// for a local channel. In claimable receipt mode, incoming vouchers
+    /// are held by ICS721 until the receiver claims or rejects them. In
+    /// case of None, the config is removed.
+    SetClaimableReceiptConfig {
+        channel_id: Option<String>,
+        config: Option<ClaimableReceiptConfig>,
+    },
+
+    /// Transfers vouchers pending in claimable receipt mode to the
+    /// receiver. Only callable by the receiver.
+    ClaimNfts {
+        class_id: String,
+        token_ids: Vec<String>,
+    },
+
+    /// Sends vouchers pending in claimable receipt mode back to the
+    /// sender on the source chain, using the channel they have been
+    /// received on and its default timeout. ICS721 is the sender of the
+    /// vouchers, so in case the return fails, the claims are pending
+    /// again. Only callable by the receiver.
+    RejectNfts {
+        class_id: String,
+        token_ids: Vec<String>,
+    },
+
+    /// Same as `RejectNfts`, but callable by anyone once the pending
+    /// claims have expired.
+    ReturnExpiredClaims {
+        class_id: String,
+        token_ids: Vec<String>,
+    },
+
+    /// Admin msg for enabling park on failure mode. Incoming transfers
+    /// that can't be delivered (e.g. invalid receiver or failing receive
+    /// callback) are parked in ICS721 custody, instead of acking an
+    /// error.
+    SetParkOnFailure { enabled: bool },
+
+    /// Delivers a parked transfer to a corrected receiver, or sends it
+    /// back to the sender on the source chain. Only callable by admin or
+    /// the sender (its address on this chain).
+    ResolveParkedTransfer {
+        channel_id: String,
+        sequence: u64,
+        action: ParkedTransferAction,
+    },
+
+    /// Admin msg for opting a local channel in to receiver translation.
+    /// Incoming receivers using the bech32 prefix of the source chain
+    /// (e.g. `juno1XXX`) are translated to this chain (e.g. `stars1XXX`).
+    /// Only applies to wallet addresses.
+    SetReceiverTranslation { channel_id: String, enabled: bool },
+
+    /// Admin msg for setting the address policy of the counterparty
+    /// chain for a local channel. Outgoing receivers not matching it are
+    /// rejected, except for refunds of pending claims and parked
+    /// transfers to their source sender. In case of None, the policy is
+    /// removed and any receiver is accepted.
+    SetCounterpartyAddressPolicy {
+        channel_id: String,
+        policy: Option<CounterpartyAddressPolicy>,
+    },
+
+    /// Admin msg for setting the timeout config of outgoing transfers
+    /// for a local channel. In case of None, the config is removed and
+    /// outgoing msgs must provide a timeout. Returned pending claims use
+    /// the default timeout, returned parked transfers their given
+    /// timeout.
+    SetChannelTimeoutConfig {
+        channel_id: String,
+        config: Option<ChannelTimeoutConfig>,
+    },
+
+    /// Admin msg for adding or updating a hook subscriber contract,
+    /// which is notified by an `Ics721HookMsg` on transfer lifecycle
+    /// events. In case of None, the subscriber is removed. At most 10
+    /// subscribers can be added, each hook runs with a gas limit.
+    SetHookSubscriber {
+        addr: String,
+        subscription: Option<HookSubscription>,
+    },
+
+    /// Indexes up to `limit` channel entries saved before channel
+    /// entries were indexed by channel, continuing where the previous
+    /// call stopped. In the same pass, transfer stats are seeded in case
+    /// there were none before. Started by migrate. Anyone may call this,
+    /// since it only re-saves existing entries. The `done` attribute is
+    /// `false` in case there may be more entries. Transfers until done
+    /// may skew the seeded stats, so the contract may be paused
+    /// meanwhile.
+    BuildChannelIndexes { limit: Option<u32> },
+}
+
+#[cw_serde]
+pub enum VoucherCollectionRuleKey {
+    /// Rule applies to this class ID only.
+    ClassId(String),
+    /// Rule applies to all class IDs starting with this prefix (e.g.
+    /// `wasm.xyz/channel-5/`).
+    Prefix(String),
 }
 
 #[cw_serde]
@@ -77,17 +277,43 @@ pub enum CallbackMsg {
         receiver: String,
         /// Information about the vouchers being created.
         create: VoucherCreation,
+        /// The delivered transfer, emitted as receive event. None for
+        /// parked transfers.
+        #[serde(default)]
+        transfer: Option<Ics721HookTransfer>,
     },
     RedeemVouchers {
         /// The address that should receive the tokens.
         receiver: String,
         /// Information about the vouchers been redeemed.
         redeem: VoucherRedemption,
+        /// The delivered transfer, emitted as receive event. None for
+        /// parked transfers.
+        #[serde(default)]
+        transfer: Option<Ics721HookTransfer>,
     },
     /// Redeem all entries in outgoing channel.
     RedeemOutgoingChannelEntries(Vec<(ClassId, TokenId)>),
     /// Save all entries in incoming channel.
     AddIncomingChannelEntries(Vec<((ClassId, TokenId), String)>),
+    /// Updates token metadata and, if supported by the voucher
+    /// collection, token URI of vouchers, as received by an
+    /// `UpdateMetadata` control packet.
+    UpdateTokenMetadata {
+        class_id: ClassId,
+        tokens: Vec<Token>,
+    },
+    /// Save all pending claims of vouchers held by ICS721.
+    AddPendingClaims(Vec<((ClassId, TokenId), PendingClaim)>),
+    /// Save an incoming transfer parked in ICS721 custody.
+    ParkTransfer {
+        channel_id: String,
+        sequence: u64,
+        transfer: ParkedTransfer,
+    },
+    /// Notifies hook subscribers about an incoming transfer, once its
+    /// vouchers have been minted or redeemed.
+    DispatchHook(Ics721HookMsg),
     /// Mints a NFT of collection class_id for receiver with the
     /// provided id and metadata. Only callable by this contract.
     Mint {
@@ -126,7 +352,10 @@ pub enum QueryMsg {
     NftContract { class_id: String },
 
     /// Returns predictable NFT contract using instantiate2. If no
-    /// cw721_code_id is provided, default cw721_code_id from storage is used.
+    /// cw721_code_id is provided, the code ID is resolved the same way
+    /// as on receive: the code ID of the matching voucher collection
+    /// rule, else the CW2981 code ID (if configured), or else default
+    /// cw721_code_id from storage.
     #[returns(::cosmwasm_std::Addr)]
     GetInstantiate2NftContract {
         class_id: String,
@@ -176,6 +405,74 @@ pub enum QueryMsg {
     #[returns(Option<u32>)]
     ContractAddrLength {},
 
+    /// Gets the voucher collection rule used for instantiating the
+    /// collection of a class ID. An exact class ID rule takes
+    /// precedence, otherwise the longest matching prefix rule is used.
+    /// In case of None, `Cw721CodeId` and `Cw721Admin` are used.
+    #[returns(Option<VoucherCollectionRule>)]
+    VoucherCollectionRule { class_id: String },
+
+    /// Gets the config for instantiating CW2981 voucher collections, if
+    /// one is set.
+    #[returns(Option<Cw2981Config>)]
+    Cw2981Config {},
+
+    /// Gets the controller of the voucher collection of a class ID,
+    /// as attested by its home chain. If there is no controller,
+    /// returns None.
+    #[returns(Option<CollectionController>)]
+    VoucherCollectionController { class_id: String },
+
+    /// Gets the claimable receipt config applying to a local channel,
+    /// or the global config in case of no channel. Returns None if
+    /// there is no config.
+    #[returns(Option<ClaimableReceiptConfig>)]
+    ClaimableReceiptConfig { channel_id: Option<String> },
+
+    /// Gets a list of pending claims, optionally filtered by receiver.
+    #[returns(Vec<((ClassId, TokenId), PendingClaim)>)]
+    PendingClaims {
+        receiver: Option<String>,
+        start_after: Option<ClassToken>,
+        limit: Option<u32>,
+    },
+
+    /// Gets whether park on failure mode is enabled.
+    #[returns(bool)]
+    ParkOnFailure {},
+
+    /// Gets the transfer parked for a packet sequence on a local channel.
+    #[returns(Option<ParkedTransfer>)]
+    ParkedTransfer { channel_id: String, sequence: u64 },
+
+    /// Gets a list of parked transfers by local channel and packet
+    /// sequence.
+    #[returns(Vec<((String, u64), ParkedTransfer)>)]
+    ParkedTransfers {
+        start_after: Option<(String, u64)>,
+        limit: Option<u32>,
+    },
+
+    /// Gets the address policy of the counterparty chain for a local
+    /// channel.
+    #[returns(Option<CounterpartyAddressPolicy>)]
+    CounterpartyAddressPolicy { channel_id: String },
+
+    /// Gets the timeout config of outgoing transfers for a local channel.
+    #[returns(Option<ChannelTimeoutConfig>)]
+    ChannelTimeoutConfig { channel_id: String },
+
+    /// Gets a list of hook subscriber contracts and their subscriptions.
+    #[returns(Vec<(Addr, HookSubscription)>)]
+    HookSubscribers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Gets whether receivers are translated for a local channel.
+    #[returns(bool)]
+    ReceiverTranslation { channel_id: String },
+
     /// Gets a list of classID as key (from
     /// NonFungibleTokenPacketData) and cw721 contract as value
     /// (instantiated for that classID).
@@ -202,6 +499,205 @@ pub enum QueryMsg {
         start_after: Option<ClassToken>,
         limit: Option<u32>,
     },
+
+    /// Gets a list of classID and tokenID of NFTs sent out on a local
+    /// channel.
+    #[returns(Vec<ClassToken>)]
+    OutgoingNftsByChannel {
+        channel_id: String,
+        start_after: Option<ClassToken>,
+        limit: Option<u32>,
+    },
+
+    /// Gets a list of classID and tokenID of NFTs that have arrived at
+    /// this contract on a local channel.
+    #[returns(Vec<ClassToken>)]
+    IncomingNftsByChannel {
+        channel_id: String,
+        start_after: Option<ClassToken>,
+        limit: Option<u32>,
+    },
+
+    /// Gets a list of tokenID and local channel ID of NFTs of a class
+    /// sent out by this contract.
+    #[returns(Vec<(TokenId, String)>)]
+    OutgoingChannelsByClass {
+        class_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Gets a list of tokenID and local channel ID of NFTs of a class
+    /// that have arrived at this contract.
+    #[returns(Vec<(TokenId, String)>)]
+    IncomingChannelsByClass {
+        class_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Audits escrowed NFTs and vouchers against the outgoing and
+    /// incoming channel entries and token metadata stored by this
+    /// contract. Lists outgoing entries for NFTs not owned by ICS721,
+    /// NFTs owned by ICS721 without an outgoing entry, incoming
+    /// entries whose voucher no longer exists, and token metadata
+    /// without an incoming entry.
+    ///
+    /// `limit` is the number of (class ID, token ID) pairs inspected,
+    /// not the number of findings. Continue with `next_start_after`
+    /// until it is `None`.
+    #[returns(AuditEscrowResponse)]
+    AuditEscrow {
+        class_id: Option<String>,
+        start_after: Option<ClassToken>,
+        limit: Option<u32>,
+    },
+
+    /// Dry-runs receiving a transfer from `src` on local `dest`, without
+    /// any state changes. Returns what receiving it would do, or the
+    /// error that would be acked.
+    #[returns(SimulateReceiveResponse)]
+    SimulateReceive {
+        packet_data: NonFungibleTokenPacketData,
+        src: IbcEndpoint,
+        dest: IbcEndpoint,
+    },
+
+    /// Previews the packets sending `token_ids` of `collection` to
+    /// `receiver` over `channel_id` would emit, without any state
+    /// changes. Timeouts are resolved the same way as in
+    /// `IbcOutgoingMsg`.
+    #[returns(PreviewSendResponse)]
+    PreviewSend {
+        collection: String,
+        token_ids: Vec<String>,
+        receiver: String,
+        channel_id: String,
+        timeout: Option<IbcTimeout>,
+        relative_timeout_seconds: Option<u64>,
+        memo: Option<String>,
+    },
+
+    /// Gets the transfer stats of a local class ID.
+    #[returns(TransferStats)]
+    ClassStats { class_id: String },
+
+    /// Gets the transfer stats of a local channel.
+    #[returns(TransferStats)]
+    ChannelStats { channel_id: String },
+
+    /// Gets the transfer stats across all classes and channels.
+    #[returns(TransferStats)]
+    GlobalStats {},
+
+    /// Checks the transfer stats of a local channel, or the global stats
+    /// in case of `None`, against the outgoing and incoming channel
+    /// entries.
+    #[returns(StatsInvariantResponse)]
+    StatsInvariant { channel_id: Option<String> },
+}
+
+/// A drift between ICS721 state and cw721 ownership.
+#[cw_serde]
+pub enum AuditIssue {
+    /// There is an outgoing channel entry, but the NFT is not owned by
+    /// ICS721. `owner` is `None` if the NFT doesn't exist.
+    OutgoingNotEscrowed { owner: Option<String> },
+    /// The NFT is owned by ICS721, but there is no outgoing channel
+    /// entry.
+    EscrowedWithoutOutgoing {},
+    /// There is an incoming channel entry, but the voucher doesn't
+    /// exist.
+    IncomingWithoutVoucher { channel: String },
+    /// There is token metadata, but no incoming channel entry.
+    MetadataWithoutIncoming {},
+}
+
+#[cw_serde]
+pub struct AuditFinding {
+    pub class_id: ClassId,
+    pub token_id: TokenId,
+    pub nft_contract: Addr,
+    pub issue: AuditIssue,
+    /// The admin `ExecuteMsg` variant that fixes this finding, e.g.
+    /// `admin_clean_and_burn_nft`.
+    pub fix: String,
+}
+
+/// What receiving a transfer would do, as simulated by `SimulateReceive`.
+#[cw_serde]
+pub struct ReceivePlan {
+    /// The local class ID the transfer maps to.
+    pub local_class_id: ClassId,
+    /// Whether NFTs sent out before are returned, or vouchers are
+    /// created.
+    pub is_redemption: bool,
+    /// The nft contract receiving the transfer.
+    pub nft_contract: Addr,
+    /// Whether the voucher collection is instantiated (instantiate2)
+    /// first.
+    pub instantiates_nft_contract: bool,
+    /// The receiver, translated in case the channel is opted in.
+    pub receiver: String,
+    pub receiver_translated: bool,
+    /// Whether vouchers are held by ICS721 until claimed by receiver.
+    pub is_claimable: bool,
+    /// In park on failure mode, the reason the transfer is parked in
+    /// ICS721 custody.
+    pub parked_reason: Option<String>,
+    /// The incoming proxy validating the packet. Its validation isn't
+    /// simulated.
+    pub incoming_proxy: Option<String>,
+    /// The contract called by the receive callback.
+    pub receive_callback: Option<String>,
+    /// The hook subscribers notified about the transfer.
+    pub hook_subscribers: Vec<String>,
+}
+
+#[cw_serde]
+pub struct SimulateReceiveResponse {
+    /// `None` in case the transfer is rejected.
+    pub plan: Option<ReceivePlan>,
+    /// The error acked in case the transfer is rejected.
+    pub error: Option<String>,
+}
+
+#[cw_serde]
+pub struct StatsInvariantResponse {
+    pub stats: TransferStats,
+    /// Number of outgoing channel entries, expected to equal `escrowed`.
+    pub outgoing_entries: u64,
+    /// Number of incoming channel entries, expected to equal
+    /// `vouchers_minted - vouchers_burned`.
+    pub incoming_entries: u64,
+    /// Whether both expectations hold.
+    pub holds: bool,
+}
+
+/// A packet, as previewed by `PreviewSend`.
+#[cw_serde]
+pub struct OutgoingPacketPreview {
+    pub packet: NonFungibleTokenPacketData,
+    /// Whether the NFT is a voucher going back to its source chain,
+    /// in which case the counterparty redeems instead of minting it.
+    pub is_returning_voucher: bool,
+}
+
+#[cw_serde]
+pub struct PreviewSendResponse {
+    /// One packet per token, since each NFT is sent in its own packet.
+    pub packets: Vec<OutgoingPacketPreview>,
+    /// The error sending would fail with, in which case `packets` is
+    /// empty.
+    pub error: Option<String>,
+}
+
+#[cw_serde]
+pub struct AuditEscrowResponse {
+    pub findings: Vec<AuditFinding>,
+    /// The last (class ID, token ID) pair inspected. `None` once all
+    /// pairs have been inspected.
+    pub next_start_after: Option<ClassToken>,
 }
 
 #[cw_serde]
@@ -1,18 +1,36 @@
+use std::collections::BTreeSet;
+
 use cosmwasm_std::{to_json_binary, Addr, Binary, Deps, Env, Order, StdError, StdResult, Storage};
-use cw_storage_plus::{Bound, Map};
+use cw_storage_plus::{Bound, IndexedMap};
 use sha2::{Digest, Sha256};
 
 use crate::{
-    helpers::get_instantiate2_address,
-    msg::QueryMsg,
+    helpers::{get_instantiate2_address, resolve_outgoing_timeout, validate_counterparty_receiver},
+    ibc_packet_receive::simulate_receive,
+    msg::{
+        AuditEscrowResponse, AuditFinding, AuditIssue, OutgoingPacketPreview, PreviewSendResponse,
+        QueryMsg, StatsInvariantResponse,
+    },
     state::{
-        UniversalAllNftInfoResponse, ADMIN_USED_FOR_CW721, CLASS_ID_AND_NFT_CONTRACT_INFO,
-        CLASS_ID_TO_CLASS, CONTRACT_ADDR_LENGTH, CW721_CODE_ID, INCOMING_CLASS_TOKEN_TO_CHANNEL,
-        INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PO, TOKEN_METADATA,
+        ChannelIndexes, ClaimableReceiptConfig, ClassIdInfo, Cw2981Config, HookSubscription,
+        ParkedTransfer, PendingClaim, UniversalAllNftInfoResponse, UniversalOwnerOfResponse,
+        VoucherCollectionRule, ADMIN_USED_FOR_CW721, CHANNEL_STATS, CHANNEL_TIMEOUT_CONFIGS,
+        CLAIMABLE_RECEIPT_CONFIG, CLAIMABLE_RECEIPT_CONFIG_BY_CHANNEL,
+        CLASS_ID_AND_NFT_CONTRACT_INFO, CLASS_ID_TO_CLASS, CLASS_STATS, CONTRACT_ADDR_LENGTH,
+        COUNTERPARTY_ADDRESS_POLICIES, CW2981_CONFIG, CW721_CODE_ID, GLOBAL_STATS,
+        HOOK_SUBSCRIBERS, INCOMING_CLASS_TOKEN_TO_CHANNEL, INCOMING_PROXY,
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PARKED_TOKENS, PARKED_TRANSFERS,
+        PARK_ON_FAILURE, PENDING_CLAIMS, PO, RECEIVER_TRANSLATION_CHANNELS, TOKEN_METADATA,
+        VOUCHER_COLLECTION_CONTROLLERS, VOUCHER_COLLECTION_RULES_BY_CLASS_ID,
+        VOUCHER_COLLECTION_RULES_BY_PREFIX,
     },
+    utils::{get_token_data, query_collection_data},
     ContractError,
 };
-use ics721_types::token_types::{Class, ClassId, ClassToken, Token, TokenId};
+use ics721_types::{
+    ibc_types::{IbcOutgoingMsg, NonFungibleTokenPacketData},
+    token_types::{Class, ClassId, ClassToken, Token, TokenId},
+};
 
 pub trait Ics721Query {
     fn query(&self, deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
@@ -52,6 +70,55 @@ pub trait Ics721Query {
             QueryMsg::ContractAddrLength {} => Ok(to_json_binary(
                 &CONTRACT_ADDR_LENGTH.may_load(deps.storage)?,
             )?),
+            QueryMsg::VoucherCollectionRule { class_id } => Ok(to_json_binary(
+                &load_voucher_collection_rule(deps.storage, &ClassId::new(class_id))?,
+            )?),
+            QueryMsg::Cw2981Config {} => {
+                Ok(to_json_binary(&CW2981_CONFIG.may_load(deps.storage)?)?)
+            }
+            QueryMsg::VoucherCollectionController { class_id } => Ok(to_json_binary(
+                &VOUCHER_COLLECTION_CONTROLLERS.may_load(deps.storage, ClassId::new(class_id))?,
+            )?),
+            QueryMsg::ClaimableReceiptConfig { channel_id } => {
+                Ok(to_json_binary(&match channel_id {
+                    Some(channel_id) => query_claimable_receipt_config(deps.storage, &channel_id)?,
+                    None => CLAIMABLE_RECEIPT_CONFIG.may_load(deps.storage)?,
+                })?)
+            }
+            QueryMsg::PendingClaims {
+                receiver,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_pending_claims(
+                deps,
+                receiver,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::ParkOnFailure {} => Ok(to_json_binary(
+                &PARK_ON_FAILURE.may_load(deps.storage)?.unwrap_or(false),
+            )?),
+            QueryMsg::ParkedTransfer {
+                channel_id,
+                sequence,
+            } => Ok(to_json_binary(
+                &PARKED_TRANSFERS.may_load(deps.storage, (&channel_id, sequence))?,
+            )?),
+            QueryMsg::ParkedTransfers { start_after, limit } => Ok(to_json_binary(
+                &query_parked_transfers(deps, start_after, limit)?,
+            )?),
+            QueryMsg::CounterpartyAddressPolicy { channel_id } => Ok(to_json_binary(
+                &COUNTERPARTY_ADDRESS_POLICIES.may_load(deps.storage, &channel_id)?,
+            )?),
+            QueryMsg::ChannelTimeoutConfig { channel_id } => Ok(to_json_binary(
+                &CHANNEL_TIMEOUT_CONFIGS.may_load(deps.storage, &channel_id)?,
+            )?),
+            QueryMsg::HookSubscribers { start_after, limit } => Ok(to_json_binary(
+                &query_hook_subscribers(deps, start_after, limit)?,
+            )?),
+            QueryMsg::ReceiverTranslation { channel_id } => Ok(to_json_binary(
+                &RECEIVER_TRANSLATION_CHANNELS.has(deps.storage, &channel_id),
+            )?),
             QueryMsg::NftContracts { start_after, limit } => Ok(to_json_binary(
                 &query_nft_contracts(deps, start_after, limit)?,
             )?),
@@ -61,7 +128,219 @@ pub trait Ics721Query {
             QueryMsg::IncomingChannels { start_after, limit } => Ok(to_json_binary(
                 &query_channels(deps, &INCOMING_CLASS_TOKEN_TO_CHANNEL, start_after, limit)?,
             )?),
+            QueryMsg::OutgoingNftsByChannel {
+                channel_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_nfts_by_channel(
+                deps,
+                &OUTGOING_CLASS_TOKEN_TO_CHANNEL,
+                channel_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::IncomingNftsByChannel {
+                channel_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_nfts_by_channel(
+                deps,
+                &INCOMING_CLASS_TOKEN_TO_CHANNEL,
+                channel_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::OutgoingChannelsByClass {
+                class_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_channels_by_class(
+                deps,
+                &OUTGOING_CLASS_TOKEN_TO_CHANNEL,
+                class_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::IncomingChannelsByClass {
+                class_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_channels_by_class(
+                deps,
+                &INCOMING_CLASS_TOKEN_TO_CHANNEL,
+                class_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::AuditEscrow {
+                class_id,
+                start_after,
+                limit,
+            } => Ok(to_json_binary(&query_audit_escrow(
+                deps,
+                &env,
+                class_id,
+                start_after,
+                limit,
+            )?)?),
+            QueryMsg::SimulateReceive {
+                packet_data,
+                src,
+                dest,
+            } => Ok(to_json_binary(&simulate_receive(
+                deps,
+                &env,
+                packet_data,
+                src,
+                dest,
+            )?)?),
+            QueryMsg::PreviewSend {
+                collection,
+                token_ids,
+                receiver,
+                channel_id,
+                timeout,
+                relative_timeout_seconds,
+                memo,
+            } => Ok(to_json_binary(&self.preview_send(
+                deps,
+                &env,
+                collection,
+                token_ids,
+                IbcOutgoingMsg {
+                    receiver,
+                    channel_id,
+                    timeout,
+                    relative_timeout_seconds,
+                    memo,
+                },
+            )?)?),
+            QueryMsg::ClassStats { class_id } => Ok(to_json_binary(
+                &CLASS_STATS
+                    .may_load(deps.storage, ClassId::new(class_id))?
+                    .unwrap_or_default(),
+            )?),
+            QueryMsg::ChannelStats { channel_id } => Ok(to_json_binary(
+                &CHANNEL_STATS
+                    .may_load(deps.storage, &channel_id)?
+                    .unwrap_or_default(),
+            )?),
+            QueryMsg::GlobalStats {} => Ok(to_json_binary(
+                &GLOBAL_STATS.may_load(deps.storage)?.unwrap_or_default(),
+            )?),
+            QueryMsg::StatsInvariant { channel_id } => {
+                Ok(to_json_binary(&query_stats_invariant(deps, channel_id)?)?)
+            }
+        }
+    }
+
+    /// Class data of a local NFT contract, as sent by
+    /// `Ics721Execute::get_class_data`. Used for previewing packets,
+    /// since queries can't call `get_class_data`. By default, it is the
+    /// cw721 collection data.
+    fn get_preview_class_data(&self, deps: Deps, nft_contract: &Addr) -> StdResult<Option<Binary>> {
+        to_json_binary(&query_collection_data(deps, nft_contract)?).map(Some)
+    }
+
+    /// Previews the packets of sending NFTs, or the error sending would
+    /// fail with.
+    fn preview_send(
+        &self,
+        deps: Deps,
+        env: &Env,
+        collection: String,
+        token_ids: Vec<String>,
+        msg: IbcOutgoingMsg,
+    ) -> StdResult<PreviewSendResponse> {
+        Ok(
+            match self.preview_packets(deps, env, collection, token_ids, msg) {
+                Ok(packets) => PreviewSendResponse {
+                    packets,
+                    error: None,
+                },
+                Err(error) => PreviewSendResponse {
+                    packets: vec![],
+                    error: Some(error.to_string()),
+                },
+            },
+        )
+    }
+
+    /// Builds the packets the same way `receive_nft` does, with the
+    /// current NFT owner as sender. Checks are the ones of sending
+    /// directly from the collection, without outgoing proxy.
+    fn preview_packets(
+        &self,
+        deps: Deps,
+        env: &Env,
+        collection: String,
+        token_ids: Vec<String>,
+        msg: IbcOutgoingMsg,
+    ) -> Result<Vec<OutgoingPacketPreview>, ContractError> {
+        PO.error_if_paused(deps.storage)?;
+        // with an outgoing proxy, NFTs sent by the collection are rejected
+        if OUTGOING_PROXY.load(deps.storage)?.is_some() {
+            return Err(ContractError::Unauthorized {});
         }
+        let IbcOutgoingMsg {
+            receiver,
+            channel_id,
+            timeout,
+            relative_timeout_seconds,
+            memo,
+        } = msg;
+        validate_counterparty_receiver(deps.storage, &channel_id, &receiver)?;
+        resolve_outgoing_timeout(
+            deps.storage,
+            env,
+            &channel_id,
+            timeout,
+            relative_timeout_seconds,
+        )?;
+        let collection = deps.api.addr_validate(&collection)?;
+        let class = match load_class_id_for_nft_contract(deps.storage, &collection)? {
+            Some(class_id) => CLASS_ID_TO_CLASS.load(deps.storage, class_id)?,
+            // Local NFT that has never been sent out, same class as
+            // created by `receive_nft`.
+            None => Class {
+                id: ClassId::new(collection.to_string()),
+                uri: None,
+                data: self.get_preview_class_data(deps, &collection)?,
+            },
+        };
+        token_ids
+            .into_iter()
+            .map(|token_id| {
+                let token_id = TokenId::new(token_id);
+                let UniversalAllNftInfoResponse { access, info } = deps.querier.query_wasm_smart(
+                    &collection,
+                    &cw721::Cw721QueryMsg::AllNftInfo {
+                        token_id: token_id.clone().into(),
+                        include_expired: None,
+                    },
+                )?;
+                let token_data = get_token_data(deps, &class.id, &collection, &token_id)?;
+                let is_returning_voucher = INCOMING_CLASS_TOKEN_TO_CHANNEL
+                    .may_load(deps.storage, (class.id.clone(), token_id.clone()))?
+                    .map_or(false, |incoming_channel| incoming_channel == channel_id);
+                let packet = NonFungibleTokenPacketData {
+                    class_id: class.id.clone(),
+                    class_uri: class.uri.clone(),
+                    class_data: class.data.clone(),
+                    token_ids: vec![token_id],
+                    token_uris: info.token_uri.map(|uri| vec![uri]),
+                    token_data: token_data.map(|data| vec![data]),
+                    sender: access.owner,
+                    receiver: receiver.clone(),
+                    memo: memo.clone(),
+                };
+                packet.validate()?;
+                Ok(OutgoingPacketPreview {
+                    packet,
+                    is_returning_voucher,
+                })
+            })
+            .collect()
     }
 }
 
@@ -102,7 +381,7 @@ pub fn query_get_instantiate2_nft_contract(
     let cw721_code_id = if let Some(cw721_code_id) = cw721_code_id {
         cw721_code_id
     } else {
-        CW721_CODE_ID.load(deps.storage)?
+        resolve_voucher_cw721_code_id(deps.storage, &class_id)?.0
     };
 
     let mut hasher = Sha256::new();
@@ -114,6 +393,49 @@ pub fn query_get_instantiate2_nft_contract(
     Ok(nft_contract)
 }
 
+/// Returns the voucher collection rule for a class ID. An exact class
+/// ID rule takes precedence over the longest matching prefix rule.
+pub fn load_voucher_collection_rule(
+    storage: &dyn Storage,
+    class_id: &ClassId,
+) -> StdResult<Option<VoucherCollectionRule>> {
+    if let Some(rule) = VOUCHER_COLLECTION_RULES_BY_CLASS_ID.may_load(storage, class_id.clone())? {
+        return Ok(Some(rule));
+    }
+    let mut longest_match: Option<(String, VoucherCollectionRule)> = None;
+    for item in VOUCHER_COLLECTION_RULES_BY_PREFIX.range(storage, None, None, Order::Ascending) {
+        let (prefix, rule) = item?;
+        let is_longer = longest_match
+            .as_ref()
+            .map_or(true, |(longest, _)| prefix.len() > longest.len());
+        if class_id.starts_with(prefix.as_str()) && is_longer {
+            longest_match = Some((prefix, rule));
+        }
+    }
+    Ok(longest_match.map(|(_, rule)| rule))
+}
+
+/// Returns the code ID for instantiating the voucher collection of a
+/// class ID: the code ID of its voucher collection rule, else the
+/// CW2981 code ID (if configured), else `CW721_CODE_ID`. Returns the
+/// code ID and whether it is CW2981. The decision is per class, so it
+/// doesn't depend on whether the first transferred NFTs have royalties.
+pub fn resolve_voucher_cw721_code_id(
+    storage: &dyn Storage,
+    class_id: &ClassId,
+) -> StdResult<(u64, bool)> {
+    let cw2981_code_id = CW2981_CONFIG
+        .may_load(storage)?
+        .map(|Cw2981Config { code_id, .. }| code_id);
+    if let Some(rule) = load_voucher_collection_rule(storage, class_id)? {
+        return Ok((rule.code_id, cw2981_code_id == Some(rule.code_id)));
+    }
+    match cw2981_code_id {
+        Some(code_id) => Ok((code_id, true)),
+        None => Ok((CW721_CODE_ID.load(storage)?, false)),
+    }
+}
+
 pub fn load_nft_contract_for_class_id(storage: &dyn Storage, class_id: String) -> StdResult<Addr> {
     query_nft_contract_for_class_id(storage, class_id.clone().into())?.map_or_else(
         || {
@@ -197,23 +519,429 @@ pub fn query_nft_contracts(
     }
 }
 
+pub fn load_pending_claim(
+    storage: &dyn Storage,
+    key: (ClassId, TokenId),
+) -> Result<PendingClaim, ContractError> {
+    PENDING_CLAIMS
+        .may_load(storage, key.clone())?
+        .ok_or_else(|| ContractError::NoPendingClaim {
+            class_id: key.0.to_string(),
+            token_id: key.1.into(),
+        })
+}
+
+/// Returns the claimable receipt config applying to a local channel.
+pub fn query_claimable_receipt_config(
+    storage: &dyn Storage,
+    channel_id: &str,
+) -> StdResult<Option<ClaimableReceiptConfig>> {
+    match CLAIMABLE_RECEIPT_CONFIG_BY_CHANNEL.may_load(storage, channel_id)? {
+        Some(config) => Ok(Some(config)),
+        None => CLAIMABLE_RECEIPT_CONFIG.may_load(storage),
+    }
+}
+
+/// Returns the claimable receipt config, in case claimable receipt mode
+/// is enabled for a local channel.
+pub fn load_claimable_receipt_config(
+    storage: &dyn Storage,
+    channel_id: &str,
+) -> StdResult<Option<ClaimableReceiptConfig>> {
+    Ok(query_claimable_receipt_config(storage, channel_id)?.filter(|config| config.enabled))
+}
+
+const DEFAULT_PENDING_CLAIMS_LIMIT: u32 = 30;
+const MAX_PENDING_CLAIMS_LIMIT: u32 = 100;
+
+pub fn query_pending_claims(
+    deps: Deps,
+    receiver: Option<String>,
+    start_after: Option<ClassToken>,
+    limit: Option<u32>,
+) -> StdResult<Vec<((ClassId, TokenId), PendingClaim)>> {
+    let receiver = receiver
+        .map(|receiver| deps.api.addr_validate(&receiver))
+        .transpose()?;
+    let limit = limit
+        .unwrap_or(DEFAULT_PENDING_CLAIMS_LIMIT)
+        .min(MAX_PENDING_CLAIMS_LIMIT) as usize;
+    let start = start_after.map(|class_token| {
+        Bound::exclusive((
+            ClassId::new(class_token.class_id),
+            TokenId::new(class_token.token_id),
+        ))
+    });
+    PENDING_CLAIMS
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match (item, &receiver) {
+            (Ok((_, claim)), Some(receiver)) => &claim.receiver == receiver,
+            _ => true,
+        })
+        .take(limit)
+        .collect()
+}
+
+pub fn load_parked_transfer(
+    storage: &dyn Storage,
+    channel_id: &str,
+    sequence: u64,
+) -> Result<ParkedTransfer, ContractError> {
+    PARKED_TRANSFERS
+        .may_load(storage, (channel_id, sequence))?
+        .ok_or_else(|| ContractError::NoParkedTransfer {
+            channel_id: channel_id.to_string(),
+            sequence,
+        })
+}
+
+const DEFAULT_PARKED_TRANSFERS_LIMIT: u32 = 30;
+const MAX_PARKED_TRANSFERS_LIMIT: u32 = 100;
+
+pub fn query_parked_transfers(
+    deps: Deps,
+    start_after: Option<(String, u64)>,
+    limit: Option<u32>,
+) -> StdResult<Vec<((String, u64), ParkedTransfer)>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PARKED_TRANSFERS_LIMIT)
+        .min(MAX_PARKED_TRANSFERS_LIMIT) as usize;
+    let start = start_after
+        .as_ref()
+        .map(|(channel_id, sequence)| Bound::exclusive((channel_id.as_str(), *sequence)));
+    PARKED_TRANSFERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+const DEFAULT_HOOK_SUBSCRIBERS_LIMIT: u32 = 30;
+const MAX_HOOK_SUBSCRIBERS_LIMIT: u32 = 100;
+
+pub fn query_hook_subscribers(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, HookSubscription)>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HOOK_SUBSCRIBERS_LIMIT)
+        .min(MAX_HOOK_SUBSCRIBERS_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into_bytes()));
+    HOOK_SUBSCRIBERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
 fn query_channels(
     deps: Deps,
-    class_token_to_channel: &Map<(ClassId, TokenId), String>,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ChannelIndexes>,
     start_after: Option<ClassToken>,
     limit: Option<u32>,
 ) -> StdResult<Vec<((String, String), String)>> {
-    let start_after = start_after.map(|class_token| {
-        (
+    let start = start_after.map(|class_token| {
+        Bound::exclusive((
             ClassId::new(class_token.class_id),
             TokenId::new(class_token.token_id),
-        )
+        ))
     });
-    cw_paginate_storage::paginate_map(
-        deps,
-        class_token_to_channel,
-        start_after,
-        limit,
-        Order::Ascending,
-    )
+    let all = class_token_to_channel.range(deps.storage, start, None, Order::Ascending);
+    match limit {
+        Some(limit) => all.take(limit as usize).collect(),
+        None => all.collect(),
+    }
+}
+
+/// Counts outgoing and incoming channel entries of a channel, or all of
+/// them in case of `None`, and compares them to the stats.
+fn query_stats_invariant(
+    deps: Deps,
+    channel_id: Option<String>,
+) -> StdResult<StatsInvariantResponse> {
+    let count_entries =
+        |class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ChannelIndexes>| {
+            match &channel_id {
+                Some(channel_id) => class_token_to_channel
+                    .idx
+                    .channel
+                    .prefix(channel_id.clone())
+                    .keys_raw(deps.storage, None, None, Order::Ascending)
+                    .count(),
+                None => class_token_to_channel
+                    .keys_raw(deps.storage, None, None, Order::Ascending)
+                    .count(),
+            }
+        };
+    let outgoing_entries = count_entries(&OUTGOING_CLASS_TOKEN_TO_CHANNEL) as u64;
+    let incoming_entries = count_entries(&INCOMING_CLASS_TOKEN_TO_CHANNEL) as u64;
+    let stats = match &channel_id {
+        Some(channel_id) => CHANNEL_STATS.may_load(deps.storage, channel_id)?,
+        None => GLOBAL_STATS.may_load(deps.storage)?,
+    }
+    .unwrap_or_default();
+    let holds = outgoing_entries == stats.escrowed
+        && stats.vouchers_minted.checked_sub(stats.vouchers_burned) == Some(incoming_entries);
+    Ok(StatsInvariantResponse {
+        stats,
+        outgoing_entries,
+        incoming_entries,
+        holds,
+    })
+}
+
+const DEFAULT_CHANNEL_NFTS_LIMIT: u32 = 30;
+const MAX_CHANNEL_NFTS_LIMIT: u32 = 100;
+
+/// Lists NFTs by channel using the channel index, so NFTs on other
+/// channels aren't scanned.
+fn query_nfts_by_channel(
+    deps: Deps,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ChannelIndexes>,
+    channel_id: String,
+    start_after: Option<ClassToken>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ClassToken>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_CHANNEL_NFTS_LIMIT)
+        .min(MAX_CHANNEL_NFTS_LIMIT) as usize;
+    let start = start_after.map(|class_token| {
+        Bound::exclusive((
+            ClassId::new(class_token.class_id),
+            TokenId::new(class_token.token_id),
+        ))
+    });
+    class_token_to_channel
+        .idx
+        .channel
+        .prefix(channel_id)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| {
+            key.map(|(class_id, token_id)| ClassToken {
+                class_id: ClassId::new(class_id),
+                token_id: TokenId::new(token_id),
+            })
+        })
+        .collect()
+}
+
+/// Lists NFTs of a class and their channels, so NFTs of other classes
+/// aren't scanned.
+fn query_channels_by_class(
+    deps: Deps,
+    class_token_to_channel: &IndexedMap<(ClassId, TokenId), String, ChannelIndexes>,
+    class_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, String)>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_CHANNEL_NFTS_LIMIT)
+        .min(MAX_CHANNEL_NFTS_LIMIT) as usize;
+    let start = start_after.map(|token_id| Bound::exclusive(TokenId::new(token_id)));
+    class_token_to_channel
+        .prefix(ClassId::new(class_id))
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+const DEFAULT_AUDIT_LIMIT: u32 = 30;
+const MAX_AUDIT_LIMIT: u32 = 100;
+
+pub fn query_audit_escrow(
+    deps: Deps,
+    env: &Env,
+    class_id: Option<String>,
+    start_after: Option<ClassToken>,
+    limit: Option<u32>,
+) -> StdResult<AuditEscrowResponse> {
+    let limit = limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT) as usize;
+    let classes: Box<dyn Iterator<Item = StdResult<ClassIdInfo>> + '_> = match class_id {
+        Some(class_id) => Box::new(
+            CLASS_ID_AND_NFT_CONTRACT_INFO
+                .may_load(deps.storage, &class_id)
+                .transpose()
+                .into_iter(),
+        ),
+        None => {
+            // start_after's class may still have tokens left, so it is included
+            let start = start_after
+                .as_ref()
+                .map(|s| Bound::InclusiveRaw(s.class_id.to_string().into()));
+            Box::new(
+                CLASS_ID_AND_NFT_CONTRACT_INFO
+                    .range(deps.storage, start, None, Order::Ascending)
+                    .map(|item| item.map(|(_, v)| v)),
+            )
+        }
+    };
+
+    let mut findings = vec![];
+    let mut inspected = 0;
+    let mut last_inspected = None;
+    for class_id_info in classes {
+        let class_id_info = class_id_info?;
+        let start_after_token = start_after
+            .as_ref()
+            .filter(|s| s.class_id == class_id_info.class_id)
+            .map(|s| s.token_id.clone());
+        let token_ids = audit_candidates(
+            deps,
+            env,
+            &class_id_info,
+            start_after_token,
+            limit - inspected,
+        )?;
+        for token_id in token_ids {
+            findings.extend(audit_token(deps, env, &class_id_info, token_id.clone())?);
+            inspected += 1;
+            last_inspected = Some(ClassToken {
+                class_id: class_id_info.class_id.clone(),
+                token_id,
+            });
+        }
+        if inspected >= limit {
+            return Ok(AuditEscrowResponse {
+                findings,
+                next_start_after: last_inspected,
+            });
+        }
+    }
+    Ok(AuditEscrowResponse {
+        findings,
+        next_start_after: None,
+    })
+}
+
+/// Returns the first `limit` token IDs after `start_after` that
+/// either have an outgoing entry, an incoming entry, token metadata,
+/// or are owned by ICS721.
+fn audit_candidates(
+    deps: Deps,
+    env: &Env,
+    class_id_info: &ClassIdInfo,
+    start_after: Option<TokenId>,
+    limit: usize,
+) -> StdResult<Vec<TokenId>> {
+    let class_id = class_id_info.class_id.clone();
+    let min = || start_after.clone().map(Bound::exclusive);
+    let mut token_ids = BTreeSet::new();
+    for token_id in OUTGOING_CLASS_TOKEN_TO_CHANNEL
+        .prefix(class_id.clone())
+        .keys(deps.storage, min(), None, Order::Ascending)
+        .take(limit)
+        .chain(
+            INCOMING_CLASS_TOKEN_TO_CHANNEL
+                .prefix(class_id.clone())
+                .keys(deps.storage, min(), None, Order::Ascending)
+                .take(limit),
+        )
+        .chain(
+            TOKEN_METADATA
+                .prefix(class_id)
+                .keys(deps.storage, min(), None, Order::Ascending)
+                .take(limit),
+        )
+    {
+        token_ids.insert(token_id?);
+    }
+
+    // NFTs owned by ICS721, paging through the collection until
+    // `limit` tokens are found or there are no more
+    let mut start_after = start_after.map(String::from);
+    let mut escrowed = 0;
+    while escrowed < limit {
+        let tokens: Option<cw721::TokensResponse> = deps
+            .querier
+            .query_wasm_smart(
+                &class_id_info.address,
+                &cw721::Cw721QueryMsg::Tokens {
+                    owner: env.contract.address.to_string(),
+                    start_after: start_after.clone(),
+                    limit: Some((limit - escrowed) as u32),
+                },
+            )
+            .ok();
+        match tokens {
+            Some(cw721::TokensResponse { tokens }) if !tokens.is_empty() => {
+                escrowed += tokens.len();
+                start_after = tokens.last().cloned();
+                token_ids.extend(tokens);
+            }
+            _ => break,
+        }
+    }
+
+    Ok(token_ids
+        .into_iter()
+        .take(limit)
+        .map(TokenId::new)
+        .collect())
+}
+
+fn audit_token(
+    deps: Deps,
+    env: &Env,
+    class_id_info: &ClassIdInfo,
+    token_id: TokenId,
+) -> StdResult<Vec<AuditFinding>> {
+    let key = (class_id_info.class_id.clone(), token_id.clone());
+    let outgoing_channel = OUTGOING_CLASS_TOKEN_TO_CHANNEL.may_load(deps.storage, key.clone())?;
+    let incoming_channel = INCOMING_CLASS_TOKEN_TO_CHANNEL.may_load(deps.storage, key.clone())?;
+    let has_metadata = TOKEN_METADATA.has(deps.storage, key.clone());
+    let is_pending_claim = PENDING_CLAIMS.has(deps.storage, key.clone());
+    let is_parked = PARKED_TOKENS.has(deps.storage, key);
+    let owner = deps
+        .querier
+        .query_wasm_smart::<UniversalOwnerOfResponse>(
+            &class_id_info.address,
+            &cw721::Cw721QueryMsg::OwnerOf {
+                token_id: token_id.clone().into(),
+                include_expired: None,
+            },
+        )
+        .ok()
+        .map(|res| res.owner);
+    let escrowed = owner.as_deref() == Some(env.contract.address.as_str());
+
+    let mut issues = vec![];
+    match outgoing_channel {
+        Some(_) if !escrowed => issues.push((
+            AuditIssue::OutgoingNotEscrowed {
+                owner: owner.clone(),
+            },
+            "admin_clean_and_unescrow_nft",
+        )),
+        // vouchers pending in claimable receipt mode and parked transfers are escrowed
+        // without outgoing entry
+        None if escrowed && !is_pending_claim && !is_parked => issues.push((
+            AuditIssue::EscrowedWithoutOutgoing {},
+            "admin_clean_and_unescrow_nft",
+        )),
+        _ => {}
+    }
+    match incoming_channel {
+        Some(channel) if owner.is_none() => issues.push((
+            AuditIssue::IncomingWithoutVoucher { channel },
+            "admin_clean_and_burn_nft",
+        )),
+        None if has_metadata => issues.push((
+            AuditIssue::MetadataWithoutIncoming {},
+            "admin_clean_and_burn_nft",
+        )),
+        _ => {}
+    }
+
+    Ok(issues
+        .into_iter()
+        .map(|(issue, fix)| AuditFinding {
+            class_id: class_id_info.class_id.clone(),
+            token_id: token_id.clone(),
+            nft_contract: class_id_info.address.clone(),
+            issue,
+            fix: fix.to_string(),
+        })
+        .collect())
 }
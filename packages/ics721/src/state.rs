@@ -1,10 +1,15 @@
 use cosmwasm_schema::{cw_serde, schemars::JsonSchema};
-use cosmwasm_std::{Addr, Binary, ContractInfoResponse, Empty};
+use cosmwasm_std::{
+    Addr, Binary, ContractInfoResponse, Decimal, Empty, IbcPacket, IbcTimeout, Timestamp, Uint128,
+};
 use cw_pause_once::PauseOrchestrator;
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, UniqueIndex};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, UniqueIndex};
 use serde::{Deserialize, Serialize};
 
-use ics721_types::token_types::{Class, ClassId, TokenId};
+use ics721_types::{
+    ibc_types::NonFungibleTokenPacketData,
+    token_types::{Class, ClassId, TokenId},
+};
 
 /// The code ID we will use for instantiating new cw721s.
 pub const CW721_CODE_ID: Item<u64> = Item::new("a");
@@ -34,10 +39,23 @@ pub const CLASS_ID_AND_NFT_CONTRACT_INFO: IndexedMap<&str, ClassIdInfo, ClassIdI
 pub const CLASS_ID_TO_CLASS: Map<ClassId, Class> = Map::new("g");
 
 /// Maps (class ID, token ID) -> local channel ID. Used to determine
-/// the local channel that NFTs have been sent out on.
-pub const OUTGOING_CLASS_TOKEN_TO_CHANNEL: Map<(ClassId, TokenId), String> = Map::new("h");
+/// the local channel that NFTs have been sent out on. Indexed by
+/// channel for listing all NFTs sent out on a channel.
+pub const OUTGOING_CLASS_TOKEN_TO_CHANNEL: IndexedMap<(ClassId, TokenId), String, ChannelIndexes> =
+    IndexedMap::new(
+        "h",
+        ChannelIndexes {
+            channel: MultiIndex::new(|_pk, channel| channel.clone(), "h", "outgoing__channel"),
+        },
+    );
 /// Same as above, but for NFTs arriving at this contract.
-pub const INCOMING_CLASS_TOKEN_TO_CHANNEL: Map<(ClassId, TokenId), String> = Map::new("i");
+pub const INCOMING_CLASS_TOKEN_TO_CHANNEL: IndexedMap<(ClassId, TokenId), String, ChannelIndexes> =
+    IndexedMap::new(
+        "i",
+        ChannelIndexes {
+            channel: MultiIndex::new(|_pk, channel| channel.clone(), "i", "incoming__channel"),
+        },
+    );
 
 /// Maps (class ID, token ID) -> token metadata. Used to store
 /// on-chain metadata for tokens that have arrived from other
@@ -54,6 +72,99 @@ pub const ADMIN_USED_FOR_CW721: Item<Option<Addr>> = Item::new("l");
 /// Bug: https://github.com/CosmWasm/cosmwasm/issues/2155
 pub const CONTRACT_ADDR_LENGTH: Item<u32> = Item::new("n");
 
+/// Voucher collections being migrated by `MigrateVoucherCollections`,
+/// in the order of their submessages. Each reply pops the first one.
+pub const MIGRATING_VOUCHER_COLLECTIONS: Item<Vec<ClassIdInfo>> = Item::new("o");
+
+/// Voucher collection rules for an exact class ID. Takes precedence
+/// over prefix rules.
+pub const VOUCHER_COLLECTION_RULES_BY_CLASS_ID: Map<ClassId, VoucherCollectionRule> = Map::new("p");
+/// Voucher collection rules for a class ID prefix (e.g.
+/// `wasm.xyz/channel-5/`). In case several prefixes match, the
+/// longest one is used.
+pub const VOUCHER_COLLECTION_RULES_BY_PREFIX: Map<&str, VoucherCollectionRule> = Map::new("q");
+
+/// Config for instantiating new voucher collections as CW2981
+/// collections. In case of None, royalties are not minted on vouchers.
+pub const CW2981_CONFIG: Item<Cw2981Config> = Item::new("r");
+/// Voucher collections instantiated with the CW2981 code ID. Vouchers
+/// of these collections are minted with royalty metadata.
+pub const CW2981_VOUCHERS: Map<ClassId, Empty> = Map::new("s");
+
+/// Maps class ID -> controller of its voucher collection. Controllers
+/// are attested by the home chain of the collection via a
+/// `CollectionOwnership` control packet.
+pub const VOUCHER_COLLECTION_CONTROLLERS: Map<ClassId, CollectionController> = Map::new("t");
+
+/// Global config for claimable receipt mode. In case of None, incoming
+/// vouchers are minted to the receiver directly.
+pub const CLAIMABLE_RECEIPT_CONFIG: Item<ClaimableReceiptConfig> = Item::new("u");
+/// Maps local channel ID -> config for claimable receipt mode. Takes
+/// precedence over the global config.
+pub const CLAIMABLE_RECEIPT_CONFIG_BY_CHANNEL: Map<&str, ClaimableReceiptConfig> = Map::new("v");
+/// Maps (class ID, token ID) -> pending claim. In claimable receipt
+/// mode, incoming vouchers are held by ICS721 until the receiver claims
+/// or rejects them.
+pub const PENDING_CLAIMS: Map<(ClassId, TokenId), PendingClaim> = Map::new("w");
+
+/// Whether undeliverable incoming transfers are parked in ICS721
+/// custody, instead of acking an error.
+pub const PARK_ON_FAILURE: Item<bool> = Item::new("x");
+/// Maps (local channel ID, packet sequence) -> incoming transfer parked
+/// in ICS721 custody.
+pub const PARKED_TRANSFERS: Map<(&str, u64), ParkedTransfer> = Map::new("y");
+/// The packet being received in park on failure mode. Kept until its
+/// submessage is done, so it can be parked in case of failure.
+pub const RECEIVING_PACKET: Item<IbcPacket> = Item::new("z");
+
+/// Local channel IDs, receivers are translated for, in case they use
+/// the bech32 prefix of the source chain.
+pub const RECEIVER_TRANSLATION_CHANNELS: Map<&str, Empty> = Map::new("aa");
+
+/// Maps local channel ID -> address policy of the counterparty chain.
+/// Outgoing receivers are checked against it, before escrowing the NFT.
+pub const COUNTERPARTY_ADDRESS_POLICIES: Map<&str, CounterpartyAddressPolicy> = Map::new("ab");
+
+/// Maps local channel ID -> timeout config for outgoing transfers.
+pub const CHANNEL_TIMEOUT_CONFIGS: Map<&str, ChannelTimeoutConfig> = Map::new("ac");
+
+/// Maps hook subscriber contract -> its subscription. Subscribers are
+/// notified on transfer lifecycle events.
+pub const HOOK_SUBSCRIBERS: Map<&Addr, HookSubscription> = Map::new("ad");
+
+/// Maps local class ID -> transfer stats of the class.
+pub const CLASS_STATS: Map<ClassId, TransferStats> = Map::new("ae");
+
+/// Maps local channel ID -> transfer stats of the channel.
+pub const CHANNEL_STATS: Map<&str, TransferStats> = Map::new("af");
+
+/// Transfer stats across all classes and channels.
+pub const GLOBAL_STATS: Item<TransferStats> = Item::new("ag");
+
+/// Voucher classes whose class metadata has been set by the controller
+/// of their voucher collection. Incoming transfers don't overwrite the
+/// class of these.
+pub const VOUCHER_CLASS_OVERRIDES: Map<ClassId, Empty> = Map::new("ah");
+/// Maps (class ID, token ID) -> (local channel ID, packet sequence) of
+/// the parked transfer holding the token.
+pub const PARKED_TOKENS: Map<(ClassId, TokenId), (String, u64)> = Map::new("ai");
+/// Maps (class ID, token ID) -> key and parked transfer of a token being
+/// returned to the source chain. In case the return fails, the token is
+/// parked again.
+pub const RETURNING_PARKED_TOKENS: Map<(ClassId, TokenId), ((String, u64), ParkedTransfer)> =
+    Map::new("aj");
+/// Progress of building the channel indexes of channel entries saved
+/// before they were indexed, and of seeding transfer stats from them.
+/// Removed once all entries are done.
+pub const CHANNEL_INDEX_CURSOR: Item<ChannelIndexCursor> = Item::new("ak");
+/// Maps (class ID, token ID) -> pending claim of a voucher being returned
+/// to the source chain. In case the return fails, the claim is pending
+/// again.
+pub const RETURNING_PENDING_CLAIMS: Map<(ClassId, TokenId), PendingClaim> = Map::new("al");
+/// Error of the transfer being parked. Kept until parking is done, so
+/// it is acked along with the parking error in case parking fails.
+pub const PARKING_ERROR: Item<String> = Item::new("am");
+
 #[derive(Deserialize)]
 pub struct UniversalAllNftInfoResponse {
     pub access: UniversalOwnerOfResponse,
@@ -93,6 +204,201 @@ pub struct CollectionData {
     pub num_tokens: Option<u64>,
 }
 
+/// Royalty of an NFT, based on CW2981 `RoyaltyInfo`. The share is the
+/// part of the sale price paid to the payment address.
+// NB: like `CollectionData`, parsing of incoming data is lenient.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[schemars(crate = "cosmwasm_schema::schemars")]
+#[serde(crate = "cosmwasm_schema::serde")]
+pub struct RoyaltyData {
+    /// Payment address on source chain.
+    pub payment_address: String,
+    pub share: Decimal,
+}
+
+/// Token data send by ICS721 for NFTs of collections supporting CW2981.
+/// It is passed as optional token data during interchain transfer to target chain.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[schemars(crate = "cosmwasm_schema::schemars")]
+#[serde(crate = "cosmwasm_schema::serde")]
+pub struct TokenData {
+    pub royalty_info: Option<RoyaltyData>,
+}
+
+/// Defines who receives royalties of CW2981 vouchers on this chain.
+#[cw_serde]
+pub enum RoyaltyPaymentPolicy {
+    /// Royalties are paid to a local address (e.g. an escrow contract).
+    Fixed { address: String },
+    /// Payment address on source chain is converted to this chain. Since
+    /// this is only safe for wallets, royalties of contracts and multisigs
+    /// are paid to the fallback address.
+    ConvertWalletAddress { fallback: String },
+}
+
+#[cw_serde]
+pub struct Cw2981Config {
+    /// Code ID of a CW2981 (cw2981-royalties) contract.
+    pub code_id: u64,
+    pub payment_policy: RoyaltyPaymentPolicy,
+}
+
+#[cw_serde]
+pub struct CollectionController {
+    /// The address on this chain that may update the voucher collection.
+    pub controller: Addr,
+    /// Owner of the collection on its home chain.
+    pub source_owner: String,
+    /// The local channel the ownership has been received on.
+    pub channel_id: String,
+}
+
+#[cw_serde]
+pub struct ClaimableReceiptConfig {
+    /// Whether incoming vouchers are held by ICS721 until claimed. A
+    /// channel may opt out while the global config is enabled.
+    pub enabled: bool,
+    /// Seconds after which anyone may return unclaimed vouchers.
+    pub expiry: u64,
+}
+
+#[cw_serde]
+pub struct PendingClaim {
+    /// The address that may claim or reject the voucher.
+    pub receiver: Addr,
+    /// The sender on the source chain, the NFT is returned to on
+    /// rejection or expiry.
+    pub sender: String,
+    /// The local channel the voucher has been received on.
+    pub channel_id: String,
+    pub expires_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct CounterpartyAddressPolicy {
+    /// The bech32 prefix of the counterparty chain (e.g. `juno`).
+    pub prefix: String,
+    /// Allowed address lengths in bytes (e.g. 20 for wallets and 32 for
+    /// contracts). In case of empty, any length is allowed.
+    pub address_lengths: Vec<u32>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct HookSubscription {
+    /// Local class IDs the subscriber is notified about. In case of
+    /// empty, any class is notified.
+    pub class_ids: Vec<ClassId>,
+    /// Local channels the subscriber is notified about. In case of
+    /// empty, any channel is notified.
+    pub channel_ids: Vec<String>,
+}
+
+/// Transfer counters. `failed` and `timed_out` count packets, all other
+/// counters count NFTs.
+#[cw_serde]
+#[derive(Default)]
+pub struct TransferStats {
+    /// NFTs held in escrow by ICS721 for outgoing transfers, including
+    /// those in flight.
+    pub escrowed: u64,
+    /// NFTs transferred out, waiting for an ack or timeout.
+    pub in_flight: u64,
+    pub vouchers_minted: u64,
+    pub vouchers_burned: u64,
+    /// Outgoing packets acked with an error.
+    pub failed: u64,
+    /// Outgoing packets timed out.
+    pub timed_out: u64,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ChannelTimeoutConfig {
+    /// Seconds from now, in case an outgoing msg has no timeout.
+    pub default_seconds: Option<u64>,
+    /// Max seconds from now an outgoing timeout may be. Block heights
+    /// of the counterparty chain are unknown, so in case of a max, the
+    /// timeout must be a timestamp.
+    pub max_seconds: Option<u64>,
+}
+
+#[cw_serde]
+pub struct ChannelIndexCursor {
+    /// Whether outgoing entries are indexed and incoming entries are
+    /// being indexed.
+    pub incoming: bool,
+    /// (class ID, token ID) of the last indexed entry.
+    pub start_after: Option<(String, String)>,
+    /// Whether transfer stats are seeded from the entries.
+    pub seed_stats: bool,
+}
+
+#[cw_serde]
+pub struct ParkedTransfer {
+    /// The original packet data.
+    pub data: NonFungibleTokenPacketData,
+    /// The local class ID the NFTs are held under.
+    pub class_id: ClassId,
+    /// Why the transfer couldn't be delivered.
+    pub reason: String,
+}
+
+#[cw_serde]
+pub enum ParkedTransferAction {
+    /// Transfers the NFTs to a corrected receiver on this chain.
+    Deliver { receiver: String },
+    /// Sends the NFTs back to the sender on the source chain. In case
+    /// the return fails, the NFTs are parked again.
+    Return { timeout: IbcTimeout },
+}
+
+/// Based on `cw721::msg::Cw721ExecuteMsg::UpdateNftInfo v0.19`. Voucher
+/// collections must support it for receiving token URI updates, see
+/// `Ics721Execute::update_token_uri_msg`.
+#[cw_serde]
+pub enum Cw721UpdateNftInfoMsg {
+    UpdateNftInfo {
+        token_id: String,
+        token_uri: Option<String>,
+        extension: Option<Empty>,
+    },
+}
+
+/// Based on `cw2981_royalties::msg::Cw2981QueryMsg v0.18`
+#[cw_serde]
+pub enum Cw2981QueryMsg {
+    RoyaltyInfo {
+        token_id: String,
+        sale_price: Uint128,
+    },
+    CheckRoyalties {},
+}
+
+/// Based on `cw2981_royalties::msg::RoyaltiesInfoResponse v0.18`
+#[derive(Deserialize)]
+pub struct UniversalRoyaltiesInfoResponse {
+    pub address: String,
+    pub royalty_amount: Uint128,
+}
+
+/// Based on `cw2981_royalties::msg::CheckRoyaltiesResponse v0.18`
+#[derive(Deserialize)]
+pub struct UniversalCheckRoyaltiesResponse {
+    pub royalty_payments: bool,
+}
+
+/// Royalty part of `cw2981_royalties::Metadata v0.18`, used as extension
+/// for minting vouchers on CW2981 collections.
+#[cw_serde]
+#[derive(Default)]
+pub struct Cw2981Metadata {
+    pub royalty_percentage: Option<u64>,
+    pub royalty_payment_address: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct UniversalOwnerOfResponse {
     pub owner: String,
@@ -111,6 +417,21 @@ pub struct ClassIdInfo {
     pub address: Addr,
 }
 
+/// Overrides code ID, label, and admin for instantiating new voucher
+/// collections, instead of using `CW721_CODE_ID` and `ADMIN_USED_FOR_CW721`.
+#[cw_serde]
+pub struct VoucherCollectionRule {
+    /// Code ID used for instantiating the voucher collection.
+    pub code_id: u64,
+    /// Label template, where `{class_id}` is replaced by the class ID.
+    /// In case of None, the default label is used.
+    pub label: Option<String>,
+    /// Admin of the voucher collection. In case of None,
+    /// `ADMIN_USED_FOR_CW721` is used. An empty string makes the
+    /// collection immutable.
+    pub admin: Option<String>,
+}
+
 pub struct ClassIdInfoIndexes<'a> {
     pub class_id: UniqueIndex<'a, ClassId, ClassIdInfo>,
     pub address: UniqueIndex<'a, Addr, ClassIdInfo>,
@@ -123,6 +444,17 @@ impl<'a> IndexList<ClassIdInfo> for ClassIdInfoIndexes<'a> {
     }
 }
 
+pub struct ChannelIndexes<'a> {
+    pub channel: MultiIndex<'a, String, String, (ClassId, TokenId)>,
+}
+
+impl<'a> IndexList<String> for ChannelIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<String>> + '_> {
+        let v: Vec<&dyn Index<String>> = vec![&self.channel];
+        Box::new(v.into_iter())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::{from_json, to_json_binary, Coin, Empty};
@@ -1,9 +1,9 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    from_json,
+    attr, from_json,
     testing::{mock_dependencies, mock_env, mock_info, MockQuerier, MOCK_CONTRACT_ADDR},
-    to_json_binary, Addr, ContractResult, CosmosMsg, DepsMut, Empty, IbcMsg, IbcTimeout, Order,
-    QuerierResult, Response, StdResult, SubMsg, Timestamp, WasmQuery,
+    to_json_binary, Addr, Binary, ContractResult, CosmosMsg, Decimal, Deps, DepsMut, Empty, IbcMsg,
+    IbcTimeout, Order, QuerierResult, Response, StdResult, SubMsg, Timestamp, Uint128, WasmQuery,
 };
 use cw721::{AllNftInfoResponse, NftInfoResponse, NumTokensResponse};
 use cw721_base::QueryMsg;
@@ -14,20 +14,22 @@ use cw_storage_plus::Map;
 use crate::{
     execute::Ics721Execute,
     ibc::{Ics721Ibc, INSTANTIATE_INCOMING_PROXY_REPLY_ID, INSTANTIATE_OUTGOING_PROXY_REPLY_ID},
-    msg::{InstantiateMsg, MigrateMsg},
+    msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg as Ics721QueryMsg},
     query::{
         query_class_id_for_nft_contract, query_nft_contract_for_class_id, query_nft_contracts,
         Ics721Query,
     },
     state::{
-        CollectionData, ADMIN_USED_FOR_CW721, CLASS_ID_TO_CLASS, CONTRACT_ADDR_LENGTH,
-        CW721_CODE_ID, INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PO,
+        CollectionData, Cw2981QueryMsg, RoyaltyData, TokenData, TransferStats,
+        ADMIN_USED_FOR_CW721, CLASS_ID_TO_CLASS, CONTRACT_ADDR_LENGTH, CW721_CODE_ID,
+        INCOMING_PROXY, OUTGOING_CLASS_TOKEN_TO_CHANNEL, OUTGOING_PROXY, PO,
     },
     utils::get_collection_data,
+    ContractError,
 };
 use ics721_types::{
     ibc_types::{IbcOutgoingMsg, NonFungibleTokenPacketData},
-    token_types::{ClassId, TokenId},
+    token_types::{ClassId, ClassToken, TokenId},
 };
 
 const NFT_CONTRACT_1: &str = "nft1";
@@ -37,6 +39,7 @@ const CLASS_ID_2: &str = "some/class/id2";
 const OWNER_ADDR: &str = "owner";
 const ADMIN_ADDR: &str = "admin";
 const PAUSER_ADDR: &str = "pauser";
+const ROYALTY_ADDR: &str = "royalty";
 
 #[derive(Default)]
 pub struct Ics721Contract {}
@@ -64,7 +67,15 @@ impl Ics721Execute<Empty> for Ics721ContractNoClassData {
     }
 }
 impl Ics721Ibc<Empty> for Ics721ContractNoClassData {}
-impl Ics721Query for Ics721ContractNoClassData {}
+impl Ics721Query for Ics721ContractNoClassData {
+    fn get_preview_class_data(
+        &self,
+        _deps: Deps,
+        _nft_contract: &Addr,
+    ) -> StdResult<Option<Binary>> {
+        Ok(None)
+    }
+}
 
 // copy of cosmwasm_std::ContractInfoResponse (marked as non-exhaustive)
 #[cw_serde]
@@ -85,7 +96,7 @@ fn mock_querier(query: &WasmQuery) -> QuerierResult {
         cosmwasm_std::WasmQuery::Smart {
             contract_addr: _,
             msg,
-        } => match from_json::<cw721_base::msg::QueryMsg<Empty>>(&msg).unwrap() {
+        } => match from_json::<cw721_base::msg::QueryMsg<Cw2981QueryMsg>>(&msg).unwrap() {
             QueryMsg::Ownership {} => QuerierResult::Ok(ContractResult::Ok(
                 to_json_binary(&Ownership::<Addr> {
                     owner: Some(Addr::unchecked(OWNER_ADDR)),
@@ -117,6 +128,8 @@ fn mock_querier(query: &WasmQuery) -> QuerierResult {
             QueryMsg::NumTokens {} => QuerierResult::Ok(ContractResult::Ok(
                 to_json_binary(&NumTokensResponse { count: 1 }).unwrap(),
             )),
+            // cw721-base doesn't support CW2981
+            QueryMsg::Extension { .. } => QuerierResult::Err(cosmwasm_std::SystemError::Unknown {}),
             _ => unimplemented!(),
         },
         cosmwasm_std::WasmQuery::ContractInfo { .. } => QuerierResult::Ok(ContractResult::Ok(
@@ -138,7 +151,7 @@ fn mock_querier_v016(query: &WasmQuery) -> QuerierResult {
         cosmwasm_std::WasmQuery::Smart {
             contract_addr: _,
             msg,
-        } => match from_json::<cw721_base::msg::QueryMsg<Empty>>(&msg).unwrap() {
+        } => match from_json::<cw721_base::msg::QueryMsg<Cw2981QueryMsg>>(&msg).unwrap() {
             // unwrap using latest (not old) cw721-base, since it is backwards compatible
             cw721_base::msg::QueryMsg::Minter {} => QuerierResult::Ok(ContractResult::Ok(
                 to_json_binary(
@@ -191,6 +204,69 @@ fn mock_querier_v016(query: &WasmQuery) -> QuerierResult {
     }
 }
 
+// copy of cw2981_royalties::msg::CheckRoyaltiesResponse
+#[cw_serde]
+pub struct CheckRoyaltiesResponse {
+    pub royalty_payments: bool,
+}
+
+// copy of cw2981_royalties::msg::RoyaltiesInfoResponse
+#[cw_serde]
+pub struct RoyaltiesInfoResponse {
+    pub address: String,
+    pub royalty_amount: Uint128,
+}
+
+fn mock_querier_cw2981(query: &WasmQuery) -> QuerierResult {
+    if let WasmQuery::Smart { msg, .. } = query {
+        match from_json::<cw721_base::msg::QueryMsg<Cw2981QueryMsg>>(msg).unwrap() {
+            QueryMsg::Extension {
+                msg: Cw2981QueryMsg::CheckRoyalties {},
+            } => {
+                return QuerierResult::Ok(ContractResult::Ok(
+                    to_json_binary(&CheckRoyaltiesResponse {
+                        royalty_payments: true,
+                    })
+                    .unwrap(),
+                ))
+            }
+            QueryMsg::Extension {
+                msg: Cw2981QueryMsg::RoyaltyInfo { sale_price, .. },
+            } => {
+                return QuerierResult::Ok(ContractResult::Ok(
+                    to_json_binary(&RoyaltiesInfoResponse {
+                        address: ROYALTY_ADDR.to_string(),
+                        royalty_amount: sale_price * Decimal::percent(5),
+                    })
+                    .unwrap(),
+                ))
+            }
+            _ => {}
+        }
+    }
+    mock_querier(query)
+}
+
+// collection that doesn't support ownership or minter queries
+fn mock_querier_no_owner(query: &WasmQuery) -> QuerierResult {
+    match query {
+        cosmwasm_std::WasmQuery::Smart { .. } => {
+            QuerierResult::Err(cosmwasm_std::SystemError::Unknown {})
+        }
+        cosmwasm_std::WasmQuery::ContractInfo { .. } => QuerierResult::Ok(ContractResult::Ok(
+            to_json_binary(&ContractInfoResponse {
+                code_id: 0,
+                creator: "creator".to_string(),
+                admin: Some(ADMIN_ADDR.to_string()),
+                pinned: false,
+                ibc_port: None,
+            })
+            .unwrap(),
+        )),
+        _ => unimplemented!(),
+    }
+}
+
 #[test]
 fn test_receive_nft() {
     // test case: receive nft from cw721-base
@@ -219,7 +295,8 @@ fn test_receive_nft() {
         let msg = to_json_binary(&IbcOutgoingMsg {
             receiver: "callum".to_string(),
             channel_id: "channel-1".to_string(),
-            timeout: IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+            timeout: Some(IbcTimeout::with_timestamp(Timestamp::from_seconds(42))),
+            relative_timeout_seconds: None,
             memo: None,
         })
         .unwrap();
@@ -300,7 +377,8 @@ fn test_receive_nft() {
         let msg = to_json_binary(&IbcOutgoingMsg {
             receiver: "callum".to_string(),
             channel_id: "channel-1".to_string(),
-            timeout: IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+            timeout: Some(IbcTimeout::with_timestamp(Timestamp::from_seconds(42))),
+            relative_timeout_seconds: None,
             memo: None,
         })
         .unwrap();
@@ -381,7 +459,8 @@ fn test_receive_nft() {
         let msg = to_json_binary(&IbcOutgoingMsg {
             receiver: "callum".to_string(),
             channel_id: "channel-1".to_string(),
-            timeout: IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+            timeout: Some(IbcTimeout::with_timestamp(Timestamp::from_seconds(42))),
+            relative_timeout_seconds: None,
             memo: None,
         })
         .unwrap();
@@ -440,6 +519,49 @@ fn test_receive_nft() {
     }
 }
 
+#[test]
+fn test_receive_nft_with_royalty() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier_cw2981);
+
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+
+    let res: cosmwasm_std::Response<_> = Ics721ContractNoClassData::default()
+        .receive_nft(
+            deps.as_mut(),
+            mock_env(),
+            &Addr::unchecked(NFT_CONTRACT_1),
+            TokenId::new("1"),
+            "ekez".to_string(),
+            to_json_binary(&IbcOutgoingMsg {
+                receiver: "callum".to_string(),
+                channel_id: "channel-1".to_string(),
+                timeout: Some(IbcTimeout::with_timestamp(Timestamp::from_seconds(42))),
+                relative_timeout_seconds: None,
+                memo: None,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+    // royalty is passed as token data
+    let CosmosMsg::Ibc(IbcMsg::SendPacket { data, .. }) = res.messages[0].msg.clone() else {
+        panic!("expected send packet");
+    };
+    let packet: NonFungibleTokenPacketData = from_json(data).unwrap();
+    assert_eq!(
+        packet.token_data,
+        Some(vec![to_json_binary(&TokenData {
+            royalty_info: Some(RoyaltyData {
+                payment_address: ROYALTY_ADDR.to_string(),
+                share: Decimal::percent(5),
+            }),
+        })
+        .unwrap()])
+    );
+}
+
 #[test]
 fn test_receive_sets_uri() {
     let mut querier = MockQuerier::default();
@@ -455,7 +577,8 @@ fn test_receive_sets_uri() {
     let msg = to_json_binary(&IbcOutgoingMsg {
         receiver: "ekez".to_string(),
         channel_id: "channel-1".to_string(),
-        timeout: IbcTimeout::with_timestamp(Timestamp::from_nanos(42)),
+        timeout: Some(IbcTimeout::with_timestamp(Timestamp::from_nanos(42))),
+        relative_timeout_seconds: None,
         memo: None,
     })
     .unwrap();
@@ -664,3 +787,243 @@ fn test_migrate() {
         query_class_id_for_nft_contract(deps.as_ref(), NFT_CONTRACT_2.to_string()).unwrap();
     assert_eq!(class_id_2, Some(ClassId::new(CLASS_ID_2)));
 }
+
+#[test]
+fn test_migrate_channel_indexes() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let info = mock_info(OWNER_ADDR, &[]);
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    // before migrate, populate channel entries as plain maps
+    let outgoing: Map<(ClassId, TokenId), String> = Map::new("h");
+    let incoming: Map<(ClassId, TokenId), String> = Map::new("i");
+    for (class_id, token_id, channel) in [
+        (CLASS_ID_1, "1", "channel-0"),
+        (CLASS_ID_1, "2", "channel-1"),
+        (CLASS_ID_2, "1", "channel-0"),
+    ] {
+        outgoing
+            .save(
+                deps.as_mut().storage,
+                (ClassId::new(class_id), TokenId::new(token_id)),
+                &channel.to_string(),
+            )
+            .unwrap();
+    }
+    incoming
+        .save(
+            deps.as_mut().storage,
+            (ClassId::new(CLASS_ID_2), TokenId::new("2")),
+            &"channel-1".to_string(),
+        )
+        .unwrap();
+
+    let msg = MigrateMsg::WithUpdate {
+        pauser: None,
+        outgoing_proxy: None,
+        incoming_proxy: None,
+        cw721_base_code_id: None,
+        cw721_admin: None,
+        contract_addr_length: None,
+    };
+    let res = Ics721Contract {}
+        .migrate(deps.as_mut(), env.clone(), msg.clone())
+        .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("channel_indexes_done", "false")));
+
+    // entries are indexed in batches, outgoing ones first
+    for (indexed, done) in [("2", "false"), ("2", "false"), ("0", "true")] {
+        let res = Ics721Contract {}
+            .execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("anyone", &[]),
+                ExecuteMsg::BuildChannelIndexes { limit: Some(2) },
+            )
+            .unwrap();
+        assert!(res
+            .attributes
+            .contains(&attr("indexed_channel_entries", indexed)));
+        assert!(res.attributes.contains(&attr("done", done)));
+    }
+    // indexes are built only once
+    let res = Ics721Contract {}
+        .migrate(deps.as_mut(), env.clone(), msg)
+        .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("channel_indexes_done", "true")));
+
+    // stats are seeded in the same pass
+    let class_stats = |deps: Deps, class_id: &str| -> TransferStats {
+        from_json(
+            Ics721Contract {}
+                .query(
+                    deps,
+                    mock_env(),
+                    Ics721QueryMsg::ClassStats {
+                        class_id: class_id.to_string(),
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        class_stats(deps.as_ref(), CLASS_ID_1),
+        TransferStats {
+            escrowed: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        class_stats(deps.as_ref(), CLASS_ID_2),
+        TransferStats {
+            escrowed: 1,
+            vouchers_minted: 1,
+            ..Default::default()
+        }
+    );
+
+    let class_token = |class_id: &str, token_id: &str| ClassToken {
+        class_id: ClassId::new(class_id),
+        token_id: TokenId::new(token_id),
+    };
+    let query = |deps: Deps, msg: Ics721QueryMsg| -> Vec<ClassToken> {
+        from_json(Ics721Contract {}.query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+    assert_eq!(
+        query(
+            deps.as_ref(),
+            Ics721QueryMsg::OutgoingNftsByChannel {
+                channel_id: "channel-0".to_string(),
+                start_after: None,
+                limit: None,
+            }
+        ),
+        vec![class_token(CLASS_ID_1, "1"), class_token(CLASS_ID_2, "1")]
+    );
+    assert_eq!(
+        query(
+            deps.as_ref(),
+            Ics721QueryMsg::OutgoingNftsByChannel {
+                channel_id: "channel-0".to_string(),
+                start_after: Some(class_token(CLASS_ID_1, "1")),
+                limit: None,
+            }
+        ),
+        vec![class_token(CLASS_ID_2, "1")]
+    );
+    assert_eq!(
+        query(
+            deps.as_ref(),
+            Ics721QueryMsg::IncomingNftsByChannel {
+                channel_id: "channel-1".to_string(),
+                start_after: None,
+                limit: None,
+            }
+        ),
+        vec![class_token(CLASS_ID_2, "2")]
+    );
+
+    // new entries are indexed on save and unindexed on remove
+    OUTGOING_CLASS_TOKEN_TO_CHANNEL
+        .remove(
+            deps.as_mut().storage,
+            (ClassId::new(CLASS_ID_1), TokenId::new("1")),
+        )
+        .unwrap();
+    OUTGOING_CLASS_TOKEN_TO_CHANNEL
+        .save(
+            deps.as_mut().storage,
+            (ClassId::new(CLASS_ID_1), TokenId::new("3")),
+            &"channel-1".to_string(),
+        )
+        .unwrap();
+    assert_eq!(
+        query(
+            deps.as_ref(),
+            Ics721QueryMsg::OutgoingNftsByChannel {
+                channel_id: "channel-1".to_string(),
+                start_after: None,
+                limit: None,
+            }
+        ),
+        vec![class_token(CLASS_ID_1, "2"), class_token(CLASS_ID_1, "3")]
+    );
+
+    let channels_by_class: Vec<(TokenId, String)> = from_json(
+        Ics721Contract {}
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                Ics721QueryMsg::OutgoingChannelsByClass {
+                    class_id: CLASS_ID_1.to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        channels_by_class,
+        vec![
+            (TokenId::new("2"), "channel-1".to_string()),
+            (TokenId::new("3"), "channel-1".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_send_metadata_update_without_collection_owner() {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(mock_querier_no_owner);
+    let mut deps = mock_dependencies();
+    deps.querier = querier;
+    Ics721Contract {}
+        .instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER_ADDR, &[]),
+            instantiate_msg(None, None),
+        )
+        .unwrap();
+
+    let msg = ExecuteMsg::SendMetadataUpdate {
+        collection: NFT_CONTRACT_1.to_string(),
+        token_ids: vec!["1".to_string()],
+        channel_id: "channel-0".to_string(),
+        timeout: IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+    };
+
+    // failing owner query is treated as no owner
+    let err = Ics721Contract {}
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER_ADDR, &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // admin doesn't depend on owner query
+    let err = Ics721Contract {}
+        .execute(deps.as_mut(), mock_env(), mock_info(ADMIN_ADDR, &[]), msg)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NoClassIdForNftContract(NFT_CONTRACT_1.to_string())
+    );
+}
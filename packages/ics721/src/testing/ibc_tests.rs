@@ -4,26 +4,40 @@ use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
     attr, from_json,
     testing::{mock_dependencies, mock_env, mock_info},
-    to_json_binary, to_json_vec, Addr, Attribute, Binary, DepsMut, Empty, Env, IbcAcknowledgement,
-    IbcChannel, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcOrder, IbcPacket,
-    IbcPacketReceiveMsg, IbcTimeout, Reply, Response, StdResult, SubMsgResponse, SubMsgResult,
-    Timestamp, WasmMsg,
+    to_json_binary, to_json_vec, Addr, Attribute, Binary, CosmosMsg, Deps, DepsMut, Empty, Env,
+    IbcAcknowledgement, IbcChannel, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcOrder,
+    IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcTimeout, Reply,
+    Response, StdResult, SubMsg, SubMsgResponse, SubMsgResult, Timestamp, WasmMsg,
 };
 
 use crate::{
     execute::Ics721Execute,
-    ibc::{Ics721Ibc, ACK_AND_DO_NOTHING_REPLY_ID, IBC_VERSION, INSTANTIATE_CW721_REPLY_ID},
-    ibc_helpers::{ack_fail, ack_success, try_get_ack_error},
-    msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, QueryMsg},
+    helpers::{update_stats, HOOK_GAS_LIMIT},
+    ibc::{
+        Ics721Ibc, ACK_AND_DO_NOTHING_REPLY_ID, HOOK_REPLY_ID, IBC_VERSION,
+        INSTANTIATE_CW721_REPLY_ID, PARK_ON_FAILURE_REPLY_ID, PARK_REPLY_ID,
+    },
+    msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, QueryMsg, StatsInvariantResponse},
     query::Ics721Query,
-    state::{ClassIdInfo, CollectionData, CLASS_ID_AND_NFT_CONTRACT_INFO, CW721_CODE_ID, PO},
+    state::{
+        ChannelIndexCursor, ClaimableReceiptConfig, ClassIdInfo, CollectionController,
+        CollectionData, HookSubscription, ParkedTransfer, PendingClaim, TransferStats,
+        CHANNEL_INDEX_CURSOR, CLAIMABLE_RECEIPT_CONFIG, CLASS_ID_AND_NFT_CONTRACT_INFO,
+        CLASS_ID_TO_CLASS, CW721_CODE_ID, HOOK_SUBSCRIBERS, INCOMING_CLASS_TOKEN_TO_CHANNEL,
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL, PARKED_TOKENS, PARKED_TRANSFERS, PARKING_ERROR,
+        PARK_ON_FAILURE, PENDING_CLAIMS, PO, RECEIVING_PACKET, RETURNING_PARKED_TOKENS,
+        RETURNING_PENDING_CLAIMS,
+    },
+    token_types::{VoucherCreation, VoucherRedemption},
     utils::get_collection_data,
     ContractError,
 };
 use ics721_types::{
-    ibc_types::NonFungibleTokenPacketData,
-    token_types::{ClassId, TokenId},
-    types::{Ics721Callbacks, ReceiverExecuteMsg},
+    ack::{ack_fail, ack_success, try_get_ack_error},
+    events::Ics721Event,
+    ibc_types::{Ics721ControlPacketData, NonFungibleTokenPacketData},
+    token_types::{Class, ClassId, Token, TokenId},
+    types::{Ics721Callbacks, Ics721HookMsg, Ics721HookTransfer, ReceiverExecuteMsg},
 };
 
 const CONTRACT_PORT: &str = "wasm.address1";
@@ -492,6 +506,23 @@ fn test_ibc_packet_receive() {
     let operands = conjunction_msg.unwrap();
     assert_eq!(operands.len(), 2);
 
+    // receive event is emitted by the create vouchers callback
+    match operands[0].clone() {
+        WasmMsg::Execute { msg, .. } => match from_json(msg).unwrap() {
+            ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                transfer: Some(transfer),
+                ..
+            }) => {
+                assert_eq!(transfer.channel_id, CHANNEL_ID);
+                assert_eq!(transfer.token_ids, vec![TokenId::new("1")]);
+                assert_eq!(transfer.sender, "violet");
+                assert_eq!(transfer.receiver, "blue");
+            }
+            _ => panic!("unexpected execute msg"),
+        },
+        _ => panic!("unexpected wasm msg"),
+    }
+
     let add_incoming_msg = operands[1].clone();
     match add_incoming_msg {
         WasmMsg::Execute { msg, .. } => {
@@ -828,3 +859,1127 @@ fn test_ibc_packet_not_json_memo() {
         .ibc_packet_receive(deps.as_mut(), env, packet)
         .unwrap();
 }
+
+#[test]
+fn test_ibc_packet_receive_collection_ownership() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    let receive_ownership = |deps: DepsMut, class_id: &str, controller: &str| {
+        let data = to_json_binary(&Ics721ControlPacketData::CollectionOwnership {
+            class_id: ClassId::new(class_id),
+            owner: "violet".to_string(),
+            controller: Some(controller.to_string()),
+        })
+        .unwrap();
+        let packet = IbcPacketReceiveMsg::new(mock_packet(data), Addr::unchecked(RELAYER_ADDR));
+        Ics721Contract::default()
+            .ibc_packet_receive(deps, mock_env(), packet)
+            .unwrap()
+    };
+
+    // controller is recorded for local class id
+    let res = receive_ownership(deps.as_mut(), "id", "blue");
+    assert_eq!(res.acknowledgement, ack_success());
+    let local_class_id = format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id");
+    let controller: Option<CollectionController> = from_json(
+        Ics721Contract::default()
+            .query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VoucherCollectionController {
+                    class_id: local_class_id.clone(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        controller,
+        Some(CollectionController {
+            controller: Addr::unchecked("blue"),
+            source_owner: "violet".to_string(),
+            channel_id: CHANNEL_ID.to_string(),
+        })
+    );
+
+    // sending chain is not home chain of vouchers
+    let voucher_class_id = format!("{}/{}/{}", REMOTE_PORT, CHANNEL_ID, "id");
+    let res = receive_ownership(deps.as_mut(), &voucher_class_id, "blue");
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement));
+    assert_eq!(
+        error,
+        Some(ContractError::NotHomeCollection(voucher_class_id).to_string())
+    );
+
+    // only controller may update class metadata
+    let update_msg = ExecuteMsg::UpdateVoucherClassMetadata {
+        class_id: local_class_id.clone(),
+        uri: Some("ipfs://updated".to_string()),
+        data: None,
+    };
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("violet", &[]),
+            update_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    // class must exist
+    let err = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("blue", &[]),
+            update_msg.clone(),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NoNftContractForClassId(local_class_id.clone())
+    );
+    CLASS_ID_TO_CLASS
+        .save(
+            &mut deps.storage,
+            ClassId::new(local_class_id.clone()),
+            &Class {
+                id: ClassId::new(local_class_id.clone()),
+                uri: None,
+                data: None,
+            },
+        )
+        .unwrap();
+    Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("blue", &[]),
+            update_msg,
+        )
+        .unwrap();
+    let updated_class = Some(Class {
+        id: ClassId::new(local_class_id.clone()),
+        uri: Some("ipfs://updated".to_string()),
+        data: None,
+    });
+    let query_class = |deps: Deps| -> Option<Class> {
+        from_json(
+            Ics721Contract::default()
+                .query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::ClassMetadata {
+                        class_id: local_class_id.clone(),
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(query_class(deps.as_ref()), updated_class);
+
+    // incoming transfers don't overwrite class set by controller
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &ClassId::new(local_class_id.clone()),
+            &ClassIdInfo {
+                class_id: ClassId::new(local_class_id.clone()),
+                address: Addr::unchecked("voucher"),
+            },
+        )
+        .unwrap();
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(env.contract.address.as_str(), &[]),
+            ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                receiver: "blue".to_string(),
+                create: VoucherCreation {
+                    class: Class {
+                        id: ClassId::new(local_class_id.clone()),
+                        uri: Some("ipfs://source".to_string()),
+                        data: None,
+                    },
+                    tokens: vec![],
+                },
+                transfer: None,
+            }),
+        )
+        .unwrap();
+    assert!(res
+        .events
+        .iter()
+        .all(|event| event.ty != "ics721_class_updated"));
+    assert_eq!(query_class(deps.as_ref()), updated_class);
+}
+
+#[test]
+fn test_ibc_packet_receive_metadata_update() {
+    let mut deps = mock_dependencies();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    let local_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    let tokens = vec![Token {
+        id: TokenId::new("1"),
+        uri: Some("ipfs://revealed".to_string()),
+        data: None,
+    }];
+    let data = to_json_binary(&Ics721ControlPacketData::UpdateMetadata {
+        class_id: ClassId::new("id"),
+        tokens: tokens.clone(),
+    })
+    .unwrap();
+    let packet = IbcPacketReceiveMsg::new(mock_packet(data), Addr::unchecked(RELAYER_ADDR));
+
+    // voucher must have been received on the same channel
+    INCOMING_CLASS_TOKEN_TO_CHANNEL
+        .save(
+            &mut deps.storage,
+            (local_class_id.clone(), TokenId::new("1")),
+            &"channel-2".to_string(),
+        )
+        .unwrap();
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), mock_env(), packet.clone())
+        .unwrap();
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement));
+    assert_eq!(
+        error,
+        Some(
+            ContractError::TokenNotOnChannel {
+                class_id: local_class_id.to_string(),
+                token_id: "1".to_string(),
+                channel: CHANNEL_ID.to_string(),
+            }
+            .to_string()
+        )
+    );
+
+    INCOMING_CLASS_TOKEN_TO_CHANNEL
+        .save(
+            &mut deps.storage,
+            (local_class_id.clone(), TokenId::new("1")),
+            &CHANNEL_ID.to_string(),
+        )
+        .unwrap();
+    let env = mock_env();
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), env.clone(), packet)
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::UpdateTokenMetadata {
+                    class_id: local_class_id,
+                    tokens,
+                }))
+                .unwrap(),
+                funds: vec![],
+            },
+            ACK_AND_DO_NOTHING_REPLY_ID,
+        )]
+    );
+}
+
+#[test]
+fn test_ibc_packet_receive_claimable_receipt() {
+    let mut deps = mock_dependencies();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    CLAIMABLE_RECEIPT_CONFIG
+        .save(
+            &mut deps.storage,
+            &ClaimableReceiptConfig {
+                enabled: true,
+                expiry: 100,
+            },
+        )
+        .unwrap();
+    let local_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &local_class_id,
+            &ClassIdInfo {
+                class_id: local_class_id.clone(),
+                address: Addr::unchecked("cosmos2contract"),
+            },
+        )
+        .unwrap();
+
+    let data = to_json_binary(&NonFungibleTokenPacketData {
+        class_id: ClassId::new("id"),
+        class_uri: None,
+        class_data: None,
+        token_ids: vec![TokenId::new("1")],
+        token_uris: None,
+        token_data: None,
+        sender: "violet".to_string(),
+        receiver: "blue".to_string(),
+        memo: None,
+    })
+    .unwrap();
+    let packet = IbcPacketReceiveMsg::new(mock_packet(data), Addr::unchecked(RELAYER_ADDR));
+    let env = mock_env();
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), env.clone(), packet)
+        .unwrap();
+    assert!(res.attributes.contains(&attr("is_claimable", "true")));
+
+    let callbacks = match res.messages[0].msg.clone() {
+        cosmwasm_std::CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_json::<ExecuteMsg>(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands
+                    .into_iter()
+                    .map(|operand| match operand {
+                        WasmMsg::Execute { msg, .. } => match from_json(msg).unwrap() {
+                            ExecuteMsg::Callback(callback) => callback,
+                            _ => panic!("unexpected execute msg"),
+                        },
+                        _ => panic!("unexpected wasm msg"),
+                    })
+                    .collect::<Vec<CallbackMsg>>(),
+                _ => panic!("unexpected execute msg"),
+            }
+        }
+        _ => panic!("unexpected cosmos msg"),
+    };
+    // vouchers are held by ICS721
+    match &callbacks[0] {
+        CallbackMsg::CreateVouchers { receiver, .. } => {
+            assert_eq!(receiver, &env.contract.address.to_string())
+        }
+        _ => panic!("unexpected callback msg"),
+    }
+    // pending claims are added after incoming channel entries
+    let channel_callbacks = match &callbacks[1] {
+        CallbackMsg::Conjunction { operands } => operands
+            .iter()
+            .map(|operand| match operand {
+                WasmMsg::Execute { msg, .. } => from_json::<ExecuteMsg>(msg).unwrap(),
+                _ => panic!("unexpected wasm msg"),
+            })
+            .collect::<Vec<ExecuteMsg>>(),
+        _ => panic!("unexpected callback msg"),
+    };
+    assert_eq!(
+        channel_callbacks[1],
+        ExecuteMsg::Callback(CallbackMsg::AddPendingClaims(vec![(
+            (local_class_id, TokenId::new("1")),
+            PendingClaim {
+                receiver: Addr::unchecked("blue"),
+                sender: "violet".to_string(),
+                channel_id: CHANNEL_ID.to_string(),
+                expires_at: env.block.time.plus_seconds(100),
+            }
+        )]))
+    );
+}
+
+#[test]
+fn test_ibc_packet_receive_park_on_failure() {
+    let mut deps = mock_dependencies();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let local_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &local_class_id,
+            &ClassIdInfo {
+                class_id: local_class_id.clone(),
+                address: Addr::unchecked("cosmos2contract"),
+            },
+        )
+        .unwrap();
+    let packet_data = |receiver: &str| NonFungibleTokenPacketData {
+        class_id: ClassId::new("id"),
+        class_uri: None,
+        class_data: None,
+        token_ids: vec![TokenId::new("1")],
+        token_uris: None,
+        token_data: None,
+        sender: "violet".to_string(),
+        receiver: receiver.to_string(),
+        memo: None,
+    };
+    // parked transfers are received into ICS721 custody and saved at last
+    let env = mock_env();
+    let assert_parked = |message: &CosmosMsg, data: NonFungibleTokenPacketData, reason: &str| {
+        let operands = match message {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_json(msg).unwrap() {
+                ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => operands,
+                _ => panic!("unexpected execute msg"),
+            },
+            _ => panic!("unexpected cosmos msg"),
+        };
+        let callbacks = operands
+            .iter()
+            .map(|operand| match operand {
+                WasmMsg::Execute { msg, .. } => match from_json(msg).unwrap() {
+                    ExecuteMsg::Callback(callback) => callback,
+                    _ => panic!("unexpected execute msg"),
+                },
+                _ => panic!("unexpected wasm msg"),
+            })
+            .collect::<Vec<CallbackMsg>>();
+        match &callbacks[0] {
+            CallbackMsg::CreateVouchers { receiver, .. } => {
+                assert_eq!(receiver, &env.contract.address.to_string())
+            }
+            _ => panic!("unexpected callback msg"),
+        }
+        let park_msg = match &callbacks[1] {
+            CallbackMsg::Conjunction { operands } => operands[1].clone(),
+            _ => panic!("unexpected callback msg"),
+        };
+        assert_eq!(
+            park_msg,
+            WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::ParkTransfer {
+                    channel_id: CHANNEL_ID.to_string(),
+                    sequence: 42,
+                    transfer: ParkedTransfer {
+                        data,
+                        class_id: local_class_id.clone(),
+                        reason: reason.to_string(),
+                    },
+                }))
+                .unwrap(),
+                funds: vec![],
+            }
+        );
+    };
+
+    // invalid receiver isn't parked by default
+    let invalid_data = packet_data("BLUE");
+    let invalid_packet = IbcPacketReceiveMsg::new(
+        mock_packet(to_json_binary(&invalid_data).unwrap()),
+        Addr::unchecked(RELAYER_ADDR),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), env.clone(), invalid_packet.clone())
+        .unwrap();
+    let error = try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).unwrap();
+
+    PARK_ON_FAILURE.save(&mut deps.storage, &true).unwrap();
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), env.clone(), invalid_packet)
+        .unwrap();
+    assert_eq!(res.messages[0].id, PARK_REPLY_ID);
+    assert!(res
+        .attributes
+        .contains(&attr("parked_reason", error.clone())));
+    assert_parked(&res.messages[0].msg, invalid_data, &error);
+
+    // in case parking fails too, both errors are acked
+    assert_eq!(PARKING_ERROR.load(&deps.storage).unwrap(), error);
+    let res = Ics721Contract::default()
+        .reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: PARK_REPLY_ID,
+                result: SubMsgResult::Err("parking failed".to_string()),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.data,
+        Some(ack_fail(format!("{error}; parking failed: parking failed")))
+    );
+    assert!(PARKING_ERROR.may_load(&deps.storage).unwrap().is_none());
+
+    // in park on failure mode, packet is kept until its submessage is done
+    let data = packet_data("blue");
+    let ibc_packet = mock_packet(to_json_binary(&data).unwrap());
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(
+            deps.as_mut(),
+            env.clone(),
+            IbcPacketReceiveMsg::new(ibc_packet.clone(), Addr::unchecked(RELAYER_ADDR)),
+        )
+        .unwrap();
+    assert_eq!(res.messages[0].id, PARK_ON_FAILURE_REPLY_ID);
+    assert_eq!(RECEIVING_PACKET.load(&deps.storage).unwrap(), ibc_packet);
+
+    let res = Ics721Contract::default()
+        .reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: PARK_ON_FAILURE_REPLY_ID,
+                result: SubMsgResult::Err("receive callback failed".to_string()),
+            },
+        )
+        .unwrap();
+    assert_eq!(res.messages[0].id, PARK_REPLY_ID);
+    assert_parked(&res.messages[0].msg, data, "receive callback failed");
+    assert!(RECEIVING_PACKET.may_load(&deps.storage).unwrap().is_none());
+}
+
+#[test]
+fn test_ibc_packet_receive_dispatches_hook() {
+    let mut deps = mock_dependencies();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    CW721_CODE_ID
+        .save(&mut deps.storage, &CW721_BASE_CODE_ID)
+        .unwrap();
+    let local_class_id = ClassId::new(format!("{}/{}/{}", CONTRACT_PORT, CHANNEL_ID, "id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &local_class_id,
+            &ClassIdInfo {
+                class_id: local_class_id.clone(),
+                address: Addr::unchecked("cosmos2contract"),
+            },
+        )
+        .unwrap();
+    // one subscriber for all transfers, another one for a different channel only
+    HOOK_SUBSCRIBERS
+        .save(
+            &mut deps.storage,
+            &Addr::unchecked("analytics"),
+            &HookSubscription::default(),
+        )
+        .unwrap();
+    HOOK_SUBSCRIBERS
+        .save(
+            &mut deps.storage,
+            &Addr::unchecked("royalties"),
+            &HookSubscription {
+                class_ids: vec![],
+                channel_ids: vec!["channel-9".to_string()],
+            },
+        )
+        .unwrap();
+
+    let data = to_json_binary(&NonFungibleTokenPacketData {
+        class_id: ClassId::new("id"),
+        class_uri: None,
+        class_data: None,
+        token_ids: vec![TokenId::new("1")],
+        token_uris: None,
+        token_data: None,
+        sender: "violet".to_string(),
+        receiver: "blue".to_string(),
+        memo: None,
+    })
+    .unwrap();
+    let packet = IbcPacketReceiveMsg::new(mock_packet(data), Addr::unchecked(RELAYER_ADDR));
+    let env = mock_env();
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(deps.as_mut(), env.clone(), packet)
+        .unwrap();
+
+    // hook is dispatched last, once vouchers are minted
+    let hook_msg = match res.messages[0].msg.clone() {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_json(msg).unwrap() {
+            ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => {
+                operands.last().unwrap().clone()
+            }
+            _ => panic!("unexpected execute msg"),
+        },
+        _ => panic!("unexpected cosmos msg"),
+    };
+    let hook = Ics721HookMsg::VoucherMint {
+        transfer: Ics721HookTransfer {
+            channel_id: CHANNEL_ID.to_string(),
+            class_id: local_class_id,
+            nft_contract: "cosmos2contract".to_string(),
+            token_ids: vec![TokenId::new("1")],
+            sender: "violet".to_string(),
+            receiver: "blue".to_string(),
+        },
+    };
+    let callback = ExecuteMsg::Callback(CallbackMsg::DispatchHook(hook.clone()));
+    assert_eq!(
+        hook_msg,
+        WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&callback).unwrap(),
+            funds: vec![],
+        }
+    );
+
+    // only subscribers of the channel are notified, failing hooks are replied on error
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(env.contract.address.as_str(), &[]),
+            callback,
+        )
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_error(
+            WasmMsg::Execute {
+                contract_addr: "analytics".to_string(),
+                msg: to_json_binary(&ReceiverExecuteMsg::Ics721Hook(hook)).unwrap(),
+                funds: vec![],
+            },
+            HOOK_REPLY_ID,
+        )
+        .with_gas_limit(HOOK_GAS_LIMIT)]
+    );
+}
+
+#[test]
+fn test_redeem_vouchers_emits_receive_event() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let class_id = ClassId::new("id");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    let transfer = Ics721HookTransfer {
+        channel_id: CHANNEL_ID.to_string(),
+        class_id: class_id.clone(),
+        nft_contract: "nft".to_string(),
+        token_ids: vec![TokenId::new("1")],
+        sender: "violet".to_string(),
+        receiver: "blue".to_string(),
+    };
+    let redeem = |transfer: Option<Ics721HookTransfer>| {
+        ExecuteMsg::Callback(CallbackMsg::RedeemVouchers {
+            receiver: "blue".to_string(),
+            redeem: VoucherRedemption {
+                class: Class {
+                    id: class_id.clone(),
+                    uri: None,
+                    data: None,
+                },
+                token_ids: vec![TokenId::new("1")],
+            },
+            transfer,
+        })
+    };
+
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(env.contract.address.as_str(), &[]),
+            redeem(Some(transfer.clone())),
+        )
+        .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Ics721Event::ReceiveRedeem { transfer }.into()]
+    );
+
+    // parked transfers emit no receive event
+    let res = Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(env.contract.address.as_str(), &[]),
+            redeem(None),
+        )
+        .unwrap();
+    assert!(res.events.is_empty());
+}
+
+#[test]
+fn test_ibc_packet_ack_and_timeout_update_stats() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let class_id = ClassId::new(format!("{CONTRACT_PORT}/{CHANNEL_ID}/id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked("voucher"),
+            },
+        )
+        .unwrap();
+    // vouchers received and sent back to their source chain
+    for token_id in ["1", "2"] {
+        let key = (class_id.clone(), TokenId::new(token_id));
+        INCOMING_CLASS_TOKEN_TO_CHANNEL
+            .save(&mut deps.storage, key.clone(), &CHANNEL_ID.to_string())
+            .unwrap();
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL
+            .save(&mut deps.storage, key, &CHANNEL_ID.to_string())
+            .unwrap();
+        update_stats(&mut deps.storage, &class_id, CHANNEL_ID, |stats| {
+            stats.vouchers_minted += 1;
+            stats.escrowed += 1;
+            stats.in_flight += 1;
+        })
+        .unwrap();
+    }
+    let packet = |token_id: &str| {
+        mock_packet(
+            to_json_binary(&NonFungibleTokenPacketData {
+                class_id: class_id.clone(),
+                class_uri: None,
+                class_data: None,
+                token_ids: vec![TokenId::new(token_id)],
+                token_uris: None,
+                token_data: None,
+                sender: "violet".to_string(),
+                receiver: "blue".to_string(),
+                memo: None,
+            })
+            .unwrap(),
+        )
+    };
+
+    // voucher is burned on ack
+    Ics721Contract::default()
+        .ibc_packet_ack(
+            deps.as_mut(),
+            env.clone(),
+            IbcPacketAckMsg::new(
+                IbcAcknowledgement::new(ack_success()),
+                packet("1"),
+                Addr::unchecked(RELAYER_ADDR),
+            ),
+        )
+        .unwrap();
+    let key = (class_id.clone(), TokenId::new("1"));
+    assert!(!INCOMING_CLASS_TOKEN_TO_CHANNEL.has(&deps.storage, key));
+
+    // voucher is refunded on timeout
+    Ics721Contract::default()
+        .ibc_packet_timeout(
+            deps.as_mut(),
+            env.clone(),
+            IbcPacketTimeoutMsg::new(packet("2"), Addr::unchecked(RELAYER_ADDR)),
+        )
+        .unwrap();
+
+    let expected_stats = TransferStats {
+        escrowed: 0,
+        in_flight: 0,
+        vouchers_minted: 2,
+        vouchers_burned: 1,
+        failed: 0,
+        timed_out: 1,
+    };
+    let stats: TransferStats = from_json(
+        Ics721Contract::default()
+            .query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::ClassStats {
+                    class_id: class_id.to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stats, expected_stats);
+    let invariant: StatsInvariantResponse = from_json(
+        Ics721Contract::default()
+            .query(
+                deps.as_ref(),
+                env,
+                QueryMsg::StatsInvariant {
+                    channel_id: Some(CHANNEL_ID.to_string()),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        invariant,
+        StatsInvariantResponse {
+            stats: expected_stats,
+            outgoing_entries: 0,
+            incoming_entries: 1,
+            holds: true,
+        }
+    );
+}
+
+#[test]
+fn test_stats_updates_while_seeding_stats() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    let class_id = ClassId::new("id");
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked("nft"),
+            },
+        )
+        .unwrap();
+    // NFTs escrowed before stats were introduced, only 1 and 2 are seeded yet
+    for token_id in ["1", "2", "3"] {
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL
+            .save(
+                &mut deps.storage,
+                (class_id.clone(), TokenId::new(token_id)),
+                &CHANNEL_ID.to_string(),
+            )
+            .unwrap();
+    }
+    update_stats(&mut deps.storage, &class_id, CHANNEL_ID, |stats| {
+        stats.escrowed += 2
+    })
+    .unwrap();
+    CHANNEL_INDEX_CURSOR
+        .save(
+            &mut deps.storage,
+            &ChannelIndexCursor {
+                incoming: false,
+                start_after: Some((class_id.to_string(), "2".to_string())),
+                seed_stats: true,
+            },
+        )
+        .unwrap();
+
+    // refunds of seeded and not yet seeded NFTs, only the seeded one is
+    // counted
+    Ics721Contract::default()
+        .ibc_packet_timeout(
+            deps.as_mut(),
+            env.clone(),
+            IbcPacketTimeoutMsg::new(
+                mock_packet(
+                    to_json_binary(&NonFungibleTokenPacketData {
+                        class_id: class_id.clone(),
+                        class_uri: None,
+                        class_data: None,
+                        token_ids: vec![TokenId::new("1"), TokenId::new("3")],
+                        token_uris: None,
+                        token_data: None,
+                        sender: "violet".to_string(),
+                        receiver: "blue".to_string(),
+                        memo: None,
+                    })
+                    .unwrap(),
+                ),
+                Addr::unchecked(RELAYER_ADDR),
+            ),
+        )
+        .unwrap();
+    // incoming entries aren't seeded yet, so voucher is counted once reached
+    Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(env.contract.address.as_str(), &[]),
+            ExecuteMsg::Callback(CallbackMsg::AddIncomingChannelEntries(vec![(
+                (class_id.clone(), TokenId::new("4")),
+                CHANNEL_ID.to_string(),
+            )])),
+        )
+        .unwrap();
+    let class_stats = |deps: Deps| -> TransferStats {
+        from_json(
+            Ics721Contract::default()
+                .query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::ClassStats {
+                        class_id: class_id.to_string(),
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        class_stats(deps.as_ref()),
+        TransferStats {
+            escrowed: 1,
+            timed_out: 1,
+            ..Default::default()
+        }
+    );
+
+    Ics721Contract::default()
+        .execute(
+            deps.as_mut(),
+            env,
+            mock_info("anyone", &[]),
+            ExecuteMsg::BuildChannelIndexes { limit: None },
+        )
+        .unwrap();
+    assert_eq!(
+        class_stats(deps.as_ref()),
+        TransferStats {
+            escrowed: 1,
+            vouchers_minted: 1,
+            timed_out: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_ibc_packet_ack_removes_outgoing_entry_of_burned_voucher() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let voucher_class_id = ClassId::new(format!("{CONTRACT_PORT}/{CHANNEL_ID}/id"));
+    let native_class_id = ClassId::new("native");
+    for (class_id, nft_contract) in [
+        (voucher_class_id.clone(), "voucher"),
+        (native_class_id.clone(), "native"),
+    ] {
+        CLASS_ID_AND_NFT_CONTRACT_INFO
+            .save(
+                &mut deps.storage,
+                &class_id,
+                &ClassIdInfo {
+                    class_id: class_id.clone(),
+                    address: Addr::unchecked(nft_contract),
+                },
+            )
+            .unwrap();
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL
+            .save(
+                &mut deps.storage,
+                (class_id, TokenId::new("1")),
+                &CHANNEL_ID.to_string(),
+            )
+            .unwrap();
+    }
+    // only the voucher was received before
+    INCOMING_CLASS_TOKEN_TO_CHANNEL
+        .save(
+            &mut deps.storage,
+            (voucher_class_id.clone(), TokenId::new("1")),
+            &CHANNEL_ID.to_string(),
+        )
+        .unwrap();
+
+    for class_id in [&voucher_class_id, &native_class_id] {
+        Ics721Contract::default()
+            .ibc_packet_ack(
+                deps.as_mut(),
+                env.clone(),
+                IbcPacketAckMsg::new(
+                    IbcAcknowledgement::new(ack_success()),
+                    mock_packet(
+                        to_json_binary(&NonFungibleTokenPacketData {
+                            class_id: class_id.clone(),
+                            class_uri: None,
+                            class_data: None,
+                            token_ids: vec![TokenId::new("1")],
+                            token_uris: None,
+                            token_data: None,
+                            sender: "violet".to_string(),
+                            receiver: "blue".to_string(),
+                            memo: None,
+                        })
+                        .unwrap(),
+                    ),
+                    Addr::unchecked(RELAYER_ADDR),
+                ),
+            )
+            .unwrap();
+    }
+
+    // burned voucher is no longer escrowed, native NFT still is
+    assert!(
+        !OUTGOING_CLASS_TOKEN_TO_CHANNEL.has(&deps.storage, (voucher_class_id, TokenId::new("1")))
+    );
+    assert!(
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL.has(&deps.storage, (native_class_id, TokenId::new("1")))
+    );
+}
+
+#[test]
+fn test_ibc_packet_timeout_parks_returned_transfer_again() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let class_id = ClassId::new(format!("{CONTRACT_PORT}/{CHANNEL_ID}/id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked("voucher"),
+            },
+        )
+        .unwrap();
+    let transfer = ParkedTransfer {
+        data: NonFungibleTokenPacketData {
+            class_id: ClassId::new("id"),
+            class_uri: None,
+            class_data: None,
+            token_ids: vec![TokenId::new("1"), TokenId::new("2")],
+            token_uris: Some(vec!["ipfs://1".to_string(), "ipfs://2".to_string()]),
+            token_data: None,
+            sender: "violet".to_string(),
+            receiver: "invalid-receiver".to_string(),
+            memo: None,
+        },
+        class_id: class_id.clone(),
+        reason: "Generic error: Invalid input".to_string(),
+    };
+    // both tokens of parked transfer are returned by ICS721
+    for token_id in ["1", "2"] {
+        let key = (class_id.clone(), TokenId::new(token_id));
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL
+            .save(&mut deps.storage, key.clone(), &CHANNEL_ID.to_string())
+            .unwrap();
+        RETURNING_PARKED_TOKENS
+            .save(
+                &mut deps.storage,
+                key,
+                &((CHANNEL_ID.to_string(), 1), transfer.clone()),
+            )
+            .unwrap();
+    }
+    let packet = |token_id: &str| {
+        mock_packet(
+            to_json_binary(&NonFungibleTokenPacketData {
+                class_id: class_id.clone(),
+                class_uri: None,
+                class_data: None,
+                token_ids: vec![TokenId::new(token_id)],
+                token_uris: None,
+                token_data: None,
+                sender: env.contract.address.to_string(),
+                receiver: "violet".to_string(),
+                memo: None,
+            })
+            .unwrap(),
+        )
+    };
+
+    // returned token made it back
+    Ics721Contract::default()
+        .ibc_packet_ack(
+            deps.as_mut(),
+            env.clone(),
+            IbcPacketAckMsg::new(
+                IbcAcknowledgement::new(ack_success()),
+                packet("1"),
+                Addr::unchecked(RELAYER_ADDR),
+            ),
+        )
+        .unwrap();
+    assert!(!RETURNING_PARKED_TOKENS.has(&deps.storage, (class_id.clone(), TokenId::new("1"))));
+
+    // timed out token stays in ICS721 custody and is parked again
+    let res = Ics721Contract::default()
+        .ibc_packet_timeout(
+            deps.as_mut(),
+            env,
+            IbcPacketTimeoutMsg::new(packet("2"), Addr::unchecked(RELAYER_ADDR)),
+        )
+        .unwrap();
+    assert!(res.messages.is_empty());
+    let key = (class_id.clone(), TokenId::new("2"));
+    assert!(!RETURNING_PARKED_TOKENS.has(&deps.storage, key.clone()));
+    assert_eq!(
+        PARKED_TOKENS.load(&deps.storage, key).unwrap(),
+        (CHANNEL_ID.to_string(), 1)
+    );
+    let parked = PARKED_TRANSFERS
+        .load(&deps.storage, (CHANNEL_ID, 1))
+        .unwrap();
+    assert_eq!(parked.data.token_ids, vec![TokenId::new("2")]);
+    assert_eq!(parked.data.token_uris, Some(vec!["ipfs://2".to_string()]));
+    assert!(!PARKED_TOKENS.has(&deps.storage, (class_id, TokenId::new("1"))));
+}
+
+#[test]
+fn test_ibc_packet_timeout_restores_returning_pending_claim() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let class_id = ClassId::new(format!("{CONTRACT_PORT}/{CHANNEL_ID}/id"));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &class_id,
+            &ClassIdInfo {
+                class_id: class_id.clone(),
+                address: Addr::unchecked("voucher"),
+            },
+        )
+        .unwrap();
+    let claim = PendingClaim {
+        receiver: Addr::unchecked("receiver"),
+        sender: "violet".to_string(),
+        channel_id: CHANNEL_ID.to_string(),
+        expires_at: env.block.time,
+    };
+    // both vouchers of pending claims are returned by ICS721
+    for token_id in ["1", "2"] {
+        let key = (class_id.clone(), TokenId::new(token_id));
+        OUTGOING_CLASS_TOKEN_TO_CHANNEL
+            .save(&mut deps.storage, key.clone(), &CHANNEL_ID.to_string())
+            .unwrap();
+        RETURNING_PENDING_CLAIMS
+            .save(&mut deps.storage, key, &claim)
+            .unwrap();
+    }
+    let packet = |token_id: &str| {
+        mock_packet(
+            to_json_binary(&NonFungibleTokenPacketData {
+                class_id: class_id.clone(),
+                class_uri: None,
+                class_data: None,
+                token_ids: vec![TokenId::new(token_id)],
+                token_uris: None,
+                token_data: None,
+                sender: env.contract.address.to_string(),
+                receiver: "violet".to_string(),
+                memo: None,
+            })
+            .unwrap(),
+        )
+    };
+
+    // returned voucher made it back
+    Ics721Contract::default()
+        .ibc_packet_ack(
+            deps.as_mut(),
+            env.clone(),
+            IbcPacketAckMsg::new(
+                IbcAcknowledgement::new(ack_success()),
+                packet("1"),
+                Addr::unchecked(RELAYER_ADDR),
+            ),
+        )
+        .unwrap();
+    let key = (class_id.clone(), TokenId::new("1"));
+    assert!(!RETURNING_PENDING_CLAIMS.has(&deps.storage, key.clone()));
+    assert!(!PENDING_CLAIMS.has(&deps.storage, key));
+
+    // timed out voucher stays in ICS721 custody and its claim is pending again
+    let res = Ics721Contract::default()
+        .ibc_packet_timeout(
+            deps.as_mut(),
+            env,
+            IbcPacketTimeoutMsg::new(packet("2"), Addr::unchecked(RELAYER_ADDR)),
+        )
+        .unwrap();
+    assert!(res.messages.is_empty());
+    let key = (class_id, TokenId::new("2"));
+    assert!(!RETURNING_PENDING_CLAIMS.has(&deps.storage, key.clone()));
+    assert_eq!(PENDING_CLAIMS.load(&deps.storage, key).unwrap(), claim);
+}
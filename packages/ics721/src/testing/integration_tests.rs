@@ -1,17 +1,23 @@
+use std::marker::PhantomData;
+
 use anyhow::Result;
 use bech32::{decode, encode, FromBase32, ToBase32, Variant};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    from_json, instantiate2_address, to_json_binary, Addr, Api, Binary, CanonicalAddr, Deps,
-    DepsMut, Empty, Env, GovMsg, IbcTimeout, IbcTimeoutBlock, MemoryStorage, MessageInfo,
-    RecoverPubkeyError, Reply, Response, StdError, StdResult, Storage, VerificationError, WasmMsg,
+    from_json, instantiate2_address,
+    testing::{mock_env, MockQuerier, MockStorage},
+    to_json_binary, Addr, Api, Binary, CanonicalAddr, CosmosMsg, Decimal, Deps, DepsMut, Empty,
+    Env, Event, GovMsg, IbcAcknowledgement, IbcEndpoint, IbcPacket, IbcPacketReceiveMsg,
+    IbcTimeout, IbcTimeoutBlock, MemoryStorage, MessageInfo, OwnedDeps, RecoverPubkeyError, Reply,
+    Response, StdError, StdResult, Storage, Uint128, VerificationError, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw721_base::msg::{InstantiateMsg as Cw721InstantiateMsg, QueryMsg as Cw721QueryMsg};
 use cw_cii::{Admin, ContractInstantiateInfo};
 use cw_multi_test::{
-    AddressGenerator, App, AppBuilder, BankKeeper, Contract, ContractWrapper, DistributionKeeper,
-    Executor, FailingModule, IbcAcceptingModule, Router, StakeKeeper, StargateFailing, WasmKeeper,
+    AddressGenerator, App, AppBuilder, AppResponse, BankKeeper, Contract, ContractWrapper,
+    DistributionKeeper, Executor, FailingModule, IbcAcceptingModule, Router, StakeKeeper,
+    StargateFailing, WasmKeeper,
 };
 use cw_pause_once::PauseError;
 use sha2::{digest::Update, Digest, Sha256};
@@ -19,15 +25,30 @@ use sha2::{digest::Update, Digest, Sha256};
 use crate::{
     execute::Ics721Execute,
     ibc::Ics721Ibc,
-    msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg},
+    msg::{
+        AuditEscrowResponse, AuditFinding, AuditIssue, CallbackMsg, ExecuteMsg, InstantiateMsg,
+        MigrateMsg, OutgoingPacketPreview, PreviewSendResponse, QueryMsg, ReceivePlan,
+        SimulateReceiveResponse, VoucherCollectionRuleKey,
+    },
     query::Ics721Query,
-    state::{CollectionData, UniversalAllNftInfoResponse},
+    state::{
+        ChannelTimeoutConfig, ClaimableReceiptConfig, ClassIdInfo, CollectionController,
+        CollectionData, CounterpartyAddressPolicy, Cw2981Config, Cw2981Metadata, Cw2981QueryMsg,
+        HookSubscription, ParkedTransfer, ParkedTransferAction, PendingClaim, RoyaltyData,
+        RoyaltyPaymentPolicy, TokenData, UniversalAllNftInfoResponse, VoucherCollectionRule,
+        CLASS_ID_AND_NFT_CONTRACT_INFO, PO, RECEIVER_TRANSLATION_CHANNELS,
+    },
     token_types::VoucherCreation,
+    utils::{convert_owner_chain_address, translate_receiver},
     ContractError,
 };
 use ics721_types::{
-    ibc_types::{IbcOutgoingMsg, IbcOutgoingProxyMsg},
-    token_types::{Class, ClassId, Token, TokenId},
+    ack::{ack_success, try_get_ack_error},
+    ibc_types::{
+        IbcOutgoingMsg, IbcOutgoingProxyMsg, Ics721ControlPacketData, NonFungibleTokenPacketData,
+    },
+    token_types::{Class, ClassId, ClassToken, Token, TokenId},
+    types::{Ics721HookMsg, Ics721HookTransfer, ReceiverExecuteMsg},
 };
 
 use super::contract::Ics721Contract;
@@ -68,6 +89,19 @@ type MockApp = App<
     IbcAcceptingModule,
 >;
 
+// copy of cw2981_royalties::msg::CheckRoyaltiesResponse
+#[cw_serde]
+pub struct CheckRoyaltiesResponse {
+    pub royalty_payments: bool,
+}
+
+// copy of cw2981_royalties::msg::RoyaltiesInfoResponse
+#[cw_serde]
+pub struct RoyaltiesInfoResponse {
+    pub address: String,
+    pub royalty_amount: Uint128,
+}
+
 // copy of cosmwasm_std::ContractInfoResponse (marked as non-exhaustive)
 #[cw_serde]
 pub struct ContractInfoResponse {
@@ -518,6 +552,24 @@ impl Test {
             .unwrap()
     }
 
+    fn query_audit_escrow(
+        &mut self,
+        start_after: Option<ClassToken>,
+        limit: Option<u32>,
+    ) -> AuditEscrowResponse {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                self.ics721.clone(),
+                &QueryMsg::AuditEscrow {
+                    class_id: None,
+                    start_after,
+                    limit,
+                },
+            )
+            .unwrap()
+    }
+
     fn query_cw721_all_nft_info(&mut self, token_id: String) -> UniversalAllNftInfoResponse {
         self.app
             .wrap()
@@ -670,6 +722,7 @@ fn test_do_instantiate_and_mint_weird_data() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -712,6 +765,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -866,6 +920,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1019,6 +1074,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1176,6 +1232,7 @@ fn test_do_instantiate_and_mint() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1327,6 +1384,7 @@ fn test_do_instantiate_and_mint_2_different_collections() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1357,6 +1415,7 @@ fn test_do_instantiate_and_mint_2_different_collections() {
                             },
                         ],
                     },
+                    transfer: None,
                 }),
                 &[],
             )
@@ -1598,6 +1657,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -1630,6 +1690,7 @@ fn test_do_instantiate_and_mint_no_instantiate() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -1705,6 +1766,7 @@ fn test_do_instantiate_and_mint_permissions() {
                         data: None,
                     }],
                 },
+                transfer: None,
             }),
             &[],
         )
@@ -1723,10 +1785,11 @@ fn test_no_proxy_unknown_msg() {
         msg: to_json_binary(&IbcOutgoingMsg {
             receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
             channel_id: "channel-0".to_string(),
-            timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+            timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                 revision: 0,
                 height: 10,
-            }),
+            })),
+            relative_timeout_seconds: None,
             memo: None,
         })
         .unwrap(),
@@ -1761,10 +1824,11 @@ fn test_no_proxy_unauthorized() {
         msg: to_json_binary(&IbcOutgoingMsg {
             receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
             channel_id: "channel-0".to_string(),
-            timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+            timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                 revision: 0,
                 height: 10,
-            }),
+            })),
+            relative_timeout_seconds: None,
             memo: None,
         })
         .unwrap(),
@@ -1856,10 +1920,11 @@ fn test_proxy_authorized() {
                     msg: to_json_binary(&IbcOutgoingMsg {
                         receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
                         channel_id: "channel-0".to_string(),
-                        timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                        timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                             revision: 0,
                             height: 10,
-                        }),
+                        })),
+                        relative_timeout_seconds: None,
                         memo: None,
                     })
                     .unwrap(),
@@ -1892,10 +1957,11 @@ fn test_receive_nft() {
                     msg: to_json_binary(&IbcOutgoingMsg {
                         receiver: NFT_OWNER_TARGET_CHAIN.to_string(), // nft owner for other chain, on this chain ics721 is owner
                         channel_id: "channel-0".to_string(),
-                        timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                        timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                             revision: 0,
                             height: 10,
-                        }),
+                        })),
+                        relative_timeout_seconds: None,
                         memo: None,
                     })
                     .unwrap(),
@@ -1960,10 +2026,11 @@ fn test_receive_nft() {
                     msg: to_json_binary(&IbcOutgoingMsg {
                         receiver: NFT_OWNER_TARGET_CHAIN.to_string(), // nft owner for other chain, on this chain ics721 is owner
                         channel_id: "channel-0".to_string(),
-                        timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                        timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                             revision: 0,
                             height: 10,
-                        }),
+                        })),
+                        relative_timeout_seconds: None,
                         memo: None,
                     })
                     .unwrap(),
@@ -2041,10 +2108,11 @@ fn test_admin_clean_and_unescrow_nft() {
                     msg: to_json_binary(&IbcOutgoingMsg {
                         receiver: NFT_OWNER_TARGET_CHAIN.to_string(), // nft owner for other chain, on this chain ics721 is owner
                         channel_id: channel.clone(),
-                        timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                        timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                             revision: 0,
                             height: 10,
-                        }),
+                        })),
+                        relative_timeout_seconds: None,
                         memo: None,
                     })
                     .unwrap(),
@@ -2159,26 +2227,2845 @@ fn test_admin_clean_and_unescrow_nft() {
     }
 }
 
-/// In case proxy for ICS721 is defined, ICS721 only accepts receival from proxy - not from nft contract!
 #[test]
-fn test_no_receive_with_proxy() {
-    let mut test = Test::new(true, false, None, None, cw721_base_contract(), true);
-    // unauthorized to receive nft from nft contract
+fn test_audit_escrow() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let home_class_id = ClassId::new(test.source_cw721.to_string());
+    let nft_owner = test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN);
+
+    // token 1 and 2 are sent out, afterwards token 2 is no longer owned by ics721
+    let send_nft = |test: &mut Test| {
+        let token_id = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+        test.app
+            .execute_contract(
+                test.source_cw721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                    sender: test.source_cw721_owner.to_string(),
+                    token_id: token_id.clone(),
+                    msg: to_json_binary(&IbcOutgoingMsg {
+                        receiver: NFT_OWNER_TARGET_CHAIN.to_string(),
+                        channel_id: "channel-0".to_string(),
+                        timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
+                            revision: 0,
+                            height: 10,
+                        })),
+                        relative_timeout_seconds: None,
+                        memo: None,
+                    })
+                    .unwrap(),
+                }),
+                &[],
+            )
+            .unwrap();
+        token_id
+    };
+    send_nft(&mut test);
+    let token_id_not_escrowed = send_nft(&mut test);
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.source_cw721.clone(),
+            &cw721_base::msg::ExecuteMsg::<Empty, Empty>::TransferNft {
+                recipient: nft_owner.to_string(),
+                token_id: token_id_not_escrowed.clone(),
+            },
+            &[],
+        )
+        .unwrap();
+    // token 3 is owned by ics721, but never sent out
+    let token_id_no_outgoing = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+
+    // vouchers 1 and 2 are received, afterwards voucher 2 is burned
+    let voucher_class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                receiver: nft_owner.to_string(),
+                create: VoucherCreation {
+                    class: Class {
+                        id: voucher_class_id.clone(),
+                        uri: None,
+                        data: None,
+                    },
+                    tokens: vec![
+                        Token {
+                            id: TokenId::new("1"),
+                            uri: None,
+                            data: None,
+                        },
+                        Token {
+                            id: TokenId::new("2"),
+                            uri: None,
+                            data: None,
+                        },
+                    ],
+                },
+                transfer: None,
+            }),
+            &[],
+        )
+        .unwrap();
+    // voucher 1 has metadata, but no incoming channel entry
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::AddIncomingChannelEntries(vec![(
+                (voucher_class_id.clone(), TokenId::new("2")),
+                CHANNEL_TARGET_CHAIN.to_string(),
+            )])),
+            &[],
+        )
+        .unwrap();
+    let voucher_contract: Addr = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::NftContract {
+                class_id: voucher_class_id.to_string(),
+            },
+        )
+        .unwrap();
+    test.app
+        .execute_contract(
+            nft_owner.clone(),
+            voucher_contract.clone(),
+            &cw721_base::msg::ExecuteMsg::<Empty, Empty>::Burn {
+                token_id: "2".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    let sort_key = |f: &AuditFinding| (f.class_id.to_string(), String::from(f.token_id.clone()));
+    let AuditEscrowResponse {
+        mut findings,
+        next_start_after,
+    } = test.query_audit_escrow(None, None);
+    assert_eq!(next_start_after, None);
+    findings.sort_by_key(sort_key);
+    let mut expected = vec![
+        AuditFinding {
+            class_id: voucher_class_id.clone(),
+            token_id: TokenId::new("1"),
+            nft_contract: voucher_contract.clone(),
+            issue: AuditIssue::MetadataWithoutIncoming {},
+            fix: "admin_clean_and_burn_nft".to_string(),
+        },
+        AuditFinding {
+            class_id: voucher_class_id,
+            token_id: TokenId::new("2"),
+            nft_contract: voucher_contract,
+            issue: AuditIssue::IncomingWithoutVoucher {
+                channel: CHANNEL_TARGET_CHAIN.to_string(),
+            },
+            fix: "admin_clean_and_burn_nft".to_string(),
+        },
+        AuditFinding {
+            class_id: home_class_id.clone(),
+            token_id: TokenId::new(token_id_not_escrowed),
+            nft_contract: test.source_cw721.clone(),
+            issue: AuditIssue::OutgoingNotEscrowed {
+                owner: Some(nft_owner.to_string()),
+            },
+            fix: "admin_clean_and_unescrow_nft".to_string(),
+        },
+        AuditFinding {
+            class_id: home_class_id,
+            token_id: TokenId::new(token_id_no_outgoing),
+            nft_contract: test.source_cw721.clone(),
+            issue: AuditIssue::EscrowedWithoutOutgoing {},
+            fix: "admin_clean_and_unescrow_nft".to_string(),
+        },
+    ];
+    expected.sort_by_key(sort_key);
+    assert_eq!(findings, expected);
+
+    // paging one token at a time yields the same findings
+    let mut paged_findings = vec![];
+    let mut start_after = None;
+    let mut pages = 0;
+    loop {
+        let AuditEscrowResponse {
+            findings,
+            next_start_after,
+        } = test.query_audit_escrow(start_after, Some(1));
+        paged_findings.extend(findings);
+        pages += 1;
+        match next_start_after {
+            Some(next) => start_after = Some(next),
+            None => break,
+        }
+    }
+    // 3 home tokens + 2 vouchers, plus the last empty page
+    assert_eq!(pages, 6);
+    paged_findings.sort_by_key(sort_key);
+    assert_eq!(paged_findings, expected);
+}
+
+/// Admin messages are rejected for everyone, in case ICS721 has no
+/// admin.
+#[test]
+fn test_register_voucher_collection_without_admin() {
+    let mut test = Test::new(false, false, None, None, cw721_base_contract(), true);
+    let class_id = format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    );
+    for sender in [
+        test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER),
+        test.app.api().addr_make("not_admin"),
+    ] {
+        let err: ContractError = test
+            .app
+            .execute_contract(
+                sender,
+                test.ics721.clone(),
+                &ExecuteMsg::RegisterVoucherCollection {
+                    class_id: class_id.clone(),
+                    nft_contract: test.source_cw721.to_string(),
+                    class: None,
+                },
+                &[],
+            )
+            .unwrap_err()
+            .downcast()
+            .unwrap();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+}
+
+#[test]
+fn test_register_voucher_collection() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    // collection of a previous bridge, ics721 is minter
+    let legacy_collection = test
+        .app
+        .instantiate_contract(
+            test.source_cw721_id,
+            admin.clone(),
+            &Cw721InstantiateMsg {
+                name: "legacy".to_string(),
+                symbol: "legacy".to_string(),
+                minter: Some(test.ics721.to_string()),
+                withdraw_address: None,
+            },
+            &[],
+            "legacy voucher",
+            None,
+        )
+        .unwrap();
+    let class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    let register_msg = ExecuteMsg::RegisterVoucherCollection {
+        class_id: class_id.to_string(),
+        nft_contract: legacy_collection.to_string(),
+        class: None,
+    };
+
+    // non admin can't call
     let err: ContractError = test
         .app
         .execute_contract(
-            test.app.api().addr_make("cw721"),
-            test.ics721,
-            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
-                sender: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+            test.app.api().addr_make("not_admin"),
+            test.ics721.clone(),
+            &register_msg,
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // ics721 must be minter
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::RegisterVoucherCollection {
+                class_id: class_id.to_string(),
+                nft_contract: test.source_cw721.to_string(),
+                class: None,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::NotMinterOfNftContract {
+            nft_contract: test.source_cw721.to_string(),
+            minter: test.source_cw721_owner.to_string(),
+        }
+    );
+
+    // class id of class must match
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::RegisterVoucherCollection {
+                class_id: class_id.to_string(),
+                nft_contract: legacy_collection.to_string(),
+                class: Some(Class {
+                    id: ClassId::new("other"),
+                    uri: None,
+                    data: None,
+                }),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::ClassIdMismatch {
+            expected: class_id.to_string(),
+            actual: "other".to_string(),
+        }
+    );
+
+    test.app
+        .execute_contract(admin.clone(), test.ics721.clone(), &register_msg, &[])
+        .unwrap();
+    assert_eq!(
+        test.query_nft_contracts(),
+        vec![(class_id.to_string(), legacy_collection.clone())]
+    );
+    let class: Option<Class> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::ClassMetadata {
+                class_id: class_id.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        class,
+        Some(Class {
+            id: class_id.clone(),
+            uri: None,
+            data: None,
+        })
+    );
+
+    // conflicts with existing class id and nft contract are rejected
+    let err: ContractError = test
+        .app
+        .execute_contract(admin.clone(), test.ics721.clone(), &register_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::ClassIdAlreadyRegistered {
+            class_id: class_id.to_string(),
+            nft_contract: legacy_collection.to_string(),
+        }
+    );
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::RegisterVoucherCollection {
+                class_id: "other".to_string(),
+                nft_contract: legacy_collection.to_string(),
+                class: None,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::NftContractAlreadyRegistered {
+            nft_contract: legacy_collection.to_string(),
+            class_id: class_id.to_string(),
+        }
+    );
+
+    // incoming nfts are minted on registered collection
+    let nft_owner = test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN);
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                receiver: nft_owner.to_string(),
+                create: VoucherCreation {
+                    class: Class {
+                        id: class_id.clone(),
+                        uri: None,
+                        data: None,
+                    },
+                    tokens: vec![Token {
+                        id: TokenId::new("1"),
+                        uri: None,
+                        data: None,
+                    }],
+                },
+                transfer: None,
+            }),
+            &[],
+        )
+        .unwrap();
+    let owner: cw721::OwnerOfResponse = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            legacy_collection,
+            &Cw721QueryMsg::<Empty>::OwnerOf {
                 token_id: "1".to_string(),
-                msg: to_json_binary(&IbcOutgoingMsg {
+                include_expired: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(owner.owner, nft_owner.to_string());
+}
+
+#[test]
+fn test_migrate_voucher_collections() {
+    fn cw721_migrate(_deps: DepsMut, _env: Env, _msg: Empty) -> StdResult<Response> {
+        Ok(Response::default())
+    }
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let old_code_id = test.app.store_code(Box::new(
+        ContractWrapper::new(
+            cw721_base::entry::execute,
+            cw721_base::entry::instantiate,
+            cw721_base::entry::query,
+        )
+        .with_migrate(cw721_migrate),
+    ));
+    let new_code_id = test.app.store_code(Box::new(
+        ContractWrapper::new(
+            cw721_base::entry::execute,
+            cw721_base::entry::instantiate,
+            cw721_base::entry::query,
+        )
+        .with_migrate(cw721_migrate),
+    ));
+    // collections "a" and "c" have ics721 as admin, "b" has another admin
+    let mut collections = vec![];
+    for (class_id, cw721_admin) in [
+        ("a", test.ics721.clone()),
+        ("b", admin.clone()),
+        ("c", test.ics721.clone()),
+    ] {
+        let collection = test
+            .app
+            .instantiate_contract(
+                old_code_id,
+                admin.clone(),
+                &Cw721InstantiateMsg {
+                    name: class_id.to_string(),
+                    symbol: class_id.to_string(),
+                    minter: Some(test.ics721.to_string()),
+                    withdraw_address: None,
+                },
+                &[],
+                class_id,
+                Some(cw721_admin.to_string()),
+            )
+            .unwrap();
+        test.app
+            .execute_contract(
+                admin.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::RegisterVoucherCollection {
+                    class_id: class_id.to_string(),
+                    nft_contract: collection.to_string(),
+                    class: None,
+                },
+                &[],
+            )
+            .unwrap();
+        collections.push(collection);
+    }
+    // native collections are skipped
+    let token_id = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+    test.app
+        .execute_contract(
+            test.source_cw721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                sender: test.source_cw721_owner.to_string(),
+                token_id,
+                msg: to_json_binary(&IbcOutgoingMsg {
+                    receiver: NFT_OWNER_TARGET_CHAIN.to_string(),
+                    channel_id: "channel-0".to_string(),
+                    timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
+                        revision: 0,
+                        height: 10,
+                    })),
+                    relative_timeout_seconds: None,
+                    memo: None,
+                })
+                .unwrap(),
+            }),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(test.query_nft_contracts().len(), 4);
+
+    let migrate_msg = |start_after: Option<&str>| ExecuteMsg::MigrateVoucherCollections {
+        new_code_id,
+        msg: to_json_binary(&Empty {}).unwrap(),
+        start_after: start_after.map(str::to_string),
+        limit: Some(2),
+    };
+    let migration_results = |res: &cw_multi_test::AppResponse| -> Vec<(String, String)> {
+        res.events
+            .iter()
+            .filter(|e| e.ty == "wasm-ics721_migrate_voucher_collection")
+            .map(|e| {
+                let attr = |key: &str| {
+                    e.attributes
+                        .iter()
+                        .find(|a| a.key == key)
+                        .unwrap()
+                        .value
+                        .clone()
+                };
+                (attr("class_id"), attr("result"))
+            })
+            .collect()
+    };
+    let attribute = |res: &cw_multi_test::AppResponse, key: &str| -> Option<String> {
+        res.events
+            .iter()
+            .flat_map(|e| e.attributes.iter())
+            .find(|a| a.key == key)
+            .map(|a| a.value.clone())
+    };
+
+    // non admin can't call
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("not_admin"),
+            test.ics721.clone(),
+            &migrate_msg(None),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = test
+        .app
+        .execute_contract(admin.clone(), test.ics721.clone(), &migrate_msg(None), &[])
+        .unwrap();
+    let mut results = migration_results(&res);
+    results.sort();
+    assert_eq!(
+        results,
+        vec![
+            ("a".to_string(), "migrated".to_string()),
+            ("b".to_string(), "skipped: ics721 not admin".to_string()),
+        ]
+    );
+    assert_eq!(attribute(&res, "done"), Some("false".to_string()));
+    assert_eq!(attribute(&res, "next_start_after"), Some("b".to_string()));
+
+    let res = test
+        .app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &migrate_msg(Some("b")),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        migration_results(&res),
+        vec![("c".to_string(), "migrated".to_string())]
+    );
+    assert_eq!(attribute(&res, "done"), Some("true".to_string()));
+    assert_eq!(attribute(&res, "next_start_after"), None);
+
+    let code_ids: Vec<u64> = collections
+        .iter()
+        .map(|c| test.app.wrap().query_wasm_contract_info(c).unwrap().code_id)
+        .collect();
+    assert_eq!(code_ids, vec![new_code_id, old_code_id, new_code_id]);
+}
+
+#[test]
+fn test_voucher_collection_rules() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let rule_code_id = test.app.store_code(cw721_base_contract());
+    let prefix = format!("wasm.{}/{}/", test.ics721, CHANNEL_TARGET_CHAIN);
+    let prefix_rule = VoucherCollectionRule {
+        code_id: rule_code_id,
+        label: Some("voucher {class_id}".to_string()),
+        admin: Some("".to_string()),
+    };
+    let set_rule_msg = ExecuteMsg::SetVoucherCollectionRule {
+        key: VoucherCollectionRuleKey::Prefix(prefix.clone()),
+        rule: Some(prefix_rule.clone()),
+    };
+    let query_rule = |test: &Test, class_id: &str| -> Option<VoucherCollectionRule> {
+        test.app
+            .wrap()
+            .query_wasm_smart(
+                test.ics721.clone(),
+                &QueryMsg::VoucherCollectionRule {
+                    class_id: class_id.to_string(),
+                },
+            )
+            .unwrap()
+    };
+
+    // non admin can't call
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("not_admin"),
+            test.ics721.clone(),
+            &set_rule_msg,
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    test.app
+        .execute_contract(admin.clone(), test.ics721.clone(), &set_rule_msg, &[])
+        .unwrap();
+    // longer prefix wins, exact class id wins over any prefix
+    let class_id = ClassId::new(format!(
+        "{}{}",
+        prefix,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    let exact_rule = VoucherCollectionRule {
+        code_id: test.source_cw721_id,
+        label: None,
+        admin: None,
+    };
+    test.app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::SetVoucherCollectionRule {
+                key: VoucherCollectionRuleKey::Prefix("wasm.".to_string()),
+                rule: Some(exact_rule.clone()),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_rule(&test, &class_id), Some(prefix_rule.clone()));
+    assert_eq!(query_rule(&test, "wasm.other"), Some(exact_rule.clone()));
+    assert_eq!(query_rule(&test, "other"), None);
+    test.app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::SetVoucherCollectionRule {
+                key: VoucherCollectionRuleKey::ClassId(class_id.to_string()),
+                rule: Some(exact_rule.clone()),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_rule(&test, &class_id), Some(exact_rule));
+    // removing exact rule falls back to prefix rule
+    test.app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::SetVoucherCollectionRule {
+                key: VoucherCollectionRuleKey::ClassId(class_id.to_string()),
+                rule: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_rule(&test, &class_id), Some(prefix_rule));
+
+    // predicted address uses code id of rule
+    let predicted_nft_contract: Addr = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::GetInstantiate2NftContract {
+                class_id: class_id.to_string(),
+                cw721_code_id: None,
+            },
+        )
+        .unwrap();
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                create: VoucherCreation {
+                    class: Class {
+                        id: class_id.clone(),
+                        uri: None,
+                        data: None,
+                    },
+                    tokens: vec![Token {
+                        id: TokenId::new("1"),
+                        uri: None,
+                        data: None,
+                    }],
+                },
+                transfer: None,
+            }),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        test.query_nft_contracts(),
+        vec![(class_id.to_string(), predicted_nft_contract.clone())]
+    );
+    let contract_info = test.app.contract_data(&predicted_nft_contract).unwrap();
+    assert_eq!(contract_info.code_id, rule_code_id);
+    assert_eq!(contract_info.admin, None);
+    assert_eq!(contract_info.label, format!("voucher {}", class_id));
+}
+
+#[test]
+fn test_cw2981_royalties() {
+    type Cw2981Contract<'a> = cw721_base::Cw721Contract<'a, Cw2981Metadata, Empty, Empty, Empty>;
+    fn cw2981_execute(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: cw721_base::msg::ExecuteMsg<Cw2981Metadata, Empty>,
+    ) -> Result<Response, cw721_base::ContractError> {
+        Cw2981Contract::default().execute(deps, env, info, msg)
+    }
+    fn cw2981_instantiate(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Cw721InstantiateMsg,
+    ) -> Result<Response, cw721_base::ContractError> {
+        Ok(Cw2981Contract::default().instantiate(deps, env, info, msg)?)
+    }
+    fn cw2981_query(deps: Deps, env: Env, msg: Cw721QueryMsg<Cw2981QueryMsg>) -> StdResult<Binary> {
+        match msg {
+            Cw721QueryMsg::Extension {
+                msg: Cw2981QueryMsg::CheckRoyalties {},
+            } => to_json_binary(&CheckRoyaltiesResponse {
+                royalty_payments: true,
+            }),
+            Cw721QueryMsg::Extension {
+                msg:
+                    Cw2981QueryMsg::RoyaltyInfo {
+                        token_id,
+                        sale_price,
+                    },
+            } => {
+                let token = Cw2981Contract::default()
+                    .tokens
+                    .load(deps.storage, &token_id)?;
+                to_json_binary(&RoyaltiesInfoResponse {
+                    address: token.extension.royalty_payment_address.unwrap_or_default(),
+                    royalty_amount: sale_price.multiply_ratio(
+                        token.extension.royalty_percentage.unwrap_or_default(),
+                        100u128,
+                    ),
+                })
+            }
+            // other queries are the same as cw721-base
+            msg => Cw2981Contract::default().query(deps, env, from_json(to_json_binary(&msg)?)?),
+        }
+    }
+
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let cw2981_code_id = test.app.store_code(Box::new(ContractWrapper::new(
+        cw2981_execute,
+        cw2981_instantiate,
+        cw2981_query,
+    )));
+    let fallback = test.app.api().addr_make("royalty-fallback").to_string();
+    let config = Cw2981Config {
+        code_id: cw2981_code_id,
+        payment_policy: RoyaltyPaymentPolicy::ConvertWalletAddress {
+            fallback: fallback.clone(),
+        },
+    };
+
+    // non admin can't set config
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("not_admin"),
+            test.ics721.clone(),
+            &ExecuteMsg::SetCw2981Config {
+                config: Some(config.clone()),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    test.app
+        .execute_contract(
+            admin,
+            test.ics721.clone(),
+            &ExecuteMsg::SetCw2981Config {
+                config: Some(config.clone()),
+            },
+            &[],
+        )
+        .unwrap();
+    let queried: Option<Cw2981Config> = test
+        .app
+        .wrap()
+        .query_wasm_smart(test.ics721.clone(), &QueryMsg::Cw2981Config {})
+        .unwrap();
+    assert_eq!(queried, Some(config));
+
+    // new voucher collections are CW2981, even if first NFTs have no royalties
+    let wallet_data = [1u8; 20].to_base32();
+    let source_payment_address = encode("juno", wallet_data.clone(), Variant::Bech32).unwrap();
+    let contract_payment_address = encode("juno", [2u8; 32].to_base32(), Variant::Bech32).unwrap();
+    let royalty_data = |payment_address: &str, share: Decimal| {
+        Some(
+            to_json_binary(&TokenData {
+                royalty_info: Some(RoyaltyData {
+                    payment_address: payment_address.to_string(),
+                    share,
+                }),
+            })
+            .unwrap(),
+        )
+    };
+    let create_vouchers = |test: &mut Test, class_id: &ClassId, tokens: Vec<Token>| {
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                    receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                    create: VoucherCreation {
+                        class: Class {
+                            id: class_id.clone(),
+                            uri: None,
+                            data: None,
+                        },
+                        tokens,
+                    },
+                    transfer: None,
+                }),
+                &[],
+            )
+            .unwrap();
+    };
+    let class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    let predicted_nft_contract: Addr = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::GetInstantiate2NftContract {
+                class_id: class_id.to_string(),
+                cw721_code_id: None,
+            },
+        )
+        .unwrap();
+    create_vouchers(
+        &mut test,
+        &class_id,
+        vec![Token {
+            id: TokenId::new("1"),
+            uri: None,
+            data: None,
+        }],
+    );
+    create_vouchers(
+        &mut test,
+        &class_id,
+        vec![
+            Token {
+                id: TokenId::new("2"),
+                uri: None,
+                data: royalty_data(&source_payment_address, Decimal::percent(5)),
+            },
+            Token {
+                id: TokenId::new("3"),
+                uri: None,
+                data: royalty_data(&contract_payment_address, Decimal::permille(105)),
+            },
+            Token {
+                id: TokenId::new("4"),
+                uri: None,
+                data: royalty_data(
+                    &source_payment_address,
+                    Decimal::from_ratio(1u128, 1_000_000u128),
+                ),
+            },
+        ],
+    );
+    assert_eq!(
+        test.query_nft_contracts(),
+        vec![(class_id.to_string(), predicted_nft_contract.clone())]
+    );
+    let contract_info = test.app.contract_data(&predicted_nft_contract).unwrap();
+    assert_eq!(contract_info.code_id, cw2981_code_id);
+
+    let query_royalty = |test: &Test, token_id: &str| -> RoyaltiesInfoResponse {
+        test.app
+            .wrap()
+            .query_wasm_smart(
+                predicted_nft_contract.clone(),
+                &Cw721QueryMsg::Extension {
+                    msg: Cw2981QueryMsg::RoyaltyInfo {
+                        token_id: token_id.to_string(),
+                        sale_price: Uint128::new(1_000),
+                    },
+                },
+            )
+            .unwrap()
+    };
+    // no royalty
+    assert_eq!(
+        query_royalty(&test, "1"),
+        RoyaltiesInfoResponse {
+            address: "".to_string(),
+            royalty_amount: Uint128::zero(),
+        }
+    );
+    // wallet address is converted to this chain, whole percent is kept
+    let target_payment_address = encode(BECH32_PREFIX_HRP, wallet_data, Variant::Bech32).unwrap();
+    assert_eq!(
+        query_royalty(&test, "2"),
+        RoyaltiesInfoResponse {
+            address: target_payment_address.clone(),
+            royalty_amount: Uint128::new(50),
+        }
+    );
+    // contract address falls back, share is rounded up to percentage
+    assert_eq!(
+        query_royalty(&test, "3"),
+        RoyaltiesInfoResponse {
+            address: fallback,
+            royalty_amount: Uint128::new(110),
+        }
+    );
+    // tiny share isn't rounded down to zero
+    assert_eq!(
+        query_royalty(&test, "4"),
+        RoyaltiesInfoResponse {
+            address: target_payment_address,
+            royalty_amount: Uint128::new(10),
+        }
+    );
+
+    // voucher collection rule with another code id opts out of CW2981
+    let other_class_id = ClassId::new(format!("{}-other", class_id));
+    test.app
+        .execute_contract(
+            test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER),
+            test.ics721.clone(),
+            &ExecuteMsg::SetVoucherCollectionRule {
+                key: VoucherCollectionRuleKey::ClassId(other_class_id.to_string()),
+                rule: Some(VoucherCollectionRule {
+                    code_id: test.source_cw721_id,
+                    label: None,
+                    admin: None,
+                }),
+            },
+            &[],
+        )
+        .unwrap();
+    create_vouchers(
+        &mut test,
+        &other_class_id,
+        vec![Token {
+            id: TokenId::new("1"),
+            uri: None,
+            data: royalty_data(&source_payment_address, Decimal::percent(5)),
+        }],
+    );
+    let other_nft_contract = test
+        .query_nft_contracts()
+        .into_iter()
+        .find(|(class_id, _)| *class_id == other_class_id.to_string())
+        .map(|(_, nft_contract)| nft_contract)
+        .unwrap();
+    let contract_info = test.app.contract_data(&other_nft_contract).unwrap();
+    assert_eq!(contract_info.code_id, test.source_cw721_id);
+}
+
+#[test]
+fn test_send_collection_ownership() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let send_msg = |collection: &Addr| ExecuteMsg::SendCollectionOwnership {
+        collection: collection.to_string(),
+        channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+        controller: None,
+        timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+            revision: 0,
+            height: 10,
+        }),
+    };
+
+    // only collection owner may send
+    let not_owner = test.app.api().addr_make("not_owner");
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            not_owner.clone(),
+            test.ics721.clone(),
+            &send_msg(&test.source_cw721),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::NotOwnerOfCollection {
+            sender: not_owner.to_string(),
+            collection: test.source_cw721.to_string(),
+            owner: test.source_cw721_owner.to_string(),
+        }
+    );
+    let res = test
+        .app
+        .execute_contract(
+            test.source_cw721_owner.clone(),
+            test.ics721.clone(),
+            &send_msg(&test.source_cw721),
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("method", "execute_send_collection_ownership")
+            .add_attribute("collection", test.source_cw721.to_string())
+            .add_attribute("owner", test.source_cw721_owner.to_string())
+    ));
+
+    // ownership of voucher collections can't be sent
+    let class_id = format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    );
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                create: VoucherCreation {
+                    class: Class {
+                        id: ClassId::new(class_id.clone()),
+                        uri: None,
+                        data: None,
+                    },
+                    tokens: vec![Token {
+                        id: TokenId::new("1"),
+                        uri: None,
+                        data: None,
+                    }],
+                },
+                transfer: None,
+            }),
+            &[],
+        )
+        .unwrap();
+    let (_, voucher_collection) = test.query_nft_contracts()[0].clone();
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &send_msg(&voucher_collection),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::NotHomeCollection(class_id));
+}
+
+#[test]
+fn test_receive_collection_ownership_default_controller() {
+    // receive packet using bech32 addresses of this chain
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApiBech32::new(BECH32_PREFIX_HRP),
+        querier: MockQuerier::new(&[]),
+        custom_query_type: PhantomData::<Empty>,
+    };
+    let mut env = mock_env();
+    env.contract.address = deps.api.addr_make("ics721");
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    let wallet_data = [1u8; 20].to_base32();
+    let source_wallet = encode("juno", wallet_data.clone(), Variant::Bech32).unwrap();
+    let wallet = encode(BECH32_PREFIX_HRP, wallet_data, Variant::Bech32).unwrap();
+    let source_contract = encode("juno", [2u8; 32].to_base32(), Variant::Bech32).unwrap();
+    let receive_ownership = |deps: DepsMut, owner: &str, controller: Option<String>| {
+        let data = Ics721ControlPacketData::CollectionOwnership {
+            class_id: ClassId::new("id"),
+            owner: owner.to_string(),
+            controller,
+        };
+        let packet = IbcPacket::new(
+            to_json_binary(&data).unwrap(),
+            IbcEndpoint {
+                port_id: "wasm.source".to_string(),
+                channel_id: "channel-0".to_string(),
+            },
+            IbcEndpoint {
+                port_id: format!("wasm.{}", env.contract.address),
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+            },
+            1,
+            IbcTimeout::with_block(IbcTimeoutBlock {
+                revision: 0,
+                height: 10,
+            }),
+        );
+        Ics721Contract::default()
+            .ibc_packet_receive(
+                deps,
+                env.clone(),
+                IbcPacketReceiveMsg::new(packet, Addr::unchecked("relayer")),
+            )
+            .unwrap()
+    };
+    let local_class_id = format!("wasm.{}/{}/id", env.contract.address, CHANNEL_TARGET_CHAIN);
+    let query_controller = |deps: Deps| -> Option<CollectionController> {
+        from_json(
+            Ics721Contract::default()
+                .query(
+                    deps,
+                    env.clone(),
+                    QueryMsg::VoucherCollectionController {
+                        class_id: local_class_id.clone(),
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // contract owner has no account on this chain
+    let res = receive_ownership(deps.as_mut(), &source_contract, None);
+    assert_eq!(
+        try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)),
+        Some(ContractError::OwnerNotWalletAddress(source_contract.clone()).to_string())
+    );
+    assert_eq!(query_controller(deps.as_ref()), None);
+
+    // unless a controller is given
+    let res = receive_ownership(deps.as_mut(), &source_contract, Some(wallet.clone()));
+    assert_eq!(res.acknowledgement, ack_success());
+    assert_eq!(
+        query_controller(deps.as_ref()).map(|c| c.controller),
+        Some(Addr::unchecked(&wallet))
+    );
+
+    // wallet owner is converted to an address of this chain
+    let res = receive_ownership(deps.as_mut(), &source_wallet, None);
+    assert_eq!(res.acknowledgement, ack_success());
+    assert_eq!(
+        query_controller(deps.as_ref()),
+        Some(CollectionController {
+            controller: Addr::unchecked(wallet),
+            source_owner: source_wallet,
+            channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_metadata_update() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    // home chain: only NFTs escrowed on channel can be updated
+    let token_id = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+    test.app
+        .execute_contract(
+            test.source_cw721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                sender: test.source_cw721_owner.to_string(),
+                token_id: token_id.clone(),
+                msg: to_json_binary(&IbcOutgoingMsg {
+                    receiver: NFT_OWNER_TARGET_CHAIN.to_string(),
+                    channel_id: "channel-0".to_string(),
+                    timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
+                        revision: 0,
+                        height: 10,
+                    })),
+                    relative_timeout_seconds: None,
+                    memo: None,
+                })
+                .unwrap(),
+            }),
+            &[],
+        )
+        .unwrap();
+    let send_msg = |channel_id: &str| ExecuteMsg::SendMetadataUpdate {
+        collection: test.source_cw721.to_string(),
+        token_ids: vec![token_id.clone()],
+        channel_id: channel_id.to_string(),
+        timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+            revision: 0,
+            height: 10,
+        }),
+    };
+    // only collection owner or admin
+    let anyone = test.app.api().addr_make("anyone");
+    let err: ContractError = test
+        .app
+        .execute_contract(anyone, test.ics721.clone(), &send_msg("channel-0"), &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.source_cw721_owner.clone(),
+            test.ics721.clone(),
+            &send_msg("channel-1"),
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::TokenNotOnChannel {
+            class_id: test.source_cw721.to_string(),
+            token_id: token_id.clone(),
+            channel: "channel-1".to_string(),
+        }
+    );
+    test.app
+        .execute_contract(
+            test.source_cw721_owner.clone(),
+            test.ics721.clone(),
+            &send_msg("channel-0"),
+            &[],
+        )
+        .unwrap();
+    let res = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER),
+            test.ics721.clone(),
+            &send_msg("channel-0"),
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("method", "execute_send_metadata_update")
+            .add_attribute("class_id", test.source_cw721.to_string())
+    ));
+
+    // voucher chain: metadata of vouchers is updated
+    let class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    let token = Token {
+        id: TokenId::new("1"),
+        uri: Some("ipfs://old".to_string()),
+        data: None,
+    };
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                create: VoucherCreation {
+                    class: Class {
+                        id: class_id.clone(),
+                        uri: None,
+                        data: None,
+                    },
+                    tokens: vec![token.clone()],
+                },
+                transfer: None,
+            }),
+            &[],
+        )
+        .unwrap();
+    let updated_token = Token {
+        data: Some(to_json_binary("revealed").unwrap()),
+        ..token.clone()
+    };
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::UpdateTokenMetadata {
+                class_id: class_id.clone(),
+                tokens: vec![updated_token.clone()],
+            }),
+            &[],
+        )
+        .unwrap();
+    let token_metadata: Option<Token> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::TokenMetadata {
+                class_id: class_id.to_string(),
+                token_id: "1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(token_metadata, Some(updated_token));
+    // changed token uri is skipped, since cw721-base v0.18 doesn't support UpdateNftInfo
+    test.app
+        .execute_contract(
+            test.ics721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::Callback(CallbackMsg::UpdateTokenMetadata {
+                class_id: class_id.clone(),
+                tokens: vec![Token {
+                    uri: Some("ipfs://new".to_string()),
+                    ..token
+                }],
+            }),
+            &[],
+        )
+        .unwrap();
+    let nft_contract: Addr = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::NftContract {
+                class_id: class_id.to_string(),
+            },
+        )
+        .unwrap();
+    let nft_info: cw721::NftInfoResponse<Empty> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            nft_contract,
+            &cw721::Cw721QueryMsg::NftInfo {
+                token_id: "1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(nft_info.token_uri, Some("ipfs://old".to_string()));
+}
+
+#[test]
+fn test_class_metadata_refresh() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    let class = Class {
+        id: class_id.clone(),
+        uri: Some("ipfs://class".to_string()),
+        data: None,
+    };
+    let create_vouchers = |test: &mut Test, class: &Class, token_id: &str| {
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
+                    receiver: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                    create: VoucherCreation {
+                        class: class.clone(),
+                        tokens: vec![Token {
+                            id: TokenId::new(token_id),
+                            uri: None,
+                            data: None,
+                        }],
+                    },
+                    transfer: None,
+                }),
+                &[],
+            )
+            .unwrap()
+    };
+    let class_updated = |res: &AppResponse| {
+        res.events
+            .iter()
+            .any(|e| e.ty == "wasm-ics721_class_updated")
+    };
+
+    // no update event on creation and for same class
+    let res = create_vouchers(&mut test, &class, "1");
+    assert!(!class_updated(&res));
+    let res = create_vouchers(&mut test, &class, "2");
+    assert!(!class_updated(&res));
+
+    // changed class is stored and emits old and new values
+    let new_class_data = to_json_binary(&CollectionData {
+        owner: None,
+        contract_info: None,
+        name: "renamed".to_string(),
+        symbol: "symbol".to_string(),
+        num_tokens: Some(3),
+    })
+    .unwrap();
+    let new_class = Class {
+        id: class_id.clone(),
+        uri: None,
+        data: Some(new_class_data.clone()),
+    };
+    let res = create_vouchers(&mut test, &new_class, "3");
+    assert!(res.has_event(
+        &Event::new("wasm-ics721_class_updated")
+            .add_attribute("class_id", class_id.to_string())
+            .add_attribute("old_uri", "ipfs://class")
+            .add_attribute("new_uri", "none")
+            .add_attribute("old_data", "none")
+            .add_attribute("new_data", new_class_data.to_base64())
+    ));
+    let class_metadata: Option<Class> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::ClassMetadata {
+                class_id: class_id.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(class_metadata, Some(new_class));
+}
+
+#[test]
+fn test_claimable_receipt() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let receiver = test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN);
+    let config = ClaimableReceiptConfig {
+        enabled: true,
+        expiry: 100,
+    };
+    let disabled_config = ClaimableReceiptConfig {
+        enabled: false,
+        expiry: 0,
+    };
+
+    // channel config takes precedence over global config
+    for (channel_id, config) in [
+        (None, config.clone()),
+        (Some("channel-9".to_string()), disabled_config.clone()),
+    ] {
+        test.app
+            .execute_contract(
+                admin.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::SetClaimableReceiptConfig {
+                    channel_id,
+                    config: Some(config),
+                },
+                &[],
+            )
+            .unwrap();
+    }
+    let query_config = |test: &Test, channel_id: &str| -> Option<ClaimableReceiptConfig> {
+        test.app
+            .wrap()
+            .query_wasm_smart(
+                test.ics721.clone(),
+                &QueryMsg::ClaimableReceiptConfig {
+                    channel_id: Some(channel_id.to_string()),
+                },
+            )
+            .unwrap()
+    };
+    assert_eq!(query_config(&test, CHANNEL_TARGET_CHAIN), Some(config));
+    assert_eq!(query_config(&test, "channel-9"), Some(disabled_config));
+
+    // vouchers held by ICS721, as done by receive packet in claimable receipt mode
+    let class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721,
+        CHANNEL_TARGET_CHAIN,
+        test.app.api().addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+    ));
+    let token_ids = ["1", "2", "3"];
+    let expires_at = test.app.block_info().time.plus_seconds(100);
+    let callbacks = [
+        CallbackMsg::CreateVouchers {
+            receiver: test.ics721.to_string(),
+            create: VoucherCreation {
+                class: Class {
+                    id: class_id.clone(),
+                    uri: None,
+                    data: None,
+                },
+                tokens: token_ids
+                    .iter()
+                    .map(|id| Token {
+                        id: TokenId::new(*id),
+                        uri: None,
+                        data: None,
+                    })
+                    .collect(),
+            },
+            transfer: None,
+        },
+        CallbackMsg::AddIncomingChannelEntries(
+            token_ids
+                .iter()
+                .map(|id| {
+                    (
+                        (class_id.clone(), TokenId::new(*id)),
+                        CHANNEL_TARGET_CHAIN.to_string(),
+                    )
+                })
+                .collect(),
+        ),
+        CallbackMsg::AddPendingClaims(
+            token_ids
+                .iter()
+                .map(|id| {
+                    (
+                        (class_id.clone(), TokenId::new(*id)),
+                        PendingClaim {
+                            receiver: receiver.clone(),
+                            sender: "source-sender".to_string(),
+                            channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                            expires_at,
+                        },
+                    )
+                })
+                .collect(),
+        ),
+    ];
+    for callback in callbacks {
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(callback),
+                &[],
+            )
+            .unwrap();
+    }
+    let query_pending_claims = |test: &Test| -> Vec<String> {
+        test.app
+            .wrap()
+            .query_wasm_smart::<Vec<((ClassId, TokenId), PendingClaim)>>(
+                test.ics721.clone(),
+                &QueryMsg::PendingClaims {
+                    receiver: Some(receiver.to_string()),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+            .into_iter()
+            .map(|((_, token_id), _)| token_id.into())
+            .collect()
+    };
+    assert_eq!(query_pending_claims(&test), vec!["1", "2", "3"]);
+    let (_, nft_contract) = test.query_nft_contracts()[0].clone();
+    let query_owner = |test: &Test, token_id: &str| -> String {
+        test.app
+            .wrap()
+            .query_wasm_smart::<cw721::OwnerOfResponse>(
+                nft_contract.clone(),
+                &Cw721QueryMsg::<Empty>::OwnerOf {
+                    token_id: token_id.to_string(),
+                    include_expired: None,
+                },
+            )
+            .unwrap()
+            .owner
+    };
+    assert_eq!(query_owner(&test, "1"), test.ics721.to_string());
+
+    // only receiver may claim
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            admin,
+            test.ics721.clone(),
+            &ExecuteMsg::ClaimNfts {
+                class_id: class_id.to_string(),
+                token_ids: vec!["1".to_string()],
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    test.app
+        .execute_contract(
+            receiver.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::ClaimNfts {
+                class_id: class_id.to_string(),
+                token_ids: vec!["1".to_string()],
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_owner(&test, "1"), receiver.to_string());
+    assert_eq!(query_pending_claims(&test), vec!["2", "3"]);
+
+    // rejected voucher is sent back on incoming channel, using its default
+    // timeout
+    let reject_msg = ExecuteMsg::RejectNfts {
+        class_id: class_id.to_string(),
+        token_ids: vec!["2".to_string()],
+    };
+    let err: ContractError = test
+        .app
+        .execute_contract(receiver.clone(), test.ics721.clone(), &reject_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::NoTimeout {
+            channel: CHANNEL_TARGET_CHAIN.to_string(),
+        }
+    );
+    test.app
+        .execute_contract(
+            test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER),
+            test.ics721.clone(),
+            &ExecuteMsg::SetChannelTimeoutConfig {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                config: Some(ChannelTimeoutConfig {
+                    default_seconds: Some(60),
+                    max_seconds: Some(60),
+                }),
+            },
+            &[],
+        )
+        .unwrap();
+    // refunds to the source sender aren't checked against the counterparty
+    // address policy
+    test.app
+        .execute_contract(
+            test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER),
+            test.ics721.clone(),
+            &ExecuteMsg::SetCounterpartyAddressPolicy {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                policy: Some(CounterpartyAddressPolicy {
+                    prefix: "juno".to_string(),
+                    address_lengths: vec![20],
+                }),
+            },
+            &[],
+        )
+        .unwrap();
+    // ICS721 is the sender, so the claim is pending again in case return fails
+    let res = test
+        .app
+        .execute_contract(receiver.clone(), test.ics721.clone(), &reject_msg, &[])
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-ics721_send").add_attribute("sender", test.ics721.to_string())
+    ));
+    assert_eq!(query_pending_claims(&test), vec!["3"]);
+    assert_eq!(
+        test.query_outgoing_channels(),
+        vec![(
+            (class_id.to_string(), "2".to_string()),
+            CHANNEL_TARGET_CHAIN.to_string()
+        )]
+    );
+
+    // anyone may return expired claims
+    let anyone = test.app.api().addr_make("anyone");
+    let return_msg = ExecuteMsg::ReturnExpiredClaims {
+        class_id: class_id.to_string(),
+        token_ids: vec!["3".to_string()],
+    };
+    let err: ContractError = test
+        .app
+        .execute_contract(anyone.clone(), test.ics721.clone(), &return_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::ClaimNotExpired {
+            class_id: class_id.to_string(),
+            token_id: "3".to_string(),
+            expires_at,
+        }
+    );
+    test.app.update_block(|block| {
+        block.time = block.time.plus_seconds(100);
+    });
+    test.app
+        .execute_contract(anyone, test.ics721.clone(), &return_msg, &[])
+        .unwrap();
+    assert!(query_pending_claims(&test).is_empty());
+}
+
+#[test]
+fn test_park_on_failure() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let anyone = test.app.api().addr_make("anyone");
+
+    // only admin may enable park on failure mode
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            anyone.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::SetParkOnFailure { enabled: true },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    test.app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::SetParkOnFailure { enabled: true },
+            &[],
+        )
+        .unwrap();
+    let park_on_failure: bool = test
+        .app
+        .wrap()
+        .query_wasm_smart(test.ics721.clone(), &QueryMsg::ParkOnFailure {})
+        .unwrap();
+    assert!(park_on_failure);
+
+    // vouchers held in ICS721 custody, as done by receive packet on failure
+    let source_class_id = test
+        .app
+        .api()
+        .addr_make(COLLECTION_CONTRACT_SOURCE_CHAIN)
+        .to_string();
+    let class_id = ClassId::new(format!(
+        "wasm.{}/{}/{}",
+        test.ics721, CHANNEL_TARGET_CHAIN, source_class_id
+    ));
+    // the sender resolves using its account on this chain
+    let wallet_data = [1u8; 20].to_base32();
+    let source_sender = encode("juno", wallet_data.clone(), Variant::Bech32).unwrap();
+    let sender = Addr::unchecked(encode(BECH32_PREFIX_HRP, wallet_data, Variant::Bech32).unwrap());
+    let parked_transfer = |token_id: &str| ParkedTransfer {
+        data: NonFungibleTokenPacketData {
+            class_id: ClassId::new(source_class_id.clone()),
+            class_uri: None,
+            class_data: None,
+            token_ids: vec![TokenId::new(token_id)],
+            token_uris: None,
+            token_data: None,
+            sender: source_sender.clone(),
+            receiver: "invalid-receiver".to_string(),
+            memo: None,
+        },
+        class_id: class_id.clone(),
+        reason: "Generic error: Invalid input".to_string(),
+    };
+    let token_ids = ["1", "2"];
+    let mut callbacks = vec![
+        CallbackMsg::CreateVouchers {
+            receiver: test.ics721.to_string(),
+            create: VoucherCreation {
+                class: Class {
+                    id: class_id.clone(),
+                    uri: None,
+                    data: None,
+                },
+                tokens: token_ids
+                    .iter()
+                    .map(|id| Token {
+                        id: TokenId::new(*id),
+                        uri: None,
+                        data: None,
+                    })
+                    .collect(),
+            },
+            transfer: None,
+        },
+        CallbackMsg::AddIncomingChannelEntries(
+            token_ids
+                .iter()
+                .map(|id| {
+                    (
+                        (class_id.clone(), TokenId::new(*id)),
+                        CHANNEL_TARGET_CHAIN.to_string(),
+                    )
+                })
+                .collect(),
+        ),
+    ];
+    for (sequence, token_id) in token_ids.iter().enumerate() {
+        callbacks.push(CallbackMsg::ParkTransfer {
+            channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+            sequence: sequence as u64 + 1,
+            transfer: parked_transfer(token_id),
+        });
+    }
+    for callback in callbacks {
+        test.app
+            .execute_contract(
+                test.ics721.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::Callback(callback),
+                &[],
+            )
+            .unwrap();
+    }
+    let parked_transfers: Vec<((String, u64), ParkedTransfer)> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::ParkedTransfers {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        parked_transfers,
+        vec![
+            ((CHANNEL_TARGET_CHAIN.to_string(), 1), parked_transfer("1")),
+            ((CHANNEL_TARGET_CHAIN.to_string(), 2), parked_transfer("2")),
+        ]
+    );
+
+    // only admin or sender may resolve
+    let receiver = test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN);
+    let deliver_msg = ExecuteMsg::ResolveParkedTransfer {
+        channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+        sequence: 1,
+        action: ParkedTransferAction::Deliver {
+            receiver: receiver.to_string(),
+        },
+    };
+    let err: ContractError = test
+        .app
+        .execute_contract(anyone, test.ics721.clone(), &deliver_msg, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    test.app
+        .execute_contract(sender, test.ics721.clone(), &deliver_msg, &[])
+        .unwrap();
+    let (_, nft_contract) = test.query_nft_contracts()[0].clone();
+    let owner: cw721::OwnerOfResponse = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            nft_contract,
+            &Cw721QueryMsg::<Empty>::OwnerOf {
+                token_id: "1".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(owner.owner, receiver.to_string());
+    let parked_transfer: Option<ParkedTransfer> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::ParkedTransfer {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                sequence: 1,
+            },
+        )
+        .unwrap();
+    assert_eq!(parked_transfer, None);
+
+    // returned voucher is sent back on the channel it has been parked on,
+    // with ICS721 as sender, so it is parked again in case return fails
+    let res = test
+        .app
+        .execute_contract(
+            admin,
+            test.ics721.clone(),
+            &ExecuteMsg::ResolveParkedTransfer {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                sequence: 2,
+                action: ParkedTransferAction::Return {
+                    timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                        revision: 0,
+                        height: 10,
+                    }),
+                },
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-ics721_send").add_attribute("sender", test.ics721.to_string())
+    ));
+    assert_eq!(
+        test.query_outgoing_channels(),
+        vec![(
+            (class_id.to_string(), "2".to_string()),
+            CHANNEL_TARGET_CHAIN.to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_receiver_translation() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+
+    // only admin may opt in a channel
+    let opt_in_msg = ExecuteMsg::SetReceiverTranslation {
+        channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+        enabled: true,
+    };
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("anyone"),
+            test.ics721.clone(),
+            &opt_in_msg,
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    test.app
+        .execute_contract(admin, test.ics721.clone(), &opt_in_msg, &[])
+        .unwrap();
+    let enabled: bool = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::ReceiverTranslation {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+            },
+        )
+        .unwrap();
+    assert!(enabled);
+
+    // receive packet using bech32 addresses of this chain
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApiBech32::new(BECH32_PREFIX_HRP),
+        querier: MockQuerier::new(&[]),
+        custom_query_type: PhantomData::<Empty>,
+    };
+    let mut env = mock_env();
+    env.contract.address = test.ics721.clone();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    let local_class_id = ClassId::new(format!("wasm.{}/{}/id", test.ics721, CHANNEL_TARGET_CHAIN));
+    CLASS_ID_AND_NFT_CONTRACT_INFO
+        .save(
+            &mut deps.storage,
+            &local_class_id,
+            &ClassIdInfo {
+                class_id: local_class_id.clone(),
+                address: test.app.api().addr_make("voucher-collection"),
+            },
+        )
+        .unwrap();
+    let wallet_data = [1u8; 20].to_base32();
+    let source_receiver = encode("juno", wallet_data.clone(), Variant::Bech32).unwrap();
+    let receiver = encode(BECH32_PREFIX_HRP, wallet_data, Variant::Bech32).unwrap();
+    let source_contract = encode("juno", [2u8; 32].to_base32(), Variant::Bech32).unwrap();
+    let receive_packet = |deps: DepsMut, receiver: &str| {
+        let data = NonFungibleTokenPacketData {
+            class_id: ClassId::new("id"),
+            class_uri: None,
+            class_data: None,
+            token_ids: vec![TokenId::new("1")],
+            token_uris: None,
+            token_data: None,
+            sender: source_receiver.clone(),
+            receiver: receiver.to_string(),
+            memo: None,
+        };
+        let packet = IbcPacket::new(
+            to_json_binary(&data).unwrap(),
+            IbcEndpoint {
+                port_id: "wasm.source".to_string(),
+                channel_id: "channel-0".to_string(),
+            },
+            IbcEndpoint {
+                port_id: format!("wasm.{}", test.ics721),
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+            },
+            1,
+            IbcTimeout::with_block(IbcTimeoutBlock {
+                revision: 0,
+                height: 10,
+            }),
+        );
+        Ics721Contract::default()
+            .ibc_packet_receive(
+                deps,
+                env.clone(),
+                IbcPacketReceiveMsg::new(packet, Addr::unchecked("relayer")),
+            )
+            .unwrap()
+    };
+
+    // channel isn't opted in
+    let res = receive_packet(deps.as_mut(), &source_receiver);
+    assert!(try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).is_some());
+
+    // wallet address is translated
+    RECEIVER_TRANSLATION_CHANNELS
+        .save(&mut deps.storage, CHANNEL_TARGET_CHAIN, &Empty {})
+        .unwrap();
+    let res = receive_packet(deps.as_mut(), &source_receiver);
+    assert_eq!(
+        res.events,
+        vec![Event::new("ics721_receiver_translated")
+            .add_attribute("channel_id", CHANNEL_TARGET_CHAIN)
+            .add_attribute("original_receiver", source_receiver.clone())
+            .add_attribute("receiver", receiver.clone())]
+    );
+    let create_vouchers_receiver = match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_json(msg).unwrap() {
+            ExecuteMsg::Callback(CallbackMsg::Conjunction { operands }) => match &operands[0] {
+                WasmMsg::Execute { msg, .. } => match from_json(msg).unwrap() {
+                    ExecuteMsg::Callback(CallbackMsg::CreateVouchers { receiver, .. }) => receiver,
+                    _ => panic!("unexpected execute msg"),
+                },
+                _ => panic!("unexpected wasm msg"),
+            },
+            _ => panic!("unexpected execute msg"),
+        },
+        _ => panic!("unexpected cosmos msg"),
+    };
+    assert_eq!(create_vouchers_receiver, receiver);
+
+    // contract addresses and invalid addresses are never translated
+    for invalid_receiver in [source_contract.as_str(), "juno1invalid"] {
+        let res = receive_packet(deps.as_mut(), invalid_receiver);
+        assert!(try_get_ack_error(&IbcAcknowledgement::new(res.acknowledgement)).is_some());
+    }
+    assert!(translate_receiver(deps.as_ref(), &env, &source_contract)
+        .unwrap_err()
+        .to_string()
+        .contains("is not a wallet address"));
+    assert!(convert_owner_chain_address(&env, "juno1invalid").is_err());
+}
+
+#[test]
+fn test_counterparty_address_policy() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let policy = CounterpartyAddressPolicy {
+        prefix: "juno".to_string(),
+        address_lengths: vec![20],
+    };
+    let set_policy_msg = ExecuteMsg::SetCounterpartyAddressPolicy {
+        channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+        policy: Some(policy.clone()),
+    };
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("anyone"),
+            test.ics721.clone(),
+            &set_policy_msg,
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    test.app
+        .execute_contract(admin, test.ics721.clone(), &set_policy_msg, &[])
+        .unwrap();
+    let queried_policy: Option<CounterpartyAddressPolicy> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::CounterpartyAddressPolicy {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(queried_policy, Some(policy));
+
+    // simplify: mint and escrowed/owned by ics721, as a precondition for receive nft
+    let token_id = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+    let other_token_id = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+    let receive_nft = |test: &mut Test, token_id: &str, channel_id: &str, receiver: String| {
+        test.app.execute_contract(
+            test.source_cw721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                sender: test.source_cw721_owner.to_string(),
+                token_id: token_id.to_string(),
+                msg: to_json_binary(&IbcOutgoingMsg {
+                    receiver,
+                    channel_id: channel_id.to_string(),
+                    timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
+                        revision: 0,
+                        height: 10,
+                    })),
+                    relative_timeout_seconds: None,
+                    memo: None,
+                })
+                .unwrap(),
+            }),
+            &[],
+        )
+    };
+
+    // wrong prefix, wrong length and invalid addresses are rejected
+    for receiver in [
+        encode("osmo", [1u8; 20].to_base32(), Variant::Bech32).unwrap(),
+        encode("juno", [1u8; 32].to_base32(), Variant::Bech32).unwrap(),
+        NFT_OWNER_TARGET_CHAIN.to_string(),
+    ] {
+        let err: ContractError = receive_nft(&mut test, &token_id, CHANNEL_TARGET_CHAIN, receiver)
+            .unwrap_err()
+            .downcast()
+            .unwrap();
+        assert_eq!(
+            err,
+            ContractError::InvalidReceiver {
+                channel: CHANNEL_TARGET_CHAIN.to_string(),
+                expected_prefix: "juno".to_string(),
+            }
+        );
+    }
+
+    // channels without policy accept any receiver
+    receive_nft(
+        &mut test,
+        &other_token_id,
+        "channel-0",
+        NFT_OWNER_TARGET_CHAIN.to_string(),
+    )
+    .unwrap();
+    let receiver = encode("juno", [1u8; 20].to_base32(), Variant::Bech32).unwrap();
+    receive_nft(&mut test, &token_id, CHANNEL_TARGET_CHAIN, receiver).unwrap();
+}
+
+#[test]
+fn test_channel_timeout_config() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let config = ChannelTimeoutConfig {
+        default_seconds: Some(600),
+        max_seconds: Some(3600),
+    };
+    let set_config_msg = ExecuteMsg::SetChannelTimeoutConfig {
+        channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+        config: Some(config.clone()),
+    };
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("anyone"),
+            test.ics721.clone(),
+            &set_config_msg,
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    test.app
+        .execute_contract(admin, test.ics721.clone(), &set_config_msg, &[])
+        .unwrap();
+    let queried_config: Option<ChannelTimeoutConfig> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::ChannelTimeoutConfig {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(queried_config, Some(config));
+
+    // simplify: mint and escrowed/owned by ics721, as a precondition for receive nft
+    let token_id = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+    let receive_nft = |test: &mut Test,
+                       channel_id: &str,
+                       timeout: Option<IbcTimeout>,
+                       relative_timeout_seconds: Option<u64>| {
+        test.app.execute_contract(
+            test.source_cw721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                sender: test.source_cw721_owner.to_string(),
+                token_id: token_id.clone(),
+                msg: to_json_binary(&IbcOutgoingMsg {
+                    receiver: NFT_OWNER_TARGET_CHAIN.to_string(),
+                    channel_id: channel_id.to_string(),
+                    timeout,
+                    relative_timeout_seconds,
+                    memo: None,
+                })
+                .unwrap(),
+            }),
+            &[],
+        )
+    };
+
+    // channels without default require a timeout
+    let err: ContractError = receive_nft(&mut test, "channel-0", None, None)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::NoTimeout {
+            channel: "channel-0".to_string()
+        }
+    );
+
+    // timeouts beyond max and block timeouts are rejected
+    let block_timeout = IbcTimeout::with_block(IbcTimeoutBlock {
+        revision: 0,
+        height: 10,
+    });
+    for (timeout, relative_timeout_seconds) in [(None, Some(3601)), (Some(block_timeout), None)] {
+        let err: ContractError = receive_nft(
+            &mut test,
+            CHANNEL_TARGET_CHAIN,
+            timeout,
+            relative_timeout_seconds,
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+        assert_eq!(
+            err,
+            ContractError::TimeoutTooFar {
+                channel: CHANNEL_TARGET_CHAIN.to_string(),
+                max_seconds: 3600,
+            }
+        );
+    }
+
+    // default and relative timeouts are filled in
+    receive_nft(&mut test, CHANNEL_TARGET_CHAIN, None, None).unwrap();
+    receive_nft(&mut test, CHANNEL_TARGET_CHAIN, None, Some(3600)).unwrap();
+}
+
+fn hook_subscriber_contract() -> Box<dyn Contract<Empty>> {
+    const HOOKS: cw_storage_plus::Item<Vec<Ics721HookMsg>> = cw_storage_plus::Item::new("hooks");
+    fn execute(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: ReceiverExecuteMsg,
+    ) -> StdResult<Response> {
+        match msg {
+            ReceiverExecuteMsg::Ics721Hook(hook) => {
+                let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+                hooks.push(hook);
+                HOOKS.save(deps.storage, &hooks)?;
+                Ok(Response::default())
+            }
+            _ => Err(StdError::generic_err("unexpected msg")),
+        }
+    }
+    fn instantiate(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Empty,
+    ) -> StdResult<Response> {
+        Ok(Response::default())
+    }
+    fn query(deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+        to_json_binary(&HOOKS.may_load(deps.storage)?.unwrap_or_default())
+    }
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
+
+fn failing_hook_subscriber_contract() -> Box<dyn Contract<Empty>> {
+    fn execute(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: ReceiverExecuteMsg,
+    ) -> StdResult<Response> {
+        Err(StdError::generic_err("hook failed"))
+    }
+    fn instantiate(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Empty,
+    ) -> StdResult<Response> {
+        Ok(Response::default())
+    }
+    fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+        to_json_binary(&Empty {})
+    }
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
+
+#[test]
+fn test_hook_subscribers() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let mut subscribers = vec![];
+    for contract in [
+        hook_subscriber_contract(),
+        hook_subscriber_contract(),
+        failing_hook_subscriber_contract(),
+    ] {
+        let code_id = test.app.store_code(contract);
+        subscribers.push(
+            test.app
+                .instantiate_contract(code_id, admin.clone(), &Empty {}, &[], "hook", None)
+                .unwrap(),
+        );
+    }
+    let (analytics, other_channel, failing) = (
+        subscribers[0].clone(),
+        subscribers[1].clone(),
+        subscribers[2].clone(),
+    );
+
+    // only admin can manage subscribers
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("anyone"),
+            test.ics721.clone(),
+            &ExecuteMsg::SetHookSubscriber {
+                addr: analytics.to_string(),
+                subscription: Some(HookSubscription::default()),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let other_channel_subscription = HookSubscription {
+        class_ids: vec![],
+        channel_ids: vec!["channel-9".to_string()],
+    };
+    for (addr, subscription) in [
+        (analytics.clone(), HookSubscription::default()),
+        (other_channel.clone(), other_channel_subscription.clone()),
+        (failing.clone(), HookSubscription::default()),
+    ] {
+        test.app
+            .execute_contract(
+                admin.clone(),
+                test.ics721.clone(),
+                &ExecuteMsg::SetHookSubscriber {
+                    addr: addr.to_string(),
+                    subscription: Some(subscription),
+                },
+                &[],
+            )
+            .unwrap();
+    }
+    let query_subscribers = |test: &Test| -> Vec<(Addr, HookSubscription)> {
+        test.app
+            .wrap()
+            .query_wasm_smart(
+                test.ics721.clone(),
+                &QueryMsg::HookSubscribers {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+    };
+    let mut expected_subscribers = vec![
+        (analytics.clone(), HookSubscription::default()),
+        (other_channel.clone(), other_channel_subscription),
+        (failing.clone(), HookSubscription::default()),
+    ];
+    expected_subscribers.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(query_subscribers(&test), expected_subscribers);
+
+    // escrow is broadcasted, a failing hook doesn't block the transfer
+    let token_id = test.execute_cw721_mint(test.ics721.clone()).unwrap();
+    let res = test
+        .app
+        .execute_contract(
+            test.source_cw721.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                sender: test.source_cw721_owner.to_string(),
+                token_id: token_id.clone(),
+                msg: to_json_binary(&IbcOutgoingMsg {
+                    receiver: NFT_OWNER_TARGET_CHAIN.to_string(),
+                    channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                    timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
+                        revision: 0,
+                        height: 10,
+                    })),
+                    relative_timeout_seconds: None,
+                    memo: None,
+                })
+                .unwrap(),
+            }),
+            &[],
+        )
+        .unwrap();
+    assert!(res.events.iter().any(|event| event
+        .attributes
+        .iter()
+        .any(|attribute| attribute.key == "hook_error")));
+    let query_hooks = |test: &Test, subscriber: &Addr| -> Vec<Ics721HookMsg> {
+        test.app
+            .wrap()
+            .query_wasm_smart(subscriber.clone(), &Empty {})
+            .unwrap()
+    };
+    assert_eq!(
+        query_hooks(&test, &analytics),
+        vec![Ics721HookMsg::Escrow {
+            transfer: Ics721HookTransfer {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                class_id: ClassId::new(test.source_cw721.to_string()),
+                nft_contract: test.source_cw721.to_string(),
+                token_ids: vec![TokenId::new(token_id)],
+                sender: test.source_cw721_owner.to_string(),
+                receiver: NFT_OWNER_TARGET_CHAIN.to_string(),
+            },
+        }]
+    );
+    // subscriber of another channel isn't notified
+    assert_eq!(query_hooks(&test, &other_channel), vec![]);
+
+    // removed subscribers aren't listed anymore
+    test.app
+        .execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::SetHookSubscriber {
+                addr: failing.to_string(),
+                subscription: None,
+            },
+            &[],
+        )
+        .unwrap();
+    expected_subscribers.retain(|(addr, _)| addr != &failing);
+    assert_eq!(query_subscribers(&test), expected_subscribers);
+
+    // number of subscribers is capped, existing ones can still be updated
+    let set_subscriber = |test: &mut Test, addr: &Addr| {
+        test.app.execute_contract(
+            admin.clone(),
+            test.ics721.clone(),
+            &ExecuteMsg::SetHookSubscriber {
+                addr: addr.to_string(),
+                subscription: Some(HookSubscription::default()),
+            },
+            &[],
+        )
+    };
+    for index in 0..8 {
+        let addr = test.app.api().addr_make(&format!("subscriber-{index}"));
+        set_subscriber(&mut test, &addr).unwrap();
+    }
+    let addr = test.app.api().addr_make("subscriber-8");
+    let err: ContractError = set_subscriber(&mut test, &addr)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::TooManyHookSubscribers { max: 10 });
+    set_subscriber(&mut test, &other_channel).unwrap();
+}
+
+#[test]
+fn test_simulate_receive() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let receiver = test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN);
+    let src = IbcEndpoint {
+        port_id: "wasm.source".to_string(),
+        channel_id: "channel-0".to_string(),
+    };
+    let dest = IbcEndpoint {
+        port_id: format!("wasm.{}", test.ics721),
+        channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+    };
+    let packet_data = |receiver: &str| NonFungibleTokenPacketData {
+        class_id: ClassId::new("id"),
+        class_uri: None,
+        class_data: None,
+        token_ids: vec![TokenId::new("1")],
+        token_uris: None,
+        token_data: None,
+        sender: "source-sender".to_string(),
+        receiver: receiver.to_string(),
+        memo: None,
+    };
+    let simulate = |test: &Test, packet_data: NonFungibleTokenPacketData| {
+        test.app
+            .wrap()
+            .query_wasm_smart::<SimulateReceiveResponse>(
+                test.ics721.clone(),
+                &QueryMsg::SimulateReceive {
+                    packet_data,
+                    src: src.clone(),
+                    dest: dest.clone(),
+                },
+            )
+            .unwrap()
+    };
+
+    // first transfer of a class instantiates the voucher collection
+    let local_class_id = ClassId::new(format!("wasm.{}/{}/id", test.ics721, CHANNEL_TARGET_CHAIN));
+    let nft_contract: Addr = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::GetInstantiate2NftContract {
+                class_id: local_class_id.to_string(),
+                cw721_code_id: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        simulate(&test, packet_data(receiver.as_str())),
+        SimulateReceiveResponse {
+            plan: Some(ReceivePlan {
+                local_class_id,
+                is_redemption: false,
+                nft_contract,
+                instantiates_nft_contract: true,
+                receiver: receiver.to_string(),
+                receiver_translated: false,
+                is_claimable: false,
+                parked_reason: None,
+                incoming_proxy: None,
+                receive_callback: None,
+                hook_subscribers: vec![],
+            }),
+            error: None,
+        }
+    );
+
+    // invalid receiver is rejected, same as receiving the packet
+    let response = simulate(&test, packet_data("invalid"));
+    assert_eq!(response.plan, None);
+    assert!(response.error.is_some());
+
+    // no state changes by simulation
+    let nft_contracts: Vec<(String, Addr)> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::NftContracts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(nft_contracts, vec![]);
+}
+
+#[test]
+fn test_preview_send() {
+    let mut test = Test::new(
+        false,
+        false,
+        None,
+        Some(ICS721_ADMIN_AND_PAUSER.to_string()),
+        cw721_base_contract(),
+        true,
+    );
+    let admin = test.app.api().addr_make(ICS721_ADMIN_AND_PAUSER);
+    let owner = test.app.api().addr_make(COLLECTION_OWNER_SOURCE_CHAIN);
+    let token_id = test.execute_cw721_mint(owner.clone()).unwrap();
+    let receiver = encode("juno", [1u8; 20].to_base32(), Variant::Bech32).unwrap();
+    test.app
+        .execute_contract(
+            admin,
+            test.ics721.clone(),
+            &ExecuteMsg::SetCounterpartyAddressPolicy {
+                channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                policy: Some(CounterpartyAddressPolicy {
+                    prefix: "juno".to_string(),
+                    address_lengths: vec![20],
+                }),
+            },
+            &[],
+        )
+        .unwrap();
+    let preview_with_timeout =
+        |test: &Test, token_ids: Vec<String>, receiver: &str, relative_timeout: Option<u64>| {
+            test.app
+                .wrap()
+                .query_wasm_smart::<PreviewSendResponse>(
+                    test.ics721.clone(),
+                    &QueryMsg::PreviewSend {
+                        collection: test.source_cw721.to_string(),
+                        token_ids,
+                        receiver: receiver.to_string(),
+                        channel_id: CHANNEL_TARGET_CHAIN.to_string(),
+                        timeout: None,
+                        relative_timeout_seconds: relative_timeout,
+                        memo: Some("memo".to_string()),
+                    },
+                )
+                .unwrap()
+        };
+    let preview = |test: &Test, token_ids: Vec<String>, receiver: &str| {
+        preview_with_timeout(test, token_ids, receiver, Some(60))
+    };
+
+    // native NFT is previewed with its owner as sender
+    let response = preview(&test, vec![token_id.clone()], &receiver);
+    assert_eq!(response.error, None);
+    assert_eq!(response.packets.len(), 1);
+    let OutgoingPacketPreview {
+        packet,
+        is_returning_voucher,
+    } = response.packets[0].clone();
+    assert!(!is_returning_voucher);
+    assert_eq!(packet.class_id, ClassId::new(test.source_cw721.to_string()));
+    assert!(packet.class_data.is_some());
+    assert_eq!(packet.token_ids, vec![TokenId::new(token_id.clone())]);
+    assert_eq!(packet.sender, owner.to_string());
+    assert_eq!(packet.receiver, receiver);
+    assert_eq!(packet.memo, Some("memo".to_string()));
+
+    // receiver not matching counterparty address policy is rejected
+    let response = preview(&test, vec![token_id.clone()], NFT_OWNER_TARGET_CHAIN);
+    assert_eq!(response.packets, vec![]);
+    assert_eq!(
+        response.error,
+        Some(
+            ContractError::InvalidReceiver {
+                channel: CHANNEL_TARGET_CHAIN.to_string(),
+                expected_prefix: "juno".to_string(),
+            }
+            .to_string()
+        )
+    );
+
+    // unknown token is rejected
+    let response = preview(&test, vec!["unknown".to_string()], &receiver);
+    assert_eq!(response.packets, vec![]);
+    assert!(response.error.is_some());
+
+    // without timeout and channel default, sending fails
+    let response = preview_with_timeout(&test, vec![token_id.clone()], &receiver, None);
+    assert_eq!(response.packets, vec![]);
+    assert_eq!(
+        response.error,
+        Some(
+            ContractError::NoTimeout {
+                channel: CHANNEL_TARGET_CHAIN.to_string(),
+            }
+            .to_string()
+        )
+    );
+
+    // no state changes by preview
+    let class_id: Option<ClassId> = test
+        .app
+        .wrap()
+        .query_wasm_smart(
+            test.ics721.clone(),
+            &QueryMsg::ClassId {
+                contract: test.source_cw721.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(class_id, None);
+
+    // with outgoing proxy, sending directly from the collection fails
+    let test = Test::new(true, false, None, None, cw721_base_contract(), true);
+    let response = preview(&test, vec![token_id], &receiver);
+    assert_eq!(response.packets, vec![]);
+    assert_eq!(
+        response.error,
+        Some(ContractError::Unauthorized {}.to_string())
+    );
+}
+
+/// In case proxy for ICS721 is defined, ICS721 only accepts receival from proxy - not from nft contract!
+#[test]
+fn test_no_receive_with_proxy() {
+    let mut test = Test::new(true, false, None, None, cw721_base_contract(), true);
+    // unauthorized to receive nft from nft contract
+    let err: ContractError = test
+        .app
+        .execute_contract(
+            test.app.api().addr_make("cw721"),
+            test.ics721,
+            &ExecuteMsg::ReceiveNft(cw721::Cw721ReceiveMsg {
+                sender: test.app.api().addr_make(NFT_OWNER_TARGET_CHAIN).to_string(),
+                token_id: "1".to_string(),
+                msg: to_json_binary(&IbcOutgoingMsg {
                     receiver: NFT_OWNER_TARGET_CHAIN.to_string(), // nft owner for other chain, on this chain ics721 is owner
                     channel_id: "channel-0".to_string(),
-                    timeout: IbcTimeout::with_block(IbcTimeoutBlock {
+                    timeout: Some(IbcTimeout::with_block(IbcTimeoutBlock {
                         revision: 0,
                         height: 10,
-                    }),
+                    })),
+                    relative_timeout_seconds: None,
                     memo: None,
                 })
                 .unwrap(),
@@ -1,3 +1,4 @@
 mod contract;
 mod ibc_tests;
 pub mod integration_tests;
+mod proptest_tests;
@@ -0,0 +1,191 @@
+//! Property based tests of the packet receive path. Packet data is
+//! fully untrusted, it comes from any counterparty.
+
+use cosmwasm_std::{
+    from_json,
+    testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage},
+    to_json_binary, Addr, Binary, CodeInfoResponse, ContractResult, HexBinary, IbcEndpoint,
+    IbcPacket, IbcPacketReceiveMsg, IbcTimeout, Order, OwnedDeps, Storage, SystemError,
+    SystemResult, Timestamp, WasmQuery,
+};
+use ics721_types::{
+    ack::{AckResult, Ics721Ack},
+    ibc_types::{Ics721ControlPacketData, NonFungibleTokenPacketData},
+    token_types::{ClassId, TokenId},
+};
+use proptest::prelude::*;
+
+use crate::{
+    ibc::Ics721Ibc,
+    ibc_helpers::{get_endpoint_prefix, try_pop_source_prefix},
+    state::{CW721_CODE_ID, PO},
+};
+
+use super::contract::Ics721Contract;
+
+fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    CW721_CODE_ID.save(&mut deps.storage, &0).unwrap();
+    PO.set_pauser(&mut deps.storage, &deps.api, None).unwrap();
+    // code info is needed for instantiate2 voucher addresses
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::CodeInfo { code_id } => SystemResult::Ok(ContractResult::Ok(
+            to_json_binary(&CodeInfoResponse::new(
+                *code_id,
+                "creator".to_string(),
+                HexBinary::from(vec![1u8; 32]),
+            ))
+            .unwrap(),
+        )),
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+    deps
+}
+
+fn endpoint() -> impl Strategy<Value = IbcEndpoint> {
+    (
+        prop_oneof!["wasm\\.[a-z0-9]{1,16}", "\\PC{0,16}"],
+        prop_oneof!["channel-[0-9]{1,3}", "\\PC{0,16}"],
+    )
+        .prop_map(|(port_id, channel_id)| IbcEndpoint {
+            port_id,
+            channel_id,
+        })
+}
+
+/// Class IDs with slashes, unicode and nested prefixes.
+fn class_id() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z0-9]{0,16}",
+        "[a-z0-9./-]{0,32}",
+        "(wasm\\.[a-z0-9]{1,8}/channel-[0-9]/){0,3}[a-z0-9]{0,8}",
+        "\\PC{0,32}",
+    ]
+}
+
+/// Binaries, occasionally enormous.
+fn binary() -> impl Strategy<Value = Binary> {
+    prop_oneof![
+        9 => prop::collection::vec(any::<u8>(), 0..64),
+        1 => prop::collection::vec(any::<u8>(), 0..65536),
+    ]
+    .prop_map(Binary::from)
+}
+
+/// Packet data with duplicate token IDs and mismatched optional arrays.
+fn packet_data() -> impl Strategy<Value = NonFungibleTokenPacketData> {
+    (
+        class_id(),
+        prop::option::of("\\PC{0,16}"),
+        prop::option::of(binary()),
+        prop::collection::vec(prop_oneof!["[0-9]{0,2}", "\\PC{0,8}"], 0..8),
+        prop::option::of(prop::collection::vec("\\PC{0,16}", 0..8)),
+        prop::option::of(prop::collection::vec(binary(), 0..8)),
+        "\\PC{0,16}",
+        prop_oneof!["[a-z0-9]{3,20}", "\\PC{0,16}"],
+        prop::option::of("\\PC{0,32}"),
+    )
+        .prop_map(
+            |(
+                class_id,
+                class_uri,
+                class_data,
+                token_ids,
+                token_uris,
+                token_data,
+                sender,
+                receiver,
+                memo,
+            )| NonFungibleTokenPacketData {
+                class_id: ClassId::new(class_id),
+                class_uri,
+                class_data,
+                token_ids: token_ids.into_iter().map(TokenId::new).collect(),
+                token_uris,
+                token_data,
+                sender,
+                receiver,
+                memo,
+            },
+        )
+}
+
+fn snapshot(storage: &dyn Storage) -> Vec<(Vec<u8>, Vec<u8>)> {
+    storage.range(None, None, Order::Ascending).collect()
+}
+
+/// Receives a packet and asserts it never panics, that errors are acked
+/// with an ICS721 error ack (leaving state untouched), and that the ack
+/// of transfers is left to the submessage reply on success.
+fn receive_packet(data: Binary, src: IbcEndpoint, dest: IbcEndpoint) -> Result<(), TestCaseError> {
+    let mut deps = setup();
+    let before = snapshot(&deps.storage);
+    let packet = IbcPacket::new(
+        data.clone(),
+        src,
+        dest,
+        1,
+        IbcTimeout::with_timestamp(Timestamp::from_seconds(42)),
+    );
+    let res = Ics721Contract::default()
+        .ibc_packet_receive(
+            deps.as_mut(),
+            mock_env(),
+            IbcPacketReceiveMsg::new(packet, Addr::unchecked("relayer")),
+        )
+        .unwrap();
+
+    match Ics721Ack::decode(&res.acknowledgement) {
+        AckResult::Error(_) => {
+            prop_assert!(res.messages.is_empty());
+            prop_assert_eq!(snapshot(&deps.storage), before);
+        }
+        // control packets are acked right away
+        _ if res.messages.is_empty() => {
+            prop_assert!(from_json::<Ics721ControlPacketData>(&data).is_ok());
+        }
+        // only valid transfers are processed, acked by the reply
+        _ => {
+            prop_assert!(res.acknowledgement.is_empty());
+            let data: NonFungibleTokenPacketData = from_json(&data).unwrap();
+            prop_assert!(data.validate().is_ok());
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_receive_packet_data(
+        data in packet_data(),
+        src in endpoint(),
+        dest in endpoint(),
+        returning in any::<bool>(),
+    ) {
+        let mut data = data;
+        if returning {
+            data.class_id = ClassId::new(format!("{}{}", get_endpoint_prefix(&src), data.class_id));
+        }
+        receive_packet(to_json_binary(&data).unwrap(), src, dest)?;
+    }
+
+    #[test]
+    fn test_receive_arbitrary_bytes(
+        data in prop::collection::vec(any::<u8>(), 0..1024),
+        src in endpoint(),
+        dest in endpoint(),
+    ) {
+        receive_packet(Binary::from(data), src, dest)?;
+    }
+
+    #[test]
+    fn test_class_id_prefix_round_trip(endpoint in endpoint(), class_id in class_id()) {
+        let prefixed = format!("{}{}", get_endpoint_prefix(&endpoint), class_id);
+        prop_assert_eq!(
+            try_pop_source_prefix(&endpoint, &prefixed),
+            Some(class_id.as_str())
+        );
+    }
+}
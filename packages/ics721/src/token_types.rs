@@ -1,6 +1,9 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{to_json_binary, Addr, StdResult, WasmMsg};
-use ics721_types::token_types::{Class, Token, TokenId};
+use ics721_types::{
+    token_types::{Class, Token, TokenId},
+    types::Ics721HookTransfer,
+};
 
 use crate::msg::{CallbackMsg, ExecuteMsg};
 
@@ -30,12 +33,19 @@ impl VoucherRedemption {
     ///   vouchers are being redeemed on.
     /// - `receiver` that address that ought to receive the NFTs the
     ///   debt-vouchers are redeemable for.
-    pub(crate) fn into_wasm_msg(self, contract: Addr, receiver: String) -> StdResult<WasmMsg> {
+    /// - `transfer` the delivered transfer, emitted as receive event.
+    pub(crate) fn into_wasm_msg(
+        self,
+        contract: Addr,
+        receiver: String,
+        transfer: Option<Ics721HookTransfer>,
+    ) -> StdResult<WasmMsg> {
         Ok(WasmMsg::Execute {
             contract_addr: contract.into_string(),
             msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::RedeemVouchers {
                 receiver,
                 redeem: self,
+                transfer,
             }))?,
             funds: vec![],
         })
@@ -52,12 +62,19 @@ impl VoucherCreation {
     ///   vouchers are being created on.
     /// - `receiver` that address that ought to receive the newly
     ///   created debt-vouchers.
-    pub(crate) fn into_wasm_msg(self, contract: Addr, receiver: String) -> StdResult<WasmMsg> {
+    /// - `transfer` the delivered transfer, emitted as receive event.
+    pub(crate) fn into_wasm_msg(
+        self,
+        contract: Addr,
+        receiver: String,
+        transfer: Option<Ics721HookTransfer>,
+    ) -> StdResult<WasmMsg> {
         Ok(WasmMsg::Execute {
             contract_addr: contract.into_string(),
             msg: to_json_binary(&ExecuteMsg::Callback(CallbackMsg::CreateVouchers {
                 receiver,
                 create: self,
+                transfer,
             }))?,
             funds: vec![],
         })
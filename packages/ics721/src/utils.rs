@@ -1,25 +1,50 @@
-use cosmwasm_std::{Addr, DepsMut, Empty, Env, StdResult};
+use bech32::FromBase32;
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Empty, Env, StdError, StdResult, Uint128,
+};
 use cw721::NumTokensResponse;
 use cw_ownable::Ownership;
+use ics721_types::token_types::{ClassId, TokenId};
 
-use crate::state::{CollectionData, UniversalCollectionInfoResponse};
+use crate::state::{
+    CollectionData, Cw2981QueryMsg, RoyaltyData, RoyaltyPaymentPolicy, TokenData,
+    UniversalCheckRoyaltiesResponse, UniversalCollectionInfoResponse,
+    UniversalRoyaltiesInfoResponse, TOKEN_METADATA,
+};
 
-pub fn get_collection_data(deps: &DepsMut, collection: &Addr) -> StdResult<CollectionData> {
+/// Sale price used for querying CW2981 royalties. Royalty amount divided
+/// by sale price is the royalty share.
+const ROYALTY_SALE_PRICE: u128 = 1_000_000;
+/// Address length of a regular wallet (derived from a public key).
+/// Contracts and multisigs have longer addresses.
+const WALLET_ADDR_LENGTH: usize = 20;
+
+/// Returns the owner (minter) of a collection.
+pub fn get_collection_owner(deps: Deps, collection: &Addr) -> StdResult<Option<String>> {
     // cw721 v0.17 and higher holds ownership in the contract
     let ownership: StdResult<Ownership<Addr>> = deps
         .querier
         .query_wasm_smart(collection, &cw721_base::msg::QueryMsg::Ownership::<Addr> {});
-    let owner = match ownership {
-        Ok(ownership) => ownership.owner.map(|a| a.to_string()),
+    match ownership {
+        Ok(ownership) => Ok(ownership.owner.map(|a| a.to_string())),
         Err(_) => {
             // cw721 v0.16 and lower holds minter
             let minter_response: cw721_base_016::msg::MinterResponse = deps
                 .querier
                 .query_wasm_smart(collection, &cw721_base_016::QueryMsg::Minter::<Empty> {})?;
             deps.api.addr_validate(&minter_response.minter)?;
-            Some(minter_response.minter)
+            Ok(Some(minter_response.minter))
         }
-    };
+    }
+}
+
+pub fn get_collection_data(deps: &DepsMut, collection: &Addr) -> StdResult<CollectionData> {
+    query_collection_data(deps.as_ref(), collection)
+}
+
+/// Same as `get_collection_data`, for queries.
+pub fn query_collection_data(deps: Deps, collection: &Addr) -> StdResult<CollectionData> {
+    let owner = get_collection_owner(deps, collection)?;
     let contract_info = deps.querier.query_wasm_contract_info(collection)?;
     let UniversalCollectionInfoResponse { name, symbol } = deps.querier.query_wasm_smart(
         collection,
@@ -39,14 +64,128 @@ pub fn get_collection_data(deps: &DepsMut, collection: &Addr) -> StdResult<Colle
     })
 }
 
+/// Returns the token data sent along with an NFT. For NFTs that have been
+/// transferred to this chain before, it is the stored token metadata.
+/// For local NFTs, it holds the CW2981 royalty (if any).
+pub fn get_token_data(
+    deps: Deps,
+    class_id: &ClassId,
+    collection: &Addr,
+    token_id: &TokenId,
+) -> StdResult<Option<Binary>> {
+    match TOKEN_METADATA.may_load(deps.storage, (class_id.clone(), token_id.clone()))? {
+        Some(token_metadata) => Ok(token_metadata),
+        None => get_royalty_data(deps, collection, &String::from(token_id.clone()))
+            .map(|royalty_info| {
+                to_json_binary(&TokenData {
+                    royalty_info: Some(royalty_info),
+                })
+            })
+            .transpose(),
+    }
+}
+
+/// Returns the royalty of an NFT, in case collection supports CW2981.
+/// Errors are ignored, since most collections don't support CW2981.
+pub fn get_royalty_data(deps: Deps, collection: &Addr, token_id: &str) -> Option<RoyaltyData> {
+    let UniversalCheckRoyaltiesResponse { royalty_payments } = deps
+        .querier
+        .query_wasm_smart(
+            collection,
+            &cw721_base::msg::QueryMsg::Extension {
+                msg: Cw2981QueryMsg::CheckRoyalties {},
+            },
+        )
+        .ok()?;
+    if !royalty_payments {
+        return None;
+    }
+    let UniversalRoyaltiesInfoResponse {
+        address,
+        royalty_amount,
+    } = deps
+        .querier
+        .query_wasm_smart(
+            collection,
+            &cw721_base::msg::QueryMsg::Extension {
+                msg: Cw2981QueryMsg::RoyaltyInfo {
+                    token_id: token_id.to_string(),
+                    sale_price: Uint128::new(ROYALTY_SALE_PRICE),
+                },
+            },
+        )
+        .ok()?;
+    if address.is_empty() || royalty_amount.is_zero() {
+        return None;
+    }
+    Some(RoyaltyData {
+        payment_address: address,
+        share: Decimal::from_ratio(royalty_amount, ROYALTY_SALE_PRICE),
+    })
+}
+
+/// Converts a royalty share into a CW2981 royalty percentage. Shares
+/// which aren't whole percents are rounded up, so no royalty is lost.
+pub fn royalty_percentage(share: Decimal) -> u64 {
+    (share.min(Decimal::one()) * Decimal::from_ratio(100u128, 1u128))
+        .to_uint_ceil()
+        .u128() as u64
+}
+
+/// Returns the local address receiving royalties for a payment address
+/// on source chain.
+pub fn resolve_royalty_payment_address(
+    deps: Deps,
+    env: &Env,
+    policy: &RoyaltyPaymentPolicy,
+    payment_address: &str,
+) -> String {
+    match policy {
+        RoyaltyPaymentPolicy::Fixed { address } => address.clone(),
+        RoyaltyPaymentPolicy::ConvertWalletAddress { fallback } => {
+            convert_wallet_address(deps, env, payment_address).unwrap_or_else(|| fallback.clone())
+        }
+    }
+}
+
+/// Converts a wallet address of the source chain (e.g. `juno1XXX`) to
+/// this chain (e.g. `stars1XXX`). Returns None for contract and multisig
+/// addresses, since there is no such account on this chain.
+pub fn convert_wallet_address(deps: Deps, env: &Env, address: &str) -> Option<String> {
+    let (_, data, variant) = bech32::decode(address).ok()?;
+    if Vec::<u8>::from_base32(&data).ok()?.len() != WALLET_ADDR_LENGTH {
+        return None;
+    }
+    let (target_hrp, _, _) = bech32::decode(env.contract.address.as_str()).ok()?;
+    let target_address = bech32::encode(&target_hrp, data, variant).ok()?;
+    deps.api.addr_validate(&target_address).ok()?;
+    Some(target_address)
+}
+
 /// Convert owner chain address (e.g. `juno1XXX`) to target owner chain address (e.g. `stars1XXX`).
 pub fn convert_owner_chain_address(env: &Env, source_owner: &str) -> StdResult<String> {
     // convert the source owner (e.g. `juno1XXX`) to target owner (e.g. `stars1XXX`)
-    let (_source_hrp, source_data, source_variant) = bech32::decode(source_owner).unwrap();
+    let (_source_hrp, source_data, source_variant) = bech32::decode(source_owner)
+        .map_err(|err| StdError::generic_err(format!("Invalid address {source_owner}: {err}")))?;
     // detect target hrp (e.g. `stars`) using contract address
-    let (target_hrp, _target_data, _target_variant) =
-        bech32::decode(env.contract.address.as_str()).unwrap();
+    let (target_hrp, _target_data, _target_variant) = bech32::decode(env.contract.address.as_str())
+        .map_err(|err| {
+            StdError::generic_err(format!(
+                "Invalid contract address {}: {err}",
+                env.contract.address
+            ))
+        })?;
     // convert source owner to target owner
-    let target_owner = bech32::encode(target_hrp.as_str(), source_data, source_variant).unwrap();
-    Ok(target_owner)
+    bech32::encode(target_hrp.as_str(), source_data, source_variant)
+        .map_err(|err| StdError::generic_err(format!("Invalid address {source_owner}: {err}")))
+}
+
+/// Translates a receiver using the bech32 prefix of the source chain
+/// (e.g. `juno1XXX`) to this chain (e.g. `stars1XXX`). Same as
+/// `convert_wallet_address`, but errors for contract, multisig and
+/// invalid addresses.
+pub fn translate_receiver(deps: Deps, env: &Env, receiver: &str) -> StdResult<String> {
+    convert_wallet_address(deps, env, receiver).ok_or_else(|| {
+        StdError::generic_err(format!("Receiver {receiver} is not a wallet address"))
+    })
 }